@@ -0,0 +1,39 @@
+// Compiled actions as a list, for programs that inspect, transform or write them again.
+
+// The action data of a compiled source, which the actions in the list borrow from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ActionList {
+    action_data: Vec<u8>,
+}
+
+impl ActionList {
+    pub(crate) fn new(action_data: Vec<u8>) -> Self {
+        Self { action_data }
+    }
+
+    pub fn action_data(&self) -> &[u8] {
+        &self.action_data
+    }
+
+    pub fn into_action_data(self) -> Vec<u8> {
+        self.action_data
+    }
+
+    // The actions in order. Those with bodies, such as `DefineFunction2` and `Try`, hold them as
+    // action data, which `decode_actions` turns into lists of their own.
+    pub fn actions(&self) -> Result<Vec<swf::avm1::types::Action<'_>>, swf::error::Error> {
+        decode_actions(&self.action_data)
+    }
+}
+
+// The actions that `action_data` encodes, in order.
+pub fn decode_actions(
+    action_data: &[u8],
+) -> Result<Vec<swf::avm1::types::Action<'_>>, swf::error::Error> {
+    let mut reader = swf::avm1::read::Reader::new(action_data, 0);
+    let mut actions = Vec::new();
+    while !reader.get_ref().is_empty() {
+        actions.push(reader.read_action()?);
+    }
+    Ok(actions)
+}
@@ -0,0 +1,575 @@
+// Instructions whose branches target symbolic labels, laid out into action data once complete.
+
+use crate::scanner::{CompileError, ErrorKind};
+use crate::source_map::{DebugInfo, FunctionSymbols, Mapping};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+// A position in the code, created by the compiler and placed with `Code::define`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Label(pub usize);
+
+pub enum Instruction {
+    // Range of encoded actions without branches in the action data of the code.
+    Actions(Range<usize>),
+    Jump(Label),
+    If(Label),
+    Label(Label),
+    // The end of the preceding actions, which never continue to the next instruction, such as
+    // `Throw`.
+    Exit,
+    // The source position of the statement that the following instructions come from.
+    Statement { line: usize, column: usize },
+    // Range of an encoded `DefineFunction` or `DefineFunction2` action without its body, which
+    // follows up to the matching `EndFunction`. Its code size is filled in once laid out.
+    Function(Range<usize>),
+    EndFunction,
+    // A change in the count of the names that `Enumerate2` left on the stack, which branches on
+    // them imply. Only the check of the stack follows it.
+    Enumerated(isize),
+}
+
+impl Instruction {
+    // Size of a `Jump` or `If` action: code, length and offset.
+    const BRANCH_SIZE: usize = 5;
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Actions(range) | Self::Function(range) => range.len(),
+            Self::Jump(_) | Self::If(_) => Self::BRANCH_SIZE,
+            Self::Label(_)
+            | Self::Exit
+            | Self::Statement { .. }
+            | Self::EndFunction
+            | Self::Enumerated(_) => 0,
+        }
+    }
+}
+
+// The start of a function body, whose definition is written once the body is complete.
+#[derive(Clone, Copy)]
+pub struct FunctionStart(usize);
+
+#[derive(Default)]
+pub struct Code {
+    // The encoded actions of all instructions, which the writer appends to for the whole code.
+    action_data: Vec<u8>,
+    instructions: Vec<Instruction>,
+    // Offset of the `Push` action that ends the code, which a following `Push` is merged into.
+    last_push: Option<usize>,
+    // Whether to leave the actions as written, without merging `Push` actions or threading jumps.
+    plain: bool,
+    // Source positions and function symbols of embedded actions that were assembled on their own,
+    // such as the bodies of try blocks, and the symbols of the functions of the code, by their
+    // offsets in its action data.
+    debug: DebugInfo,
+}
+
+impl Code {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Code that is laid out as written, without peephole optimizations.
+    pub fn plain() -> Self {
+        Self {
+            plain: true,
+            ..Self::default()
+        }
+    }
+
+    // Extend the actions at the end of the code up to the end of the action data, from `start`
+    // where they were appended.
+    fn extend_range(&mut self, start: usize) {
+        let end = self.action_data.len();
+        match self.instructions.last_mut() {
+            Some(Instruction::Actions(range)) if range.end == start => range.end = end,
+            _ => self.instructions.push(Instruction::Actions(start..end)),
+        }
+    }
+
+    fn instruction(&mut self, instruction: Instruction) {
+        self.last_push = None;
+        self.instructions.push(instruction);
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn action_data(&self) -> &[u8] {
+        &self.action_data
+    }
+
+    // The source position of the statement that the instruction at `index` comes from, if any.
+    fn statement_at(&self, index: usize) -> (usize, usize) {
+        self.instructions[..index]
+            .iter()
+            .rev()
+            .find_map(|instruction| match instruction {
+                Instruction::Statement { line, column } => Some((*line, *column)),
+                _ => None,
+            })
+            .unwrap_or((0, 0))
+    }
+
+    // An error about too much code, at the statement that the instruction at `index` comes from.
+    fn error_at(&self, index: usize, message: &str) -> CompileError {
+        let (line, column) = self.statement_at(index);
+        CompileError {
+            kind: ErrorKind::TooLarge,
+            message: message.to_string(),
+            line,
+            column,
+        }
+    }
+
+    // The source position of the statement that the end of the code comes from, if any.
+    pub fn position(&self) -> (usize, usize) {
+        self.statement_at(self.instructions.len())
+    }
+
+    pub fn write_action(&mut self, action: &swf::avm1::types::Action) {
+        let last_push = self.last_push.take();
+        let start = self.action_data.len();
+        let mut writer = swf::avm1::write::Writer::new(&mut self.action_data, 0);
+        writer.write_action(action).unwrap();
+        if matches!(action, swf::avm1::types::Action::Push(_)) && !self.plain {
+            self.last_push = Some(merge_push(&mut self.action_data, last_push, start));
+        }
+        self.extend_range(start);
+        match action {
+            swf::avm1::types::Action::Return | swf::avm1::types::Action::Throw => {
+                self.instruction(Instruction::Exit);
+            }
+            _ => {}
+        }
+    }
+
+    // Append already encoded actions, which must not branch outside of themselves.
+    pub fn extend_actions(&mut self, action_data: &[u8]) {
+        self.last_push = None;
+        let start = self.action_data.len();
+        self.action_data.extend_from_slice(action_data);
+        self.extend_range(start);
+    }
+
+    // Append already encoded actions like `extend_actions`, which were assembled with `debug`.
+    pub fn extend_mapped(&mut self, action_data: &[u8], debug: &DebugInfo) {
+        self.extend_actions(action_data);
+        self.map_embedded(action_data.len(), debug);
+    }
+
+    // Map the actions that end the action data written so far, `length` bytes of actions that were
+    // assembled with `debug`. A `Try` action ends with the bodies of its blocks.
+    pub fn map_embedded(&mut self, length: usize, debug: &DebugInfo) {
+        let start = self.action_data.len() - length;
+        self.debug.extend_moved(debug, &(0..length), start);
+    }
+
+    // Describe the function started at `start` and ended since, for debuggers. Its offset and body
+    // are filled in once laid out.
+    pub fn describe_function(&mut self, start: FunctionStart, symbols: FunctionSymbols) {
+        let Instruction::Function(range) = &self.instructions[start.0 - 1] else {
+            unreachable!();
+        };
+        self.debug.functions.push(FunctionSymbols {
+            offset: range.start,
+            body: range.start..range.start,
+            ..symbols
+        });
+    }
+
+    pub fn jump(&mut self, target: Label) {
+        self.instruction(Instruction::Jump(target));
+    }
+
+    // Branch to `target` if the value on top of the stack is truthy.
+    pub fn branch(&mut self, target: Label) {
+        self.instruction(Instruction::If(target));
+    }
+
+    // Place `label` at the current end of the code.
+    pub fn define(&mut self, label: Label) {
+        self.instruction(Instruction::Label(label));
+    }
+
+    // Mark the start of a statement at the given source position.
+    pub fn statement(&mut self, line: usize, column: usize) {
+        self.instruction(Instruction::Statement { line, column });
+    }
+
+    // Note that the enumeration on top of the stack has `count` more names, as a branch implies.
+    pub fn enumerated(&mut self, count: isize) {
+        self.instruction(Instruction::Enumerated(count));
+    }
+
+    pub fn append(&mut self, mut code: Code) {
+        for instruction in std::mem::take(&mut code.instructions) {
+            match instruction {
+                Instruction::Actions(range) => {
+                    let start = self.action_data.len();
+                    self.extend_actions(&code.action_data[range.clone()]);
+                    self.debug.extend_moved(&code.debug, &range, start);
+                }
+                Instruction::Function(range) => {
+                    let start = self.action_data.len();
+                    self.action_data
+                        .extend_from_slice(&code.action_data[range.clone()]);
+                    self.debug.extend_moved(&code.debug, &range, start);
+                    self.instruction(Instruction::Function(start..start + range.len()));
+                }
+                instruction => self.instruction(instruction),
+            }
+        }
+    }
+
+    // Start a function body, to compile in place.
+    pub fn start_function(&mut self) -> FunctionStart {
+        self.instruction(Instruction::Function(0..0));
+        FunctionStart(self.instructions.len())
+    }
+
+    // Insert `code` at the start of the function body started at `start`.
+    pub fn prepend_function(&mut self, start: FunctionStart, code: Code) {
+        // The body keeps its actions in place, and only its instructions move.
+        let last_push = self.last_push;
+        let body = self.instructions.split_off(start.0);
+        self.append(code);
+        self.instructions.extend(body);
+        self.last_push = last_push;
+    }
+
+    // End the function body started at `start`, defined by `action` without its body.
+    pub fn end_function(&mut self, start: FunctionStart, action: &swf::avm1::types::Action) {
+        let header_start = self.action_data.len();
+        let mut writer = swf::avm1::write::Writer::new(&mut self.action_data, 0);
+        writer.write_action(action).unwrap();
+        self.instructions[start.0 - 1] =
+            Instruction::Function(header_start..self.action_data.len());
+        self.instruction(Instruction::EndFunction);
+    }
+
+    // Retarget branches to labels that are followed by a `Jump` to the target of that jump, and
+    // drop branches that execution would reach the target of anyway, and jumps that it never
+    // reaches.
+    fn thread(&mut self) {
+        let definitions: HashMap<Label, usize> = self
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, instruction)| match instruction {
+                Instruction::Label(label) => Some((*label, i)),
+                _ => None,
+            })
+            .collect();
+        let is_marker = |instruction: &Instruction| {
+            matches!(
+                instruction,
+                Instruction::Label(_) | Instruction::Statement { .. } | Instruction::Enumerated(_)
+            )
+        };
+
+        // Whether the branch at `i` targets a label that only markers separate it from.
+        let is_next = |instructions: &[Instruction], i: usize| {
+            let (Instruction::Jump(target) | Instruction::If(target)) = &instructions[i] else {
+                return false;
+            };
+            instructions[i + 1..]
+                .iter()
+                .take_while(|instruction| is_marker(instruction))
+                .any(|instruction| matches!(instruction, Instruction::Label(label) if label == target))
+        };
+
+        // Follow the chain of jumps from `label`, stopping at a cycle.
+        let destination = |mut label: Label| {
+            let mut visited = HashSet::new();
+            while visited.insert(label) {
+                let next = self.instructions[definitions[&label]..]
+                    .iter()
+                    .find(|instruction| !is_marker(instruction));
+                match next {
+                    Some(Instruction::Jump(target)) => label = *target,
+                    _ => break,
+                }
+            }
+            label
+        };
+        let targets: Vec<_> = self
+            .instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::Jump(target) | Instruction::If(target) => Some(destination(*target)),
+                _ => None,
+            })
+            .collect();
+        for (instruction, destination) in self.instructions.iter_mut().zip(targets) {
+            if let (Instruction::Jump(target) | Instruction::If(target), Some(destination)) =
+                (instruction, destination)
+            {
+                *target = destination;
+            }
+        }
+
+        let is_next_jump: Vec<_> = (0..self.instructions.len())
+            .map(|i| {
+                matches!(self.instructions[i], Instruction::Jump(_))
+                    && is_next(&self.instructions, i)
+            })
+            .collect();
+        let instructions = std::mem::take(&mut self.instructions);
+        for (instruction, is_next_jump) in instructions.into_iter().zip(is_next_jump) {
+            if let Instruction::Jump(_) = instruction {
+                let is_dead = matches!(
+                    self.instructions
+                        .iter()
+                        .rfind(|instruction| !matches!(instruction, Instruction::Statement { .. })),
+                    Some(Instruction::Jump(_) | Instruction::Exit)
+                );
+                if is_dead || is_next_jump {
+                    continue;
+                }
+            }
+            self.instructions.push(instruction);
+        }
+
+        // Once the jumps are dropped, an `If` to the next instruction only needs to pop the condition.
+        for i in 0..self.instructions.len() {
+            if let Instruction::If(_) = self.instructions[i] {
+                if is_next(&self.instructions, i) {
+                    let start = self.action_data.len();
+                    let mut writer = swf::avm1::write::Writer::new(&mut self.action_data, 0);
+                    writer.write_action(&swf::avm1::types::Action::Pop).unwrap();
+                    self.instructions[i] = Instruction::Actions(start..self.action_data.len());
+                }
+            }
+        }
+    }
+
+    // Positions of the instructions, followed by the end of the code, and of the labels.
+    fn layout(&self) -> (Vec<usize>, HashMap<Label, usize>) {
+        let mut positions = Vec::with_capacity(self.instructions.len() + 1);
+        let mut labels = HashMap::new();
+        let mut position = 0;
+        for instruction in &self.instructions {
+            positions.push(position);
+            if let Instruction::Label(label) = instruction {
+                labels.insert(*label, position);
+            }
+            position += instruction.size();
+        }
+        positions.push(position);
+        (positions, labels)
+    }
+
+    // Route each branch whose target is out of the range of its 16-bit offset through a jump
+    // placed in between, which may in turn be routed the same way.
+    fn relax(&mut self) -> Result<(), CompileError> {
+        // Size of the jump to the target, plus that of a jump over it.
+        const ISLAND_SIZE: usize = 2 * Instruction::BRANCH_SIZE;
+
+        loop {
+            let (positions, labels) = self.layout();
+
+            // The function that encloses each instruction boundary, which branches cannot leave.
+            let mut functions = Vec::new();
+            let mut regions = vec![None];
+            for (i, instruction) in self.instructions.iter().enumerate() {
+                match instruction {
+                    Instruction::Function(_) => functions.push(i),
+                    Instruction::EndFunction => {
+                        functions.pop();
+                    }
+                    _ => {}
+                }
+                regions.push(functions.last().copied());
+            }
+
+            let far_branch = self
+                .instructions
+                .iter()
+                .enumerate()
+                .find_map(|(i, instruction)| {
+                    let (Instruction::Jump(target) | Instruction::If(target)) = instruction else {
+                        return None;
+                    };
+                    let end = positions[i] + Instruction::BRANCH_SIZE;
+                    let offset = labels[target] as isize - end as isize;
+                    i16::try_from(offset)
+                        .is_err()
+                        .then_some((i, *target, offset > 0))
+                });
+            let Some((branch, target, forward)) = far_branch else {
+                return Ok(());
+            };
+
+            // Place the jump at the instruction boundary that is closest to the target while still
+            // in range of the branch, even once the jump is inserted.
+            let end = positions[branch] + Instruction::BRANCH_SIZE;
+            let index = if forward {
+                (branch + 1..=self.instructions.len())
+                    .rev()
+                    .find(|&i| {
+                        regions[i] == regions[branch]
+                            && positions[i] - end <= i16::MAX as usize - ISLAND_SIZE
+                    })
+                    .filter(|&i| positions[i] > end)
+            } else {
+                (0..=branch)
+                    .find(|&i| {
+                        regions[i] == regions[branch]
+                            && end - positions[i] <= i16::MAX as usize - ISLAND_SIZE
+                    })
+                    .filter(|&i| positions[i] < positions[branch])
+            };
+            let Some(index) = index else {
+                return Err(self.error_at(branch, "Too much code to branch across"));
+            };
+
+            let next_label = self
+                .instructions
+                .iter()
+                .filter_map(|instruction| match instruction {
+                    Instruction::Jump(label)
+                    | Instruction::If(label)
+                    | Instruction::Label(label) => Some(label.0 + 1),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0);
+            let island = Label(next_label);
+            let over = Label(next_label + 1);
+
+            // Execution only needs to be routed around the jump if it can reach it.
+            let falls_through = index > 0
+                && !matches!(
+                    self.instructions[index - 1],
+                    Instruction::Jump(_) | Instruction::Exit
+                );
+            let mut instructions = vec![Instruction::Label(island), Instruction::Jump(target)];
+            if falls_through {
+                instructions.insert(0, Instruction::Jump(over));
+                instructions.push(Instruction::Label(over));
+            }
+            self.instructions.splice(index..index, instructions);
+
+            let branch = if index <= branch {
+                branch + self.instructions.len() - positions.len() + 1
+            } else {
+                branch
+            };
+            match &mut self.instructions[branch] {
+                Instruction::Jump(target) | Instruction::If(target) => *target = island,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    // Resolve the labels and encode the branches. Every branch target must be defined. Fails if
+    // the code exceeds the 16-bit sizes and offsets of actions.
+    pub fn assemble(self) -> Result<Vec<u8>, CompileError> {
+        self.assemble_mapped().map(|(action_data, _)| action_data)
+    }
+
+    // Assemble like `assemble`, also mapping the offsets of the actions to the source positions
+    // of the statements that they come from, and describing the functions that they define.
+    pub fn assemble_mapped(mut self) -> Result<(Vec<u8>, DebugInfo), CompileError> {
+        if !self.plain {
+            self.thread();
+        }
+        self.relax()?;
+        let (positions, labels) = self.layout();
+        let position = positions[positions.len() - 1];
+
+        let mut action_data = Vec::with_capacity(position);
+        // Ends of the definitions of the enclosing functions, whose code sizes come last, along
+        // with their indices and those of their symbols.
+        let mut functions = Vec::new();
+        let mut debug = DebugInfo::default();
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            let offset = |action_data: &Vec<u8>, target: &Label| {
+                let end = action_data.len() + Instruction::BRANCH_SIZE;
+                let offset = labels[target] as isize - end as isize;
+                i16::try_from(offset).unwrap()
+            };
+            let action = match instruction {
+                Instruction::Actions(range) => {
+                    debug.extend_moved(&self.debug, range, action_data.len());
+                    action_data.extend_from_slice(&self.action_data[range.clone()]);
+                    continue;
+                }
+                Instruction::Function(range) => {
+                    let symbols = self
+                        .debug
+                        .functions
+                        .iter()
+                        .find(|function| function.offset == range.start)
+                        .map(|function| {
+                            debug.functions.push(FunctionSymbols {
+                                offset: action_data.len(),
+                                ..function.clone()
+                            });
+                            debug.functions.len() - 1
+                        });
+                    action_data.extend_from_slice(&self.action_data[range.clone()]);
+                    functions.push((action_data.len(), i, symbols));
+                    continue;
+                }
+                Instruction::EndFunction => {
+                    let (end, function, symbols) = functions.pop().unwrap();
+                    let Ok(size) = u16::try_from(action_data.len() - end) else {
+                        return Err(self.error_at(function, "Function body too large"));
+                    };
+                    action_data[end - 2..end].copy_from_slice(&size.to_le_bytes());
+                    if let Some(symbols) = symbols {
+                        debug.functions[symbols].body = end..action_data.len();
+                    }
+                    continue;
+                }
+                Instruction::Statement { line, column } => {
+                    debug.map(Mapping {
+                        offset: action_data.len(),
+                        file: None,
+                        line: *line,
+                        column: *column,
+                    });
+                    continue;
+                }
+                Instruction::Label(_) | Instruction::Exit | Instruction::Enumerated(_) => continue,
+                Instruction::Jump(target) => {
+                    swf::avm1::types::Action::Jump(swf::avm1::types::Jump {
+                        offset: offset(&action_data, target),
+                    })
+                }
+                Instruction::If(target) => swf::avm1::types::Action::If(swf::avm1::types::If {
+                    offset: offset(&action_data, target),
+                }),
+            };
+            let mut writer = swf::avm1::write::Writer::new(&mut action_data, 0);
+            writer.write_action(&action).unwrap();
+        }
+        Ok((action_data, debug))
+    }
+}
+
+// Merge the `Push` action at `start`, which ends `action_data`, into the `Push` action right before
+// it at `previous`, if any and if the values fit. Returns the offset of the resulting action.
+fn merge_push(action_data: &mut Vec<u8>, previous: Option<usize>, start: usize) -> usize {
+    // Action code and length.
+    const HEADER_SIZE: usize = 3;
+
+    let Some(previous) = previous else {
+        return start;
+    };
+    let length = u16::from_le_bytes([action_data[previous + 1], action_data[previous + 2]]);
+    let values = action_data.len() - start - HEADER_SIZE;
+    let Some(length) = usize::from(length)
+        .checked_add(values)
+        .and_then(|length| u16::try_from(length).ok())
+    else {
+        return start;
+    };
+    action_data.drain(start..start + HEADER_SIZE);
+    action_data[previous + 1..previous + HEADER_SIZE].copy_from_slice(&length.to_le_bytes());
+    previous
+}
@@ -0,0 +1,362 @@
+// The syntax tree of a source file, built by the parser and compiled by the code generator.
+//
+// Nodes keep the tokens they were parsed from, so that later passes can report errors at the
+// right location.
+
+use crate::preprocessor::Defines;
+use crate::scanner::Token;
+
+#[derive(Clone)]
+pub struct Program<'a> {
+    pub items: Vec<Item<'a>>,
+}
+
+#[derive(Clone)]
+pub enum Item<'a> {
+    Statement(Statement<'a>),
+    // `on(...) { ... }` or `onClipEvent(...) { ... }`.
+    Handler(Handler<'a>),
+    // `#initclip` ... `#endinitclip`.
+    InitClip(InitClip<'a>),
+    // `#frame`, which starts a frame of the main timeline.
+    Frame(Frame<'a>),
+    // `#sprite` ... `#endsprite`.
+    Sprite(Sprite<'a>),
+    // `#export`, which exports a character under a linkage name.
+    Export(Export<'a>),
+}
+
+#[derive(Clone)]
+pub struct Handler<'a> {
+    // `on` or `onClipEvent`.
+    pub keyword: Token<'a>,
+    pub events: Vec<Event<'a>>,
+    pub body: Vec<Statement<'a>>,
+}
+
+#[derive(Clone)]
+pub enum Event<'a> {
+    Name(Token<'a>),
+    // `keyPress "<Key>"`, along with the key string.
+    KeyPress(Token<'a>),
+}
+
+#[derive(Clone)]
+pub struct InitClip<'a> {
+    pub order: u32,
+    pub body: Vec<Statement<'a>>,
+}
+
+// `#frame`, `#frame 2`, `#frame "label"` or `#frame 2 "label"`, which starts the frame with the
+// number, or else the next one, with the label if any.
+#[derive(Clone)]
+pub struct Frame<'a> {
+    pub directive: Token<'a>,
+    pub number: Option<Token<'a>>,
+    pub label: Option<Token<'a>>,
+}
+
+// `#sprite "name"` ... `#endsprite`, a sprite placed on the main timeline with the body as the
+// actions of its frame, and the name as its instance name if any.
+#[derive(Clone)]
+pub struct Sprite<'a> {
+    pub directive: Token<'a>,
+    pub name: Option<Token<'a>>,
+    pub body: Vec<Statement<'a>>,
+}
+
+// `#export MySymbol 3` or `#export "my.Symbol" 3`, which exports the character with the id under
+// the linkage name, for `attachMovie` and class registration.
+#[derive(Clone)]
+pub struct Export<'a> {
+    // An identifier or a string.
+    pub name: Token<'a>,
+    pub id: u16,
+}
+
+#[derive(Clone)]
+pub enum Statement<'a> {
+    Empty,
+    Expression(Expression<'a>),
+    Block(Vec<Statement<'a>>),
+    Var {
+        name: Token<'a>,
+        value: Option<Expression<'a>>,
+    },
+    // `const NAME = value;`, where `value` is a literal, possibly signed, or another constant.
+    Const {
+        name: Token<'a>,
+        value: Expression<'a>,
+    },
+    Function(Function<'a>),
+    If {
+        keyword: Token<'a>,
+        condition: Expression<'a>,
+        body: Box<Statement<'a>>,
+        else_body: Option<Box<Statement<'a>>>,
+    },
+    // `for ([var] variable in object) body`
+    ForIn {
+        is_declaration: bool,
+        variable: Token<'a>,
+        object: Expression<'a>,
+        body: Box<Statement<'a>>,
+    },
+    While {
+        keyword: Token<'a>,
+        condition: Expression<'a>,
+        body: Box<Statement<'a>>,
+    },
+    DoWhile {
+        keyword: Token<'a>,
+        body: Box<Statement<'a>>,
+        condition: Expression<'a>,
+    },
+    Break {
+        keyword: Token<'a>,
+        label: Option<Token<'a>>,
+    },
+    Continue {
+        keyword: Token<'a>,
+        label: Option<Token<'a>>,
+    },
+    // A loop preceded by `label:`.
+    Labeled {
+        label: Token<'a>,
+        body: Box<Statement<'a>>,
+    },
+    Try {
+        keyword: Token<'a>,
+        body: Vec<Statement<'a>>,
+        catch: Option<Catch<'a>>,
+        finally: Option<Vec<Statement<'a>>>,
+    },
+    // `throw value;`, or `throw;` to rethrow the exception of the enclosing catch block.
+    Throw {
+        keyword: Token<'a>,
+        value: Option<Expression<'a>>,
+    },
+    TellTarget {
+        keyword: Token<'a>,
+        target: Expression<'a>,
+        body: Box<Statement<'a>>,
+    },
+    Trace {
+        keyword: Token<'a>,
+        arguments: Vec<Expression<'a>>,
+    },
+    Class(Class<'a>),
+    Intrinsic(Intrinsic<'a>),
+    Import(Import<'a>),
+    // A declaration preceded by a constant such as `CONFIG::DEBUG`.
+    Conditional {
+        namespace: Token<'a>,
+        name: Token<'a>,
+        body: Box<Statement<'a>>,
+    },
+}
+
+impl<'a> Statement<'a> {
+    // The first token of the code of the statement, where warnings about it are reported.
+    pub fn token(&self) -> Option<&Token<'a>> {
+        match self {
+            Self::Empty | Self::Intrinsic(_) => None,
+            Self::Expression(expression) => Some(expression.token()),
+            Self::Block(statements) => statements.iter().find_map(Statement::token),
+            Self::Var { name, .. } | Self::Const { name, .. } => Some(name),
+            Self::Function(function) => Some(&function.keyword),
+            Self::ForIn { variable, .. } => Some(variable),
+            Self::If { keyword, .. }
+            | Self::While { keyword, .. }
+            | Self::DoWhile { keyword, .. }
+            | Self::Break { keyword, .. }
+            | Self::Continue { keyword, .. }
+            | Self::Try { keyword, .. }
+            | Self::Throw { keyword, .. }
+            | Self::TellTarget { keyword, .. }
+            | Self::Trace { keyword, .. } => Some(keyword),
+            Self::Labeled { label, .. } => Some(label),
+            Self::Class(class) => class.path.first(),
+            Self::Import(import) => Some(&import.keyword),
+            Self::Conditional { namespace, .. } => Some(namespace),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Catch<'a> {
+    pub variable: Token<'a>,
+    pub body: Vec<Statement<'a>>,
+}
+
+#[derive(Clone)]
+pub struct Function<'a> {
+    pub keyword: Token<'a>,
+    pub name: Option<Token<'a>>,
+    pub parameters: Vec<Token<'a>>,
+    pub body: Vec<Statement<'a>>,
+}
+
+#[derive(Clone)]
+pub struct Class<'a> {
+    pub path: Vec<Token<'a>>,
+    pub superclass: Option<Vec<Token<'a>>>,
+    pub members: Vec<ClassMember<'a>>,
+}
+
+#[derive(Clone)]
+pub struct ClassMember<'a> {
+    pub is_static: bool,
+    pub is_private: bool,
+    pub kind: ClassMemberKind<'a>,
+}
+
+#[derive(Clone)]
+pub enum ClassMemberKind<'a> {
+    Var {
+        name: Token<'a>,
+        value: Option<Expression<'a>>,
+    },
+    // A method, or a property accessor if preceded by `get` or `set`.
+    Method {
+        accessor: Option<Token<'a>>,
+        function: Function<'a>,
+    },
+}
+
+impl<'a> ClassMember<'a> {
+    pub fn name(&self) -> &Token<'a> {
+        match &self.kind {
+            ClassMemberKind::Var { name, .. } => name,
+            ClassMemberKind::Method { function, .. } => function.name.as_ref().unwrap(),
+        }
+    }
+}
+
+// An `intrinsic class`, declaring the members of a class implemented by the player.
+#[derive(Clone)]
+pub struct Intrinsic<'a> {
+    pub path: Vec<Token<'a>>,
+    pub superclass: Option<Vec<Token<'a>>>,
+    pub members: Vec<IntrinsicMember<'a>>,
+}
+
+#[derive(Clone)]
+pub struct IntrinsicMember<'a> {
+    pub name: Token<'a>,
+    pub is_static: bool,
+    pub is_private: bool,
+}
+
+#[derive(Clone)]
+pub struct Import<'a> {
+    pub keyword: Token<'a>,
+    pub path: Vec<Token<'a>>,
+    // The preprocessor definitions in effect, which the imported file inherits.
+    pub(crate) defines: Defines<'a>,
+}
+
+#[derive(Clone)]
+pub enum Expression<'a> {
+    // A number, string, `true`, `false`, `null` or `undefined`.
+    Literal(Token<'a>),
+    Identifier(Token<'a>),
+    // A conditional compilation constant such as `CONFIG::DEBUG`.
+    Constant {
+        namespace: Token<'a>,
+        name: Token<'a>,
+    },
+    Grouping {
+        paren: Token<'a>,
+        expression: Box<Expression<'a>>,
+    },
+    Array {
+        bracket: Token<'a>,
+        elements: Vec<Expression<'a>>,
+    },
+    Object {
+        brace: Token<'a>,
+        properties: Vec<(Token<'a>, Expression<'a>)>,
+    },
+    Function(Box<Function<'a>>),
+    Member {
+        object: Box<Expression<'a>>,
+        name: Token<'a>,
+    },
+    Index {
+        object: Box<Expression<'a>>,
+        index: Box<Expression<'a>>,
+    },
+    Call {
+        callee: Box<Expression<'a>>,
+        arguments: Vec<Expression<'a>>,
+        // The closing parenthesis.
+        end: Token<'a>,
+    },
+    New {
+        keyword: Token<'a>,
+        callee: Box<Expression<'a>>,
+        arguments: Option<Vec<Expression<'a>>>,
+    },
+    Delete {
+        keyword: Token<'a>,
+        target: Box<Expression<'a>>,
+    },
+    Unary {
+        operator: Token<'a>,
+        operand: Box<Expression<'a>>,
+    },
+    Binary {
+        operator: Token<'a>,
+        left: Box<Expression<'a>>,
+        right: Box<Expression<'a>>,
+    },
+    // An assignment, possibly compound such as `+=`.
+    Assign {
+        operator: Token<'a>,
+        target: Box<Expression<'a>>,
+        value: Box<Expression<'a>>,
+    },
+    // `++name` or `--name`.
+    Prefix {
+        operator: Token<'a>,
+        name: Token<'a>,
+    },
+    // `target++` or `target--`.
+    Postfix {
+        operator: Token<'a>,
+        target: Box<Expression<'a>>,
+    },
+}
+
+impl<'a> Expression<'a> {
+    // The first token of the expression, where errors about it are reported.
+    pub fn token(&self) -> &Token<'a> {
+        match self {
+            Self::Literal(token) | Self::Identifier(token) => token,
+            Self::Constant { namespace, .. } => namespace,
+            Self::Grouping { paren, .. } => paren,
+            Self::Array { bracket, .. } => bracket,
+            Self::Object { brace, .. } => brace,
+            Self::Function(function) => &function.keyword,
+            Self::Member { object, .. }
+            | Self::Index { object, .. }
+            | Self::Call { callee: object, .. } => object.token(),
+            Self::New { keyword, .. } | Self::Delete { keyword, .. } => keyword,
+            Self::Unary { operator, .. } | Self::Prefix { operator, .. } => operator,
+            Self::Binary { left, .. } => left.token(),
+            Self::Assign { target, .. } | Self::Postfix { target, .. } => target.token(),
+        }
+    }
+
+    // Whether the expression can be assigned to, such as `a`, `a.b`, `a[b]` or `eval(a)`.
+    pub fn is_assignable(&self) -> bool {
+        match self {
+            Self::Identifier(_) | Self::Member { .. } | Self::Index { .. } => true,
+            Self::Call { callee, .. } => {
+                matches!(&**callee, Self::Identifier(token) if token.source == "eval")
+            }
+            _ => false,
+        }
+    }
+}
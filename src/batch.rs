@@ -0,0 +1,56 @@
+// Compilation of many independent sources at once, on as many threads as there are cores.
+
+use crate::compiler::{compile_with_options, CompileOptions};
+use crate::diagnostic::Diagnostic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Stack size of the compiling threads, that of the main thread on most platforms, which the
+// default maximum nesting depth is chosen for.
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// A SWF file compiled from a source, along with the warnings about the source.
+pub struct CompiledSwf {
+    pub swf: Vec<u8>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+// Compile each of `sources` with its options into a SWF file. Each source imports its classes on
+// its own. The results are in the order of `sources`, whichever finishes first.
+pub fn compile_parallel(
+    sources: &[(&str, CompileOptions)],
+) -> Vec<Result<CompiledSwf, Vec<Diagnostic>>> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(sources.len());
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<_> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                std::thread::Builder::new()
+                    .stack_size(STACK_SIZE)
+                    .spawn_scoped(scope, || {
+                        let mut results = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((source, options)) = sources.get(index) else {
+                                break;
+                            };
+                            let mut swf = Vec::new();
+                            let result = compile_with_options(source, options, &mut swf)
+                                .map(|warnings| CompiledSwf { swf, warnings });
+                            results.push((index, result));
+                        }
+                        results
+                    })
+                    .unwrap()
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
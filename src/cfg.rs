@@ -0,0 +1,127 @@
+// The control-flow graph of code, made of basic blocks connected by branches.
+
+use crate::assembler::{Code, Instruction};
+use std::collections::HashMap;
+
+// A run of instructions that is only entered at its start and only left at its end.
+pub struct Block {
+    // Range of the instructions of the block in the code.
+    pub start: usize,
+    pub end: usize,
+    // Indices of the blocks that execution may continue to.
+    pub successors: Vec<usize>,
+}
+
+pub struct Cfg<'c> {
+    code: &'c Code,
+    pub blocks: Vec<Block>,
+}
+
+impl<'c> Cfg<'c> {
+    pub fn new(code: &'c Code) -> Self {
+        let instructions = code.instructions();
+
+        // A block starts at each label and after each instruction that leaves the block. Function
+        // definitions continue both into their bodies and after them.
+        let mut starts = vec![0];
+        let mut functions = Vec::new();
+        let mut function_ends = HashMap::new();
+        for (i, instruction) in instructions.iter().enumerate() {
+            match instruction {
+                Instruction::Label(_) => starts.push(i),
+                Instruction::Jump(_) | Instruction::If(_) | Instruction::Exit => starts.push(i + 1),
+                Instruction::Function(_) => {
+                    functions.push(i);
+                    starts.push(i + 1);
+                }
+                Instruction::EndFunction => {
+                    function_ends.insert(functions.pop().unwrap(), i);
+                    starts.push(i);
+                }
+                _ => {}
+            }
+        }
+        starts.push(instructions.len());
+        starts.dedup();
+
+        let mut blocks: Vec<_> = starts
+            .windows(2)
+            .map(|range| Block {
+                start: range[0],
+                end: range[1],
+                successors: Vec::new(),
+            })
+            .collect();
+
+        let block_starts: HashMap<_, _> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| (block.start, i))
+            .collect();
+        let labels: HashMap<_, _> = blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, block)| match instructions[block.start] {
+                Instruction::Label(label) => Some((label, i)),
+                _ => None,
+            })
+            .collect();
+
+        let count = blocks.len();
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let next = (i + 1 < count).then_some(i + 1);
+            block.successors = match &instructions[block.end - 1] {
+                Instruction::Jump(target) => vec![labels[target]],
+                Instruction::If(target) => {
+                    [Some(labels[target]), next].into_iter().flatten().collect()
+                }
+                Instruction::Exit => Vec::new(),
+                Instruction::Function(_) => {
+                    let end = block_starts[&function_ends[&(block.end - 1)]];
+                    next.into_iter().chain([end]).collect()
+                }
+                _ => next.into_iter().collect(),
+            };
+        }
+
+        Self { code, blocks }
+    }
+
+    // Whether each block can be reached from the start of the code.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = Vec::new();
+        if !self.blocks.is_empty() {
+            pending.push(0);
+        }
+        while let Some(i) = pending.pop() {
+            if std::mem::replace(&mut reachable[i], true) {
+                continue;
+            }
+            pending.extend(&self.blocks[i].successors);
+        }
+        reachable
+    }
+
+    // Source positions of the first statement of each unreachable part of the code.
+    pub fn unreachable_statements(&self) -> Vec<(usize, usize)> {
+        let instructions = self.code.instructions();
+        let mut statements = Vec::new();
+        let mut reported = false;
+        for (block, reachable) in self.blocks.iter().zip(self.reachable()) {
+            if reachable {
+                reported = false;
+                continue;
+            }
+            for instruction in &instructions[block.start..block.end] {
+                if let Instruction::Statement { line, column } = instruction {
+                    if !reported {
+                        statements.push((*line, *column));
+                        reported = true;
+                    }
+                }
+            }
+        }
+        statements
+    }
+}
@@ -1,306 +1,1057 @@
-use crate::scanner::{CompileError, Scanner, Token, TokenKind};
+use crate::action_list::ActionList;
+use crate::assembler::{Code, Label};
+use crate::ast::{
+    self, Catch, ClassMemberKind, Event, Expression, Function, Import, Intrinsic, Item, Program,
+    Statement,
+};
+use crate::cfg::Cfg;
+use crate::diagnostic::{Diagnostic, Lint, LintOptions, Severity};
+use crate::interner::{is_path, Interner};
+use crate::md5;
+use crate::parser::{Parser, DEFAULT_MAX_DEPTH};
+use crate::preprocessor::Preprocessor;
+use crate::scanner::{CompileError, ErrorKind, Token, TokenKind};
+use crate::scope::Scope;
+use crate::signatures;
+use crate::source::SourceText;
+use crate::source_map::{ActionBlock, DebugInfo, FunctionSymbols, SourceMap};
+use crate::stack;
+use crate::stats::CompileStats;
+use crate::suggest;
+use crate::suppress::suppress;
+use crate::timing::Timings;
+use crate::visit::VisitMut;
+use std::rc::Rc;
+use std::time::Instant;
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
-enum Precedence {
-    None,
-    Assignment,
-    // Or,
-    // And,
-    BitwiseOr,
-    BitwiseXor,
-    BitwiseAnd,
-    Equality,
-    Comparison,
-    BitwiseShift,
-    Term,
-    Factor,
-    Unary,
-    Call,
-    Construct,
-    Delete,
-    Path,
-    #[allow(dead_code)]
-    Primary,
+// The properties of movie clips, in the order of their indices.
+const PROPERTIES: &[&str] = &[
+    "_x",
+    "_y",
+    "_xscale",
+    "_yscale",
+    "_currentframe",
+    "_totalframes",
+    "_alpha",
+    "_visible",
+    "_width",
+    "_height",
+    "_rotation",
+    "_target",
+    "_framesloaded",
+    "_name",
+    "_droptarget",
+    "_url",
+    "_highquality",
+    "_focusrect",
+    "_soundbuftime",
+    "_quality",
+    "_xmouse",
+    "_ymouse",
+];
+
+fn property_index(name: &str) -> Option<i32> {
+    let index = PROPERTIES.iter().position(|property| *property == name)?;
+    Some(index as i32)
 }
 
-impl Precedence {
-    fn can_assign(&self) -> bool {
-        *self <= Self::Assignment
+fn number_value(token: &Token) -> Result<swf::avm1::types::Value<'static>, CompileError> {
+    let (source_without_radix, radix) = if let Some(source) = token
+        .source
+        .strip_prefix("0b")
+        .or(token.source.strip_prefix("0B"))
+    {
+        (source, 2)
+    } else if let Some(source) = token
+        .source
+        .strip_prefix("0o")
+        .or(token.source.strip_prefix("0O"))
+    {
+        (source, 8)
+    } else if let Some(source) = token
+        .source
+        .strip_prefix("0x")
+        .or(token.source.strip_prefix("0X"))
+    {
+        (source, 16)
+    } else {
+        (token.source, 10)
+    };
+
+    if let Ok(integer) = i32::from_str_radix(source_without_radix, radix) {
+        Ok(swf::avm1::types::Value::Int(integer))
+    } else if radix != 10 {
+        // Too large for an integer.
+        let double = source_without_radix.chars().fold(0.0, |double, c| {
+            double * f64::from(radix) + f64::from(c.to_digit(radix).unwrap())
+        });
+        Ok(swf::avm1::types::Value::Double(double))
+    } else {
+        let double = token.source.parse().map_err(|_| CompileError {
+            kind: ErrorKind::InvalidToken,
+            message: "Invalid number".to_string(),
+            line: token.line,
+            column: token.column,
+        })?;
+        Ok(swf::avm1::types::Value::Double(double))
     }
+}
 
-    fn is_construct(&self) -> bool {
-        *self == Self::Construct
+// `number` to push, as an integer if it is one, like number literals.
+fn number_push_value(number: f64) -> swf::avm1::types::Value<'static> {
+    let integer = number as i32;
+    if f64::from(integer) == number && !(number == 0.0 && number.is_sign_negative()) {
+        swf::avm1::types::Value::Int(integer)
+    } else {
+        swf::avm1::types::Value::Double(number)
     }
+}
 
-    fn is_delete(&self) -> bool {
-        *self == Self::Delete
+// The value of a conditional compilation constant, such as `true`, `1` or `"release"`.
+fn constant_value(value: &str) -> swf::avm1::types::Value<'_> {
+    match value {
+        "true" => swf::avm1::types::Value::Bool(true),
+        "false" => swf::avm1::types::Value::Bool(false),
+        "null" => swf::avm1::types::Value::Null,
+        "undefined" => swf::avm1::types::Value::Undefined,
+        _ if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') => {
+            swf::avm1::types::Value::Str(value[1..value.len() - 1].into())
+        }
+        _ => {
+            if let Ok(value) = value.parse() {
+                swf::avm1::types::Value::Int(value)
+            } else if let Ok(value) = value.parse() {
+                swf::avm1::types::Value::Double(value)
+            } else {
+                swf::avm1::types::Value::Str(value.into())
+            }
+        }
     }
 }
 
-impl TokenKind {
-    fn precedence(&self) -> Precedence {
-        match self {
-            Self::Dot | Self::LeftSquareBrace => Precedence::Path,
-            Self::LeftParen => Precedence::Call,
-            Self::Bang | Self::Delete | Self::Tilda | Self::Throw | Self::Typeof => {
-                Precedence::Unary
-            }
-            Self::Star | Self::Slash | Self::Percent => Precedence::Factor,
-            Self::Plus | Self::Minus => Precedence::Term,
-            Self::DoubleGreater | Self::TripleGreater | Self::DoubleLess => {
-                Precedence::BitwiseShift
-            }
-            Self::Greater
-            | Self::GreaterEqual
-            | Self::Less
-            | Self::LessEqual
-            | Self::InstanceOf => Precedence::Comparison,
-            Self::BangEqual | Self::DoubleEqual | Self::TripleEqual => Precedence::Equality,
-            Self::Ampersand => Precedence::BitwiseAnd,
-            Self::Caret => Precedence::BitwiseXor,
-            Self::Bar => Precedence::BitwiseOr,
-            _ => Precedence::None,
-        }
-    }
-
-    fn is_assign(&self) -> bool {
-        matches!(
-            self,
-            Self::Equal
-                | Self::PlusEqual
-                | Self::MinusEqual
-                | Self::StarEqual
-                | Self::SlashEqual
-                | Self::PercentEqual
-                | Self::AmpersandEqual
-                | Self::BarEqual
-                | Self::CaretEqual
-                | Self::DoubleGreaterEqual
-                | Self::TripleGreaterEqual
-                | Self::DoubleLessEqual
-        )
+fn is_truthy(value: &swf::avm1::types::Value) -> bool {
+    match value {
+        swf::avm1::types::Value::Bool(value) => *value,
+        swf::avm1::types::Value::Int(value) => *value != 0,
+        swf::avm1::types::Value::Double(value) => *value != 0.0 && !value.is_nan(),
+        swf::avm1::types::Value::Str(value) => !value.is_empty(),
+        _ => false,
     }
 }
 
-fn property_index(name: &str) -> Option<i32> {
-    match name {
-        "_x" => Some(0),
-        "_y" => Some(1),
-        "_xscale" => Some(2),
-        "_yscale" => Some(3),
-        "_currentframe" => Some(4),
-        "_totalframes" => Some(5),
-        "_alpha" => Some(6),
-        "_visible" => Some(7),
-        "_width" => Some(8),
-        "_height" => Some(9),
-        "_rotation" => Some(10),
-        "_target" => Some(11),
-        "_framesloaded" => Some(12),
-        "_name" => Some(13),
-        "_droptarget" => Some(14),
-        "_url" => Some(15),
-        "_highquality" => Some(16),
-        "_focusrect" => Some(17),
-        "_soundbuftime" => Some(18),
-        "_quality" => Some(19),
-        "_xmouse" => Some(20),
-        "_ymouse" => Some(21),
+// Whether a loop condition is a literal that is always true, such as `true` or `1`.
+fn is_always_true(condition: &Expression) -> bool {
+    match condition {
+        Expression::Literal(token) => match token.kind {
+            TokenKind::True => true,
+            TokenKind::Number => number_value(token).is_ok_and(|value| is_truthy(&value)),
+            TokenKind::String => token.source.len() > 2,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+const BUTTON_EVENTS: &[(&str, swf::ButtonActionCondition)] = &[
+    ("press", swf::ButtonActionCondition::OVER_UP_TO_OVER_DOWN),
+    ("release", swf::ButtonActionCondition::OVER_DOWN_TO_OVER_UP),
+    (
+        "releaseOutside",
+        swf::ButtonActionCondition::OUT_DOWN_TO_IDLE,
+    ),
+    ("rollOver", swf::ButtonActionCondition::IDLE_TO_OVER_UP),
+    ("rollOut", swf::ButtonActionCondition::OVER_UP_TO_IDLE),
+    (
+        "dragOver",
+        swf::ButtonActionCondition::OUT_DOWN_TO_OVER_DOWN,
+    ),
+    ("dragOut", swf::ButtonActionCondition::OVER_DOWN_TO_OUT_DOWN),
+];
+
+const CLIP_EVENTS: &[(&str, swf::ClipEventFlag)] = &[
+    ("load", swf::ClipEventFlag::LOAD),
+    ("enterFrame", swf::ClipEventFlag::ENTER_FRAME),
+    ("unload", swf::ClipEventFlag::UNLOAD),
+    ("mouseMove", swf::ClipEventFlag::MOUSE_MOVE),
+    ("mouseDown", swf::ClipEventFlag::MOUSE_DOWN),
+    ("mouseUp", swf::ClipEventFlag::MOUSE_UP),
+    ("keyDown", swf::ClipEventFlag::KEY_DOWN),
+    ("keyUp", swf::ClipEventFlag::KEY_UP),
+    ("data", swf::ClipEventFlag::DATA),
+    ("initialize", swf::ClipEventFlag::INITIALIZE),
+    ("construct", swf::ClipEventFlag::CONSTRUCT),
+    ("press", swf::ClipEventFlag::PRESS),
+    ("release", swf::ClipEventFlag::RELEASE),
+    ("releaseOutside", swf::ClipEventFlag::RELEASE_OUTSIDE),
+    ("rollOver", swf::ClipEventFlag::ROLL_OVER),
+    ("rollOut", swf::ClipEventFlag::ROLL_OUT),
+    ("dragOver", swf::ClipEventFlag::DRAG_OVER),
+    ("dragOut", swf::ClipEventFlag::DRAG_OUT),
+];
+
+// Keys with a name, rather than a character.
+const KEYS: &[(&str, u8)] = &[
+    ("<Left>", 1),
+    ("<Right>", 2),
+    ("<Home>", 3),
+    ("<End>", 4),
+    ("<Insert>", 5),
+    ("<Delete>", 6),
+    ("<Backspace>", 8),
+    ("<Enter>", 13),
+    ("<Up>", 14),
+    ("<Down>", 15),
+    ("<PageUp>", 16),
+    ("<PageDown>", 17),
+    ("<Tab>", 18),
+    ("<Escape>", 19),
+    ("<Space>", 32),
+];
+
+fn key_code(key: &str) -> Option<u8> {
+    if let Some((_, code)) = KEYS.iter().find(|(name, _)| *name == key) {
+        return Some(*code);
+    }
+    match key.as_bytes() {
+        [c @ 32..=126] => Some(*c),
         _ => None,
     }
 }
 
-fn register_index(name: &str) -> Option<u8> {
-    name.strip_prefix("register").and_then(|r| r.parse().ok())
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LoopJumpKind {
+    Break,
+    Continue,
+}
+
+// An enclosing loop, along with the targets of its `break` and `continue` jumps.
+#[derive(Clone)]
+struct Loop<'a> {
+    label: Option<&'a str>,
+    break_label: Label,
+    continue_label: Label,
+    has_break: bool,
+    // Whether the loop leaves the names that it enumerates on the stack, which jumps out of it
+    // must pop.
+    enumerates: bool,
+}
+
+// The actions of an `on(...)` or `onClipEvent(...)` block.
+struct Handler<E> {
+    events: E,
+    key_code: Option<u8>,
+    action_data: Vec<u8>,
+    debug: DebugInfo,
+}
+
+// How the written SWF file is compressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    // zlib, for a file starting with "CWS".
+    Zlib,
+    // LZMA, for a file starting with "ZWS".
+    Lzma,
+}
+
+impl Compression {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zlib => "zlib",
+            Self::Lzma => "lzma",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::None, Self::Zlib, Self::Lzma]
+            .into_iter()
+            .find(|compression| compression.name() == name)
+    }
+
+    // The first SWF version whose players read files compressed this way.
+    fn min_swf_version(self) -> u8 {
+        match self {
+            Self::None => 1,
+            Self::Zlib => 6,
+            Self::Lzma => 13,
+        }
+    }
+}
+
+// Which optimizations to apply to the compiled code.
+#[derive(Clone, Debug)]
+pub struct Optimizations {
+    // Keep parameters and local variables in registers rather than looking them up by name.
+    pub registers: bool,
+    // Merge consecutive `Push` actions, retarget jumps to jumps and drop jumps to the next action.
+    pub peephole: bool,
+}
+
+impl Optimizations {
+    // Leave the code as written, such as for debugging the compiler.
+    pub fn none() -> Self {
+        Self {
+            registers: false,
+            peephole: false,
+        }
+    }
+}
+
+impl Default for Optimizations {
+    fn default() -> Self {
+        Self {
+            registers: true,
+            peephole: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CompileOptions {
+    // Allow accessing private class members from outside of their class.
+    pub allow_private_access: bool,
+    // Directories to look up imported classes in.
+    pub classpath: Vec<std::path::PathBuf>,
+    // Preprocessor definitions, as if `#define NAME value` preceded the source.
+    pub defines: Vec<(String, String)>,
+    // Conditional compilation constants such as `CONFIG::DEBUG`, along with their values.
+    pub constants: Vec<(String, String)>,
+    // Compile `trace(...)` as a call to a `trace` function rather than the `Trace` action.
+    pub trace_call: bool,
+    // Only access registers as `@rN`, leaving `registerN` names to variables.
+    pub explicit_registers: bool,
+    // Maximum nesting depth of statements and expressions, or `None` for a default that compiling
+    // fits in the stack of the main thread with.
+    pub max_depth: Option<usize>,
+    // Which warnings to report, and whether as errors.
+    pub lints: LintOptions,
+    // Report warnings as errors and require variables to be declared before use, as if every file
+    // began with `#pragma strict`.
+    pub strict: bool,
+    // Version of the written SWF file.
+    pub swf_version: u8,
+    // Size of the stage in twips, 20 to the pixel.
+    pub stage_width: i32,
+    pub stage_height: i32,
+    // Frames per second.
+    pub frame_rate: f32,
+    // Color of the stage as 0xRRGGBB.
+    pub background_color: u32,
+    pub compression: Compression,
+    // Keep authoring tools from importing the SWF file with a `Protect` tag, unless given the
+    // password if not empty.
+    pub protect: Option<String>,
+    // Let debuggers connect to the movie with an `EnableDebugger2` tag, or `EnableDebugger` before
+    // SWF version 6, given the password if not empty.
+    pub debugger_password: Option<String>,
+    // Salt of the hashes of those passwords, 1 to 8 of the characters `./0-9A-Za-z`, or `None` for
+    // a random one, which makes each compiled file differ.
+    pub password_salt: Option<String>,
+    // How deep calls may nest, and for how many seconds the actions of a frame may run before the
+    // player offers to stop them, in a `ScriptLimits` tag, or `None` for the defaults of the
+    // player, 256 calls and 15 seconds.
+    pub max_recursion_depth: Option<u16>,
+    pub script_timeout: Option<u16>,
+    pub optimizations: Optimizations,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            allow_private_access: false,
+            classpath: Vec::new(),
+            defines: Vec::new(),
+            constants: Vec::new(),
+            trace_call: false,
+            explicit_registers: false,
+            max_depth: None,
+            lints: LintOptions::default(),
+            strict: false,
+            swf_version: 32,
+            stage_width: 100,
+            stage_height: 100,
+            frame_rate: 1.0,
+            background_color: 0xeeeeee,
+            compression: Compression::None,
+            protect: None,
+            debugger_password: None,
+            password_salt: None,
+            max_recursion_depth: None,
+            script_timeout: None,
+            optimizations: Optimizations::default(),
+        }
+    }
+}
+
+impl CompileOptions {
+    // A parser for a source file, limited to the maximum nesting depth.
+    fn parser<'a>(&self, preprocessor: Preprocessor<'a>) -> Parser<'a> {
+        let mut parser = Parser::new(preprocessor);
+        parser.set_max_depth(self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH));
+        parser
+    }
+}
+
+// A property defined by `get` and `set` methods of a class.
+struct Accessor<'a> {
+    name: &'a str,
+    is_static: bool,
+    has_getter: bool,
+    has_setter: bool,
+}
+
+// A member of a declared class.
+struct Member {
+    name: Rc<str>,
+    is_static: bool,
+    is_private: bool,
+}
+
+// A class declared so far, including intrinsic ones.
+struct ClassInfo {
+    name: Rc<str>,
+    superclass: Option<Rc<str>>,
+    members: Vec<Member>,
+}
+
+// How an unqualified class member is accessed.
+enum MemberAccess {
+    This,
+    Static(Rc<str>),
+}
+
+// The class whose members are being compiled.
+struct Class<'a> {
+    path: Vec<&'a str>,
+    name: Rc<str>,
+    superclass: Option<Rc<str>>,
+    members: Vec<&'a str>,
+    static_members: Vec<&'a str>,
+}
+
+// The actions of an `#initclip` block.
+struct InitClip {
+    order: u32,
+    action_data: Vec<u8>,
+    debug: DebugInfo,
+}
+
+// The actions of a frame of the main timeline that `#frame` ends.
+struct Frame<'a> {
+    // The number of the frame, counting from 1.
+    number: u16,
+    label: Option<&'a str>,
+    action_data: Vec<u8>,
+    debug: DebugInfo,
+    // The position of the `#frame` directive that ends the frame.
+    end: (usize, usize),
+}
+
+// The actions of a `#sprite` block.
+struct Sprite<'a> {
+    name: Option<&'a str>,
+    // The frame of the main timeline that the sprite is placed in.
+    frame: u16,
+    action_data: Vec<u8>,
+    debug: DebugInfo,
 }
 
-struct Label {
-    position: usize,
+// A character exported by `#export`.
+struct Export<'a> {
+    name: &'a str,
+    id: swf::CharacterId,
+    // The position of the linkage name.
+    position: (usize, usize),
 }
 
 struct CompilerState<'a> {
-    scanner: Scanner<'a>,
-    current: Token<'a>,
+    options: CompileOptions,
+    // Number of code labels created so far.
+    labels: usize,
+    loops: Vec<Loop<'a>>,
+    // Label of the upcoming loop.
+    label: Option<&'a str>,
+    // Loop depth and name of the innermost block that loop jumps must not leave, such as `try`.
+    jump_barrier: Option<(usize, &'static str)>,
+    // Variables of the enclosing catch blocks.
+    catch_vars: Vec<&'a str>,
+    in_function: bool,
+    // Number of SWF 4 comparisons whose left operands are kept in variables while their right
+    // operands are evaluated, to name the variable of the next one.
+    swf4_comparisons: usize,
+    // Whether the upcoming function is a constructor that should call `super()` implicitly.
+    implicit_super: bool,
+    // Whether the current function calls `super()`.
+    super_called: bool,
+    // Local variables of the enclosing functions.
+    locals: Vec<&'a str>,
+    // Variables and functions declared so far in the enclosing scopes, including the top level.
+    declared: Vec<&'a str>,
+    // Whether the current file is compiled in strict mode.
+    strict: bool,
+    // Variables of the current function kept in registers, along with their registers.
+    registers: Vec<(&'a str, u8)>,
+    // Number of registers that the current function uses.
+    register_count: usize,
+    // Special variables such as `this` that the current function looks up by name.
+    lookups: Vec<&'a str>,
+    // Constants in scope, along with the actions pushing their values.
+    consts: Vec<(&'a str, Vec<u8>)>,
+    class: Option<Class<'a>>,
+    class_infos: Vec<ClassInfo>,
+    // Classes imported by the current file.
+    imports: Vec<Vec<&'a str>>,
+    // Full names of the classes compiled so far, including imported ones.
+    classes: Vec<Rc<str>>,
+    // The files imported so far, which the mappings of source positions refer to by index.
+    files: Vec<std::path::PathBuf>,
+    // Names shared by the classes of all files.
+    interner: Interner,
+    // Time spent in each phase of compilation so far, if measured.
+    timings: Option<Timings>,
+    button_handlers: Vec<Handler<swf::ButtonActionCondition>>,
+    clip_handlers: Vec<Handler<swf::ClipEventFlag>>,
+    init_clips: Vec<InitClip>,
+    // The frames of the main timeline before the current one.
+    frames: Vec<Frame<'a>>,
+    // The number of the current frame, counting from 1, and its label if any.
+    frame: u16,
+    frame_label: Option<&'a str>,
+    sprites: Vec<Sprite<'a>>,
+    exports: Vec<Export<'a>>,
+    // Transforms each parsed file, including imported ones, before its code is generated.
+    visitor: Option<&'a mut dyn VisitMut>,
+    // Warnings about the source so far, including those made errors.
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> CompilerState<'a> {
-    fn new(source: &'a str) -> Self {
+    fn new(options: &CompileOptions) -> Self {
         Self {
-            scanner: Scanner::new(source),
-            current: Token::INVALID,
+            options: options.clone(),
+            labels: 0,
+            loops: Vec::new(),
+            label: None,
+            jump_barrier: None,
+            catch_vars: Vec::new(),
+            in_function: false,
+            swf4_comparisons: 0,
+            implicit_super: false,
+            super_called: false,
+            locals: Vec::new(),
+            declared: Vec::new(),
+            strict: false,
+            registers: Vec::new(),
+            register_count: 0,
+            lookups: Vec::new(),
+            consts: Vec::new(),
+            class: None,
+            class_infos: Vec::new(),
+            imports: Vec::new(),
+            classes: Vec::new(),
+            files: Vec::new(),
+            interner: Interner::new(),
+            timings: None,
+            button_handlers: Vec::new(),
+            clip_handlers: Vec::new(),
+            init_clips: Vec::new(),
+            frames: Vec::new(),
+            frame: 1,
+            frame_label: None,
+            sprites: Vec::new(),
+            exports: Vec::new(),
+            visitor: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // Warn about the source at a position, as the options of `lint` say.
+    fn warn(&mut self, lint: Lint, message: String, line: usize, column: usize) {
+        self.warn_with_notes(lint, message, line, column, Vec::new());
+    }
+
+    fn warn_with_notes(
+        &mut self,
+        lint: Lint,
+        message: String,
+        line: usize,
+        column: usize,
+        notes: Vec<Diagnostic>,
+    ) {
+        if let Some(severity) = self.lint_severity(lint) {
+            self.diagnostics.push(Diagnostic {
+                severity,
+                kind: None,
+                lint: Some(lint),
+                message,
+                line,
+                column,
+                end: None,
+                notes,
+            });
+        }
+    }
+
+    // The severity of warnings of `lint`, or `None` if disabled.
+    fn lint_severity(&self, lint: Lint) -> Option<Severity> {
+        let severity = self.options.lints.severity(lint)?;
+        Some(if self.strict {
+            Severity::Error
+        } else {
+            severity
+        })
+    }
+
+    // Remove the warnings about `source` that its comments suppress, and warn about the unknown
+    // lints that they name.
+    fn suppress(&mut self, source: &str) {
+        let severity = self.lint_severity(Lint::UnknownLint);
+        suppress(&mut self.diagnostics, source, severity);
+    }
+
+    // Record a phase of `file` that started at `start`, if timing.
+    fn record_phase(&mut self, name: &'static str, file: Option<&std::path::Path>, start: Instant) {
+        if let Some(timings) = &mut self.timings {
+            timings.record(name, file, start);
+        }
+    }
+}
+
+// How an expression is used by the expression containing it.
+#[derive(Clone, Copy)]
+enum Access<'e, 'a> {
+    Get,
+    // `target = value`, or a compound assignment such as `target += value`.
+    Assign(&'e Token<'a>, &'e Expression<'a>),
+    // `target++` or `target--`.
+    Increment(&'e Token<'a>),
+    // `target(arguments)`, along with the closing parenthesis.
+    Call(&'e [Expression<'a>], &'e Token<'a>),
+    // `new target(arguments)`.
+    Construct(&'e [Expression<'a>]),
+    Delete,
+}
+
+impl<'e, 'a> Access<'e, 'a> {
+    // The operator of an access that reads the target before writing it, such as `+=` or `++`.
+    fn update_operator(&self) -> Option<&'e Token<'a>> {
+        match *self {
+            Self::Assign(operator, _) if operator.kind != TokenKind::Equal => Some(operator),
+            Self::Increment(operator) => Some(operator),
+            _ => None,
+        }
+    }
+}
+
+// Whether evaluating `expression` has no effect other than its value, so that it can be reordered.
+fn is_pure(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(_) | Expression::Identifier(_) | Expression::Constant { .. } => true,
+        Expression::Grouping { expression, .. } => is_pure(expression),
+        Expression::Member { object, .. } => is_pure(object),
+        Expression::Unary { operator, operand } => {
+            operator.kind != TokenKind::Throw && is_pure(operand)
+        }
+        Expression::Binary { left, right, .. } => is_pure(left) && is_pure(right),
+        _ => false,
+    }
+}
+
+// The names of a dotted path such as `a.b.c`, if the expression is one.
+fn dotted_names<'e, 'a>(expression: &'e Expression<'a>) -> Option<Vec<&'e Token<'a>>> {
+    match expression {
+        Expression::Identifier(token) => Some(vec![token]),
+        Expression::Member { object, name } => {
+            let mut names = dotted_names(object)?;
+            names.push(name);
+            Some(names)
+        }
+        _ => None,
+    }
+}
+
+// Global functions and objects of the Flash Player, besides builtins and intrinsic classes.
+const GLOBALS: &[&str] = &[
+    "ASSetPropFlags",
+    "Accessibility",
+    "Button",
+    "Camera",
+    "Color",
+    "ContextMenu",
+    "ContextMenuItem",
+    "Error",
+    "Infinity",
+    "LocalConnection",
+    "Microphone",
+    "MovieClipLoader",
+    "NaN",
+    "NetConnection",
+    "NetStream",
+    "PrintJob",
+    "Selection",
+    "SharedObject",
+    "System",
+    "TextFormat",
+    "TextSnapshot",
+    "Video",
+    "XML",
+    "XMLNode",
+    "XMLSocket",
+    "clearInterval",
+    "clearTimeout",
+    "escape",
+    "fscommand",
+    "getURL",
+    "getVersion",
+    "isFinite",
+    "isNaN",
+    "newline",
+    "parseFloat",
+    "parseInt",
+    "setInterval",
+    "setTimeout",
+    "unescape",
+    "updateAfterEvent",
+];
+
+// Names of the functions that `statements` declare, which are defined before any statement runs.
+fn hoisted_functions<'s, 'a: 's>(
+    statements: impl IntoIterator<Item = &'s Statement<'a>>,
+) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    for statement in statements {
+        match statement {
+            Statement::Function(function) => {
+                names.extend(function.name.as_ref().map(|name| name.source))
+            }
+            Statement::Block(statements)
+            | Statement::Try {
+                body: statements, ..
+            } => names.extend(hoisted_functions(statements)),
+            Statement::If {
+                body, else_body, ..
+            } => {
+                names.extend(hoisted_functions([&**body]));
+                names.extend(hoisted_functions(else_body.as_deref()));
+            }
+            Statement::ForIn { body, .. }
+            | Statement::While { body, .. }
+            | Statement::DoWhile { body, .. }
+            | Statement::Labeled { body, .. }
+            | Statement::TellTarget { body, .. }
+            | Statement::Conditional { body, .. } => names.extend(hoisted_functions([&**body])),
+            _ => {}
+        }
+    }
+    names
+}
+
+fn is_builtin(name: &str) -> bool {
+    matches!(
+        name,
+        "call"
+            | "duplicateMovieClip"
+            | "chr"
+            | "eval"
+            | "getProperty"
+            | "getTimer"
+            | "gotoAndPlay"
+            | "gotoAndStop"
+            | "int"
+            | "length"
+            | "loadMovie"
+            | "loadMovieNum"
+            | "loadVariables"
+            | "loadVariablesNum"
+            | "mbchr"
+            | "mblength"
+            | "mbord"
+            | "mbsubstring"
+            | "nextFrame"
+            | "nextScene"
+            | "prevScene"
+            | "ord"
+            | "play"
+            | "prevFrame"
+            | "random"
+            | "set"
+            | "setProperty"
+            | "startDrag"
+            | "stop"
+            | "stopAllSounds"
+            | "stopDrag"
+            | "targetPath"
+            | "toggleHighQuality"
+    )
+}
+
+// Whether a builtin leaves a value on the stack, unlike those that only control the movie.
+fn builtin_has_value(name: &str) -> bool {
+    matches!(
+        name,
+        "chr"
+            | "eval"
+            | "getProperty"
+            | "getTimer"
+            | "int"
+            | "length"
+            | "mbchr"
+            | "mblength"
+            | "mbord"
+            | "mbsubstring"
+            | "ord"
+            | "random"
+            | "set"
+            | "targetPath"
+    )
+}
+
+// A pushed value as SWF 4 pushes it, which only has strings and floats. Flash 4 has no booleans,
+// and uses 1 and 0 instead, nor `null` or `undefined`, which read as empty strings.
+fn swf4_value<'a>(value: &swf::avm1::types::Value<'a>) -> swf::avm1::types::Value<'a> {
+    match *value {
+        swf::avm1::types::Value::Undefined | swf::avm1::types::Value::Null => {
+            swf::avm1::types::Value::Str("".into())
+        }
+        swf::avm1::types::Value::Bool(value) => {
+            swf::avm1::types::Value::Float(if value { 1.0 } else { 0.0 })
         }
+        swf::avm1::types::Value::Int(value) => swf::avm1::types::Value::Float(value as f32),
+        swf::avm1::types::Value::Double(value) => swf::avm1::types::Value::Float(value as f32),
+        ref value => value.clone(),
     }
 }
 
+// Variables that a function can preload into registers, in the order of their registers, along
+// with the flags preloading them and the flags suppressing them when unused, if any.
+const SPECIAL_VARIABLES: [(
+    &str,
+    swf::avm1::types::FunctionFlags,
+    swf::avm1::types::FunctionFlags,
+); 6] = [
+    (
+        "this",
+        swf::avm1::types::FunctionFlags::PRELOAD_THIS,
+        swf::avm1::types::FunctionFlags::SUPPRESS_THIS,
+    ),
+    (
+        "arguments",
+        swf::avm1::types::FunctionFlags::PRELOAD_ARGUMENTS,
+        swf::avm1::types::FunctionFlags::SUPPRESS_ARGUMENTS,
+    ),
+    (
+        "super",
+        swf::avm1::types::FunctionFlags::PRELOAD_SUPER,
+        swf::avm1::types::FunctionFlags::SUPPRESS_SUPER,
+    ),
+    (
+        "_root",
+        swf::avm1::types::FunctionFlags::PRELOAD_ROOT,
+        swf::avm1::types::FunctionFlags::empty(),
+    ),
+    (
+        "_parent",
+        swf::avm1::types::FunctionFlags::PRELOAD_PARENT,
+        swf::avm1::types::FunctionFlags::empty(),
+    ),
+    (
+        "_global",
+        swf::avm1::types::FunctionFlags::PRELOAD_GLOBAL,
+        swf::avm1::types::FunctionFlags::empty(),
+    ),
+];
+
 struct Compiler<'a, 'b> {
     state: &'b mut CompilerState<'a>,
-    in_loop: bool,
-    action_data: Vec<u8>,
+    code: Code,
+    // Whether the expression of the current statement ended up leaving nothing on the stack.
+    discarded: bool,
 }
 
 impl<'a, 'b> Compiler<'a, 'b> {
     fn new(state: &'b mut CompilerState<'a>) -> Self {
+        let code = if state.options.optimizations.peephole {
+            Code::new()
+        } else {
+            Code::plain()
+        };
         Self {
             state,
-            in_loop: false,
-            action_data: Vec::new(),
+            code,
+            discarded: false,
         }
     }
 
+    // Compile separate code, such as a function body.
     fn nested(
         &mut self,
         f: impl FnOnce(&mut Compiler<'a, '_>) -> Result<(), CompileError>,
-    ) -> Result<Vec<u8>, CompileError> {
+    ) -> Result<Code, CompileError> {
         let mut compiler = Compiler::new(self.state);
         f(&mut compiler)?;
-        Ok(compiler.action_data)
+        Ok(compiler.code)
     }
 
-    fn write_action(&mut self, action: swf::avm1::types::Action) {
-        let mut writer = swf::avm1::write::Writer::new(&mut self.action_data, 0);
-        writer.write_action(&action).unwrap();
+    // Compile the body of a loop, returning whether it breaks out of the loop.
+    fn loop_body(
+        &mut self,
+        break_label: Label,
+        continue_label: Label,
+        enumerates: bool,
+        f: impl FnOnce(&mut Self) -> Result<(), CompileError>,
+    ) -> Result<bool, CompileError> {
+        let label = self.state.label.take();
+        self.state.loops.push(Loop {
+            label,
+            break_label,
+            continue_label,
+            has_break: false,
+            enumerates,
+        });
+        let result = f(self);
+        let has_break = self.state.loops.pop().unwrap().has_break;
+        result?;
+        Ok(has_break)
     }
 
-    fn read_token(&mut self) -> Result<Token<'a>, CompileError> {
-        let next_token = self.state.scanner.read_token()?;
-        let token = std::mem::replace(&mut self.state.current, next_token);
-        Ok(token)
+    // Compile a block that loop jumps must not leave.
+    fn closed_block<T>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<T, CompileError> {
+        let barrier = (self.state.loops.len(), name);
+        let jump_barrier = self.state.jump_barrier.replace(barrier);
+        let result = f(self);
+        self.state.jump_barrier = jump_barrier;
+        result
     }
 
-    fn peek_token(&self) -> &Token<'a> {
-        &self.state.current
+    fn new_label(&mut self) -> Label {
+        self.state.labels += 1;
+        Label(self.state.labels)
     }
 
-    fn consume(&mut self, kind: TokenKind) -> Result<bool, CompileError> {
-        let token = self.peek_token();
-        if token.kind == kind {
-            self.read_token()?;
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+    fn define_label(&mut self, label: Label) {
+        self.code.define(label);
     }
 
-    fn expect(&mut self, kind: TokenKind, message: &str) -> Result<Token<'a>, CompileError> {
-        let token = self.peek_token();
-        if token.kind == kind {
-            self.read_token()
-        } else {
-            Err(CompileError {
-                message: message.to_string(),
-                line: token.line,
-                column: token.column,
-            })
-        }
+    fn jump(&mut self, target: Label) {
+        self.code.jump(target);
     }
 
-    fn push(&mut self, value: swf::avm1::types::Value) {
-        // TODO: Use constant pool.
-        let push = swf::avm1::types::Push {
-            values: vec![value],
-        };
-        self.write_action(swf::avm1::types::Action::Push(push));
+    // Branch to `target` if the value on top of the stack is truthy.
+    fn branch(&mut self, target: Label) {
+        self.code.branch(target);
     }
 
-    fn grouping(&mut self) -> Result<(), CompileError> {
-        self.expression()?;
-        self.expect(TokenKind::RightParen, "Expected ')' after expression")?;
-        Ok(())
+    // Check finished code and lay it out, warning about its unreachable statements, along with the
+    // source positions that the actions come from and the symbols of the functions.
+    fn assemble(&mut self, code: Code) -> Result<(Vec<u8>, DebugInfo), CompileError> {
+        let cfg = Cfg::new(&code);
+        stack::check(&code, &cfg)?;
+        log::trace!("Checked {} blocks of code", cfg.blocks.len());
+        for (line, column) in cfg.unreachable_statements() {
+            let message = "Unreachable code".to_string();
+            self.state.warn(Lint::Unreachable, message, line, column);
+        }
+        let (action_data, debug) = code.assemble_mapped()?;
+        log::debug!("Assembled {} bytes of actions", action_data.len());
+        Ok((action_data, debug))
     }
 
-    fn comma_separated(
-        &mut self,
-        f: impl Fn(&mut Compiler<'a, 'b>) -> Result<(), CompileError>,
-        terminator: TokenKind,
-        arity: Option<usize>,
-    ) -> Result<usize, CompileError> {
-        let mut count = 0;
-        let token = loop {
-            let token = self.peek_token();
-            if token.kind == terminator {
-                break self.read_token()?;
-            }
-
-            count += 1;
-            if let Some(arity) = arity {
-                if count > arity {
-                    return Err(CompileError {
-                        message: format!("Expected {} argument(s), got {}", arity, count),
-                        line: token.line,
-                        column: token.column,
-                    });
-                }
-            }
-
-            f(self)?;
+    // Lay out the code of a block of the current statement whose size an action records in 16
+    // bits, such as a try block.
+    fn block(&mut self, code: Code, name: &str) -> Result<(Vec<u8>, DebugInfo), CompileError> {
+        let (line, column) = self.code.position();
+        let (action_data, debug) = self.assemble(code)?;
+        if action_data.len() > usize::from(u16::MAX) {
+            return Err(CompileError {
+                kind: ErrorKind::TooLarge,
+                message: format!("{} too large", name),
+                line,
+                column,
+            });
+        }
+        Ok((action_data, debug))
+    }
 
-            if !self.consume(TokenKind::Comma)? {
-                // TODO: Print exact character.
-                break self.expect(terminator, "Expected end of values")?;
+    fn write_action(&mut self, action: swf::avm1::types::Action) {
+        let action = match action {
+            swf::avm1::types::Action::Push(push) if self.state.options.swf_version < 5 => {
+                let values = push.values.iter().map(swf4_value).collect();
+                swf::avm1::types::Action::Push(swf::avm1::types::Push { values })
             }
+            action => action,
         };
-
-        if let Some(arity) = arity {
-            if count < arity {
-                return Err(CompileError {
-                    message: format!("Expected {} argument(s), got {}", arity, count),
-                    line: token.line,
-                    column: token.column,
-                });
+        match &action {
+            swf::avm1::types::Action::Push(push) => {
+                for value in &push.values {
+                    if let swf::avm1::types::Value::Register(register) = value {
+                        self.use_register(*register);
+                    }
+                }
+            }
+            swf::avm1::types::Action::StoreRegister(store) => self.use_register(store.register),
+            swf::avm1::types::Action::Try(try_action) => {
+                if let Some((swf::avm1::types::CatchVar::Register(register), _)) =
+                    &try_action.catch_body
+                {
+                    self.use_register(*register);
+                }
             }
+            _ => {}
         }
-
-        Ok(count)
+        self.code.write_action(&action);
     }
 
-    fn comma_separated_rev(
-        &mut self,
-        f: impl Fn(&mut Compiler<'a, '_>) -> Result<(), CompileError>,
-        terminator: TokenKind,
-    ) -> Result<usize, CompileError> {
-        let mut values = Vec::new();
-        loop {
-            let token = self.peek_token();
-            if token.kind == terminator {
-                self.read_token()?;
-                break;
-            }
-
-            values.push(self.nested(&f)?);
+    fn use_register(&mut self, register: u8) {
+        let count = usize::from(register) + 1;
+        self.state.register_count = self.state.register_count.max(count);
+    }
 
-            if !self.consume(TokenKind::Comma)? {
-                // TODO: Print exact character.
-                self.expect(terminator, "Expected end of values")?;
-                break;
-            }
-        }
+    fn push(&mut self, value: swf::avm1::types::Value) {
+        // TODO: Use constant pool.
+        let push = swf::avm1::types::Push {
+            values: vec![value],
+        };
+        self.write_action(swf::avm1::types::Action::Push(push));
+    }
 
-        let count = values.len();
-        for value_data in values.into_iter().rev() {
-            self.action_data.extend(value_data);
+    // Push the arguments of a function call, last first, followed by their count.
+    fn arguments(&mut self, arguments: &[Expression<'a>]) -> Result<(), CompileError> {
+        for argument in arguments.iter().rev() {
+            self.expression(argument)?;
         }
-        Ok(count)
+        self.push(swf::avm1::types::Value::Int(
+            arguments.len().try_into().unwrap(),
+        ));
+        Ok(())
     }
 
-    fn array(&mut self) -> Result<(), CompileError> {
-        let count = self.comma_separated_rev(|c| c.expression(), TokenKind::RightSquareBrace)?;
-        self.push(swf::avm1::types::Value::Int(count.try_into().unwrap()));
-        self.write_action(swf::avm1::types::Action::InitArray);
+    // Push the arguments of a method call like `arguments`, keeping the object on top of the stack.
+    fn method_arguments(&mut self, arguments: &[Expression<'a>]) -> Result<(), CompileError> {
+        for argument in arguments.iter().rev() {
+            self.expression(argument)?;
+            self.write_action(swf::avm1::types::Action::StackSwap);
+        }
+        self.push(swf::avm1::types::Value::Int(
+            arguments.len().try_into().unwrap(),
+        ));
+        self.write_action(swf::avm1::types::Action::StackSwap);
         Ok(())
     }
 
-    fn object(&mut self) -> Result<(), CompileError> {
-        let count = self.comma_separated(
-            |c| {
-                let name = c.expect(TokenKind::Identifier, "Expected property name")?;
-                c.push(swf::avm1::types::Value::Str(name.source.into()));
-                c.expect(TokenKind::Colon, "Expected ':' after property name")?;
-                c.expression()
-            },
-            TokenKind::RightBrace,
-            None,
-        )?;
-        self.push(swf::avm1::types::Value::Int(count.try_into().unwrap()));
+    fn object(&mut self, properties: &[(Token<'a>, Expression<'a>)]) -> Result<(), CompileError> {
+        for (name, value) in properties {
+            self.push(swf::avm1::types::Value::Str(name.source.into()));
+            self.expression(value)?;
+        }
+        self.push(swf::avm1::types::Value::Int(
+            properties.len().try_into().unwrap(),
+        ));
         self.write_action(swf::avm1::types::Action::InitObject);
         Ok(())
     }
@@ -310,798 +1061,4295 @@ impl<'a, 'b> Compiler<'a, 'b> {
         push: impl Fn(&mut Self),
         duplicate: impl Fn(&mut Self),
         get: impl Fn(&mut Self),
-        set: impl Fn(&mut Self),
-        can_assign: bool,
+        set: impl Fn(&mut Self, bool),
+        access: Access<'_, 'a>,
+        discard: bool,
     ) -> Result<(), CompileError> {
-        if can_assign && self.peek_token().kind.is_assign() {
-            let token = self.read_token()?;
-            if token.kind == TokenKind::Equal {
-                push(self);
-            } else {
+        match access {
+            Access::Assign(operator, value) => {
+                if operator.kind == TokenKind::Equal {
+                    push(self);
+                } else {
+                    duplicate(self);
+                    push(self);
+                    get(self);
+                }
+                if !matches!(
+                    operator.kind,
+                    TokenKind::Equal
+                        | TokenKind::PlusEqual
+                        | TokenKind::MinusEqual
+                        | TokenKind::StarEqual
+                        | TokenKind::SlashEqual
+                ) {
+                    let feature = format!("'{}'", operator.source);
+                    self.require_swf_version(5, &feature, operator.line, operator.column)?;
+                }
+                self.expression(value)?;
+                match operator.kind {
+                    TokenKind::Equal => {}
+                    TokenKind::PlusEqual => self.add(),
+                    TokenKind::MinusEqual => self.write_action(swf::avm1::types::Action::Subtract),
+                    TokenKind::StarEqual => self.write_action(swf::avm1::types::Action::Multiply),
+                    TokenKind::SlashEqual => self.write_action(swf::avm1::types::Action::Divide),
+                    TokenKind::PercentEqual => self.write_action(swf::avm1::types::Action::Modulo),
+                    TokenKind::AmpersandEqual => {
+                        self.write_action(swf::avm1::types::Action::BitAnd)
+                    }
+                    TokenKind::BarEqual => self.write_action(swf::avm1::types::Action::BitOr),
+                    TokenKind::CaretEqual => self.write_action(swf::avm1::types::Action::BitXor),
+                    TokenKind::DoubleGreaterEqual => {
+                        self.write_action(swf::avm1::types::Action::BitRShift)
+                    }
+                    TokenKind::TripleGreaterEqual => {
+                        self.write_action(swf::avm1::types::Action::BitURShift)
+                    }
+                    TokenKind::DoubleLessEqual => {
+                        self.write_action(swf::avm1::types::Action::BitLShift)
+                    }
+                    _ => unreachable!(),
+                }
+                let keep = self.keep_assigned_value(discard, operator)?;
+                set(self, keep);
+            }
+            Access::Increment(operator) => {
                 duplicate(self);
                 push(self);
                 get(self);
-            }
-            self.expression()?;
-            match token.kind {
-                TokenKind::Equal => {}
-                TokenKind::PlusEqual => self.write_action(swf::avm1::types::Action::Add2),
-                TokenKind::MinusEqual => self.write_action(swf::avm1::types::Action::Subtract),
-                TokenKind::StarEqual => self.write_action(swf::avm1::types::Action::Multiply),
-                TokenKind::SlashEqual => self.write_action(swf::avm1::types::Action::Divide),
-                TokenKind::PercentEqual => self.write_action(swf::avm1::types::Action::Modulo),
-                TokenKind::AmpersandEqual => self.write_action(swf::avm1::types::Action::BitAnd),
-                TokenKind::BarEqual => self.write_action(swf::avm1::types::Action::BitOr),
-                TokenKind::CaretEqual => self.write_action(swf::avm1::types::Action::BitXor),
-                TokenKind::DoubleGreaterEqual => {
-                    self.write_action(swf::avm1::types::Action::BitRShift)
-                }
-                TokenKind::TripleGreaterEqual => {
-                    self.write_action(swf::avm1::types::Action::BitURShift)
+
+                // The value of a postfix operation is the original one.
+                let keep = self.keep_assigned_value(discard, operator)?;
+                if keep {
+                    self.write_action(swf::avm1::types::Action::StoreRegister(
+                        swf::avm1::types::StoreRegister { register: 0 },
+                    ));
                 }
-                TokenKind::DoubleLessEqual => {
-                    self.write_action(swf::avm1::types::Action::BitLShift)
+                self.increment(operator);
+                set(self, false);
+                if keep {
+                    self.push(swf::avm1::types::Value::Register(0));
                 }
-                _ => unreachable!(),
             }
-            set(self);
-        } else if self.consume(TokenKind::DoublePlus)? {
-            duplicate(self);
-            push(self);
-            get(self);
-            self.write_action(swf::avm1::types::Action::Increment);
-            set(self);
-        } else if self.consume(TokenKind::DoubleMinus)? {
-            duplicate(self);
-            push(self);
-            get(self);
-            self.write_action(swf::avm1::types::Action::Decrement);
-            set(self);
-        } else {
-            push(self);
-            get(self);
+            _ => {
+                push(self);
+                get(self);
+                self.value_access(access)?;
+            }
         }
         Ok(())
     }
 
-    fn variable_access(&mut self, name: &str, precedence: Precedence) -> Result<(), CompileError> {
-        let register = register_index(name);
-
-        if self.consume(TokenKind::LeftParen)? {
-            if register.is_some() {
-                // TODO: Tell exact location.
-                let token = self.peek_token();
-                return Err(CompileError {
-                    message: "Cannot call register".to_string(),
-                    line: token.line,
-                    column: token.column,
-                });
-            }
+    // Whether the value of an assignment is used, rather than discarded by its statement.
+    fn keep_value(&mut self, discard: bool) -> bool {
+        if discard {
+            self.discarded = true;
+            false
+        } else {
+            true
+        }
+    }
 
-            let count = self.comma_separated_rev(|c| c.expression(), TokenKind::RightParen)?;
-            self.push(swf::avm1::types::Value::Int(count.try_into().unwrap()));
+    // `keep_value` for an assignment, whose value is kept in a register, which needs SWF 5.
+    fn keep_assigned_value(
+        &mut self,
+        discard: bool,
+        operator: &Token,
+    ) -> Result<bool, CompileError> {
+        let keep = self.keep_value(discard);
+        if keep {
+            let feature = format!("Using the value of '{}'", operator.source);
+            self.require_swf_version(5, &feature, operator.line, operator.column)?;
+        }
+        Ok(keep)
+    }
 
-            self.push(swf::avm1::types::Value::Str(name.into()));
+    // Write a set action, leaving the assigned value on the stack if `keep` is set.
+    fn write_set(&mut self, action: swf::avm1::types::Action, keep: bool) {
+        if keep {
+            self.write_action(swf::avm1::types::Action::StoreRegister(
+                swf::avm1::types::StoreRegister { register: 0 },
+            ));
+        }
+        self.write_action(action);
+        if keep {
+            self.push(swf::avm1::types::Value::Register(0));
+        }
+    }
 
-            if precedence.is_construct() {
-                self.write_action(swf::avm1::types::Action::NewObject);
-            } else {
-                self.write_action(swf::avm1::types::Action::CallFunction);
+    // Use the value on top of the stack, such as calling the result of `f()` in `f()()`.
+    fn value_access(&mut self, access: Access<'_, 'a>) -> Result<(), CompileError> {
+        match access {
+            Access::Get | Access::Delete => {}
+            Access::Call(arguments, end) => {
+                self.require_swf_version(5, "Calling a function", end.line, end.column)?;
+                self.method_arguments(arguments)?;
+                self.push(swf::avm1::types::Value::Undefined);
+                self.write_action(swf::avm1::types::Action::CallMethod);
+            }
+            Access::Construct(arguments) => {
+                // Construct the value itself, such as in `new (a.b.C)(...)`.
+                self.method_arguments(arguments)?;
+                self.push(swf::avm1::types::Value::Undefined);
+                self.write_action(swf::avm1::types::Action::NewMethod);
             }
-        } else if precedence.is_delete() && self.peek_token().kind.precedence() < Precedence::Call {
-            if register.is_some() {
-                // TODO: Tell exact location.
-                let token = self.peek_token();
+            Access::Assign(operator, _) | Access::Increment(operator) => {
                 return Err(CompileError {
-                    message: "Cannot delete register".to_string(),
-                    line: token.line,
-                    column: token.column,
-                });
+                    kind: ErrorKind::InvalidTarget,
+                    message: "Invalid assignment target".to_string(),
+                    line: operator.line,
+                    column: operator.column,
+                })
             }
+        }
+        Ok(())
+    }
 
-            self.push(swf::avm1::types::Value::Str(name.into()));
-            self.write_action(swf::avm1::types::Action::Delete2);
-        } else {
-            let push = |this: &mut Self| match register {
-                Some(_) => {}
-                None => this.push(swf::avm1::types::Value::Str(name.into())),
-            };
-            let duplicate = push;
-            let get = |this: &mut Self| match register {
-                Some(register) => this.push(swf::avm1::types::Value::Register(register)),
-                None => this.write_action(swf::avm1::types::Action::GetVariable),
-            };
-            let set = |this: &mut Self| match register {
-                Some(register) => this.write_action(swf::avm1::types::Action::StoreRegister(
-                    swf::avm1::types::StoreRegister { register },
-                )),
-                None => this.write_action(swf::avm1::types::Action::SetVariable),
-            };
-            self.access(push, duplicate, get, set, precedence.can_assign())?;
+    fn variable_access(
+        &mut self,
+        token: &Token<'a>,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        let name = token.source;
+        let register = self.register_index(name);
+        if register.is_none() {
+            self.lookup(name);
         }
 
-        Ok(())
-    }
+        match access {
+            Access::Call(arguments, _) => {
+                if let Some(register) = self.variable_register(name) {
+                    // Call the function in the register as a method without a name.
+                    self.arguments(arguments)?;
+                    self.push(swf::avm1::types::Value::Register(register));
+                    self.push(swf::avm1::types::Value::Undefined);
+                    self.write_action(swf::avm1::types::Action::CallMethod);
+                    return Ok(());
+                }
+                if register.is_some() {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidTarget,
+                        message: "Cannot call register".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
 
-    fn dot(&mut self, precedence: Precedence) -> Result<(), CompileError> {
-        let name = self.expect(TokenKind::Identifier, "Expected name")?;
-
-        if self.consume(TokenKind::LeftParen)? {
-            // TODO: Error when calling a property?
-            let count = self.comma_separated_rev(
-                |c| {
-                    c.expression()?;
-                    c.write_action(swf::avm1::types::Action::StackSwap);
-                    Ok(())
-                },
-                TokenKind::RightParen,
-            )?;
-            self.push(swf::avm1::types::Value::Int(count.try_into().unwrap()));
-            self.write_action(swf::avm1::types::Action::StackSwap);
+                self.require_swf_version(5, "Calling a function", token.line, token.column)?;
+                self.arguments(arguments)?;
+                self.push(swf::avm1::types::Value::Str(name.into()));
+                self.write_action(swf::avm1::types::Action::CallFunction);
+            }
+            Access::Construct(arguments) => {
+                if let Some(register) = self.variable_register(name) {
+                    self.arguments(arguments)?;
+                    self.push(swf::avm1::types::Value::Register(register));
+                    self.push(swf::avm1::types::Value::Undefined);
+                    self.write_action(swf::avm1::types::Action::NewMethod);
+                    return Ok(());
+                }
+                if register.is_some() {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidTarget,
+                        message: "Cannot construct register".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
 
-            self.push(swf::avm1::types::Value::Str(name.source.into()));
+                self.arguments(arguments)?;
+                self.push(swf::avm1::types::Value::Str(name.into()));
+                self.write_action(swf::avm1::types::Action::NewObject);
+            }
+            Access::Delete => {
+                if register.is_some() {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidTarget,
+                        message: "Cannot delete register".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
 
-            if precedence.is_construct() {
-                self.write_action(swf::avm1::types::Action::NewMethod);
-            } else {
-                self.write_action(swf::avm1::types::Action::CallMethod);
+                self.push(swf::avm1::types::Value::Str(name.into()));
+                self.write_action(swf::avm1::types::Action::Delete2);
+            }
+            _ => {
+                let push = |this: &mut Self| match register {
+                    Some(_) => {}
+                    None => this.push(swf::avm1::types::Value::Str(name.into())),
+                };
+                let duplicate = push;
+                let get = |this: &mut Self| match register {
+                    Some(register) => this.push(swf::avm1::types::Value::Register(register)),
+                    None => this.write_action(swf::avm1::types::Action::GetVariable),
+                };
+                let set = |this: &mut Self, keep: bool| match register {
+                    Some(register) => {
+                        this.write_action(swf::avm1::types::Action::StoreRegister(
+                            swf::avm1::types::StoreRegister { register },
+                        ));
+                        if !keep {
+                            this.write_action(swf::avm1::types::Action::Pop);
+                        }
+                    }
+                    None => this.write_set(swf::avm1::types::Action::SetVariable, keep),
+                };
+                self.access(push, duplicate, get, set, access, discard)?;
             }
-        } else if precedence.is_delete() && self.peek_token().kind.precedence() < Precedence::Call {
-            // TODO: Error when deleting a property?
-            self.push(swf::avm1::types::Value::Str(name.source.into()));
-            self.write_action(swf::avm1::types::Action::Delete);
-        } else {
-            let property = property_index(name.source);
-            let push = |this: &mut Self| match property {
-                Some(property) => this.push(swf::avm1::types::Value::Int(property)),
-                None => this.push(swf::avm1::types::Value::Str(name.source.into())),
-            };
-            let duplicate = |this: &mut Self| {
-                this.write_action(swf::avm1::types::Action::PushDuplicate);
-                push(this);
-                this.write_action(swf::avm1::types::Action::StackSwap);
-            };
-            let get = |this: &mut Self| match property {
-                Some(_) => this.write_action(swf::avm1::types::Action::GetProperty),
-                None => this.write_action(swf::avm1::types::Action::GetMember),
-            };
-            let set = |this: &mut Self| match property {
-                Some(_) => this.write_action(swf::avm1::types::Action::SetProperty),
-                None => this.write_action(swf::avm1::types::Action::SetMember),
-            };
-            self.access(push, duplicate, get, set, precedence.can_assign())?;
         }
 
         Ok(())
     }
 
-    fn member_access(&mut self, precedence: Precedence) -> Result<(), CompileError> {
-        let name = self.nested(|c| c.expression())?;
-        self.expect(TokenKind::RightSquareBrace, "Expected ']'")?;
-
-        if self.consume(TokenKind::LeftParen)? {
-            let count = self.comma_separated_rev(|c| c.expression(), TokenKind::RightParen)?;
-            self.push(swf::avm1::types::Value::Int(count.try_into().unwrap()));
+    // Whether `name` refers to a member of the enclosing class, rather than to a local variable.
+    fn class_member(&self, name: &str) -> Option<MemberAccess> {
+        let class = self.state.class.as_ref()?;
+        if !self.state.in_function
+            || self.state.locals.contains(&name)
+            || self.state.catch_vars.contains(&name)
+        {
+            return None;
+        }
 
-            self.write_action(swf::avm1::types::Action::StackSwap);
-            self.action_data.extend(name);
+        if class.members.contains(&name) {
+            return Some(MemberAccess::This);
+        }
+        if class.static_members.contains(&name) {
+            return Some(MemberAccess::Static(class.name.clone()));
+        }
+        self.ancestors(class.superclass.as_deref())
+            .into_iter()
+            .find_map(|ancestor| {
+                let member = ancestor
+                    .members
+                    .iter()
+                    .find(|member| *member.name == *name)?;
+                Some(if member.is_static {
+                    MemberAccess::Static(ancestor.name.clone())
+                } else {
+                    MemberAccess::This
+                })
+            })
+    }
 
-            if precedence.is_construct() {
-                self.write_action(swf::avm1::types::Action::NewMethod);
-            } else {
-                self.write_action(swf::avm1::types::Action::CallMethod);
+    fn class_member_access(
+        &mut self,
+        name: &str,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        match self.class_member(name).unwrap() {
+            MemberAccess::This => self.get_variable("this"),
+            MemberAccess::Static(class) => {
+                let path: Vec<_> = class.split('.').collect();
+                self.global_path(&path);
             }
-        } else if precedence.is_delete() && self.peek_token().kind.precedence() < Precedence::Call {
-            self.action_data.extend(name);
-            self.write_action(swf::avm1::types::Action::Delete);
-        } else {
-            // TODO: Fix.
-            self.action_data.extend(name);
-            let push = |_this: &mut Self| {};
-            let duplicate = |this: &mut Self| {
-                this.write_action(swf::avm1::types::Action::StackSwap);
-                this.write_action(swf::avm1::types::Action::PushDuplicate);
-                this.write_action(swf::avm1::types::Action::StackSwap);
-            };
-            let get = |this: &mut Self| this.write_action(swf::avm1::types::Action::GetMember);
-            let set = |this: &mut Self| this.write_action(swf::avm1::types::Action::SetMember);
-            self.access(push, duplicate, get, set, precedence.can_assign())?;
         }
-
-        Ok(())
+        self.property_access(name, access, discard)
     }
 
-    fn construct(&mut self) -> Result<(), CompileError> {
-        self.expression_with_precedence(Precedence::Construct)
+    // The declared ancestors of a class with the given superclass, nearest first.
+    fn ancestors(&self, superclass: Option<&str>) -> Vec<&ClassInfo> {
+        let mut ancestors: Vec<&ClassInfo> = Vec::new();
+        let mut superclass = superclass;
+        while let Some(name) = superclass {
+            let Some(class) = self
+                .state
+                .class_infos
+                .iter()
+                .find(|class| *class.name == *name)
+            else {
+                break;
+            };
+            // Stop on cyclic inheritance.
+            if ancestors.iter().any(|ancestor| *ancestor.name == *name) {
+                break;
+            }
+            ancestors.push(class);
+            superclass = class.superclass.as_deref();
+        }
+        ancestors
     }
 
-    fn delete(&mut self) -> Result<(), CompileError> {
-        self.expression_with_precedence(Precedence::Delete)
+    fn import_path(&self, name: &str) -> Option<&[&'a str]> {
+        self.state
+            .imports
+            .iter()
+            .find(|path| path.last() == Some(&name))
+            .map(|path| path.as_slice())
     }
 
-    fn unary(&mut self, token_kind: TokenKind) -> Result<(), CompileError> {
-        match token_kind {
-            TokenKind::Minus => self.push(swf::avm1::types::Value::Int(0)),
-            TokenKind::Tilda => self.push(swf::avm1::types::Value::Double(u32::MAX.into())),
-            _ => {}
+    // The full path of the class referenced by the dotted name `names`, along with the number of
+    // names after the first one it spans. Resolves imported classes, classes of the current package
+    // and fully qualified names of known classes.
+    fn class_reference(&self, names: &[&'a str]) -> Option<(Vec<&'a str>, usize)> {
+        let name = names[0];
+        if self.state.locals.contains(&name) || self.state.catch_vars.contains(&name) {
+            return None;
         }
 
-        self.expression_with_precedence(Precedence::Unary)?;
+        if let Some(path) = self.import_path(name) {
+            return Some((path.to_vec(), 0));
+        }
 
-        match token_kind {
-            TokenKind::Plus => self.write_action(swf::avm1::types::Action::ToNumber),
-            TokenKind::Minus => self.write_action(swf::avm1::types::Action::Subtract),
-            TokenKind::Tilda => self.write_action(swf::avm1::types::Action::BitXor),
-            TokenKind::Bang => self.write_action(swf::avm1::types::Action::Not),
-            TokenKind::Throw => self.write_action(swf::avm1::types::Action::Throw),
-            TokenKind::Typeof => self.write_action(swf::avm1::types::Action::TypeOf),
-            _ => unreachable!(),
+        if let Some(class) = &self.state.class {
+            let mut path = class.path[..class.path.len() - 1].to_vec();
+            path.push(name);
+            if path.len() > 1 && self.is_class(&path) {
+                return Some((path, 0));
+            }
         }
 
-        Ok(())
+        (1..names.len())
+            .rev()
+            .find(|&dots| self.is_class(&names[..=dots]))
+            .map(|dots| (names[..=dots].to_vec(), dots))
     }
 
-    fn prefix(&mut self, token_kind: TokenKind) -> Result<(), CompileError> {
-        let variable = self.expect(TokenKind::Identifier, "Expected variable")?;
-        let register = register_index(variable.source);
+    // Whether `path` is the full path of a class compiled so far.
+    fn is_class(&self, path: &[&str]) -> bool {
+        self.state.classes.iter().any(|class| is_path(class, path))
+    }
 
-        if let Some(register) = register {
-            self.push(swf::avm1::types::Value::Register(register));
-        } else {
-            self.push(swf::avm1::types::Value::Str(variable.source.into()));
-            self.push(swf::avm1::types::Value::Str(variable.source.into()));
-            self.write_action(swf::avm1::types::Action::GetVariable);
-        }
+    // Whether `name` is a built-in function, rather than a constant, class member or class.
+    fn is_builtin_name(&self, name: &'a str) -> bool {
+        is_builtin(name)
+            && self.const_value(name).is_none()
+            && self.class_member(name).is_none()
+            && self.class_reference(&[name]).is_none()
+    }
 
-        match token_kind {
-            TokenKind::DoublePlus => self.write_action(swf::avm1::types::Action::Increment),
-            TokenKind::DoubleMinus => self.write_action(swf::avm1::types::Action::Decrement),
-            _ => unreachable!(),
+    // Push `_global.path`.
+    fn global_path(&mut self, path: &[&str]) {
+        self.get_variable("_global");
+        for name in path {
+            self.push(swf::avm1::types::Value::Str((*name).into()));
+            self.write_action(swf::avm1::types::Action::GetMember);
         }
+    }
 
-        if let Some(register) = register {
-            self.write_action(swf::avm1::types::Action::StoreRegister(
-                swf::avm1::types::StoreRegister { register },
-            ));
-        } else {
-            self.write_action(swf::avm1::types::Action::SetVariable);
+    // Error when accessing a private static member through its class name from outside of the
+    // class. Without type information, instance members cannot be checked.
+    fn check_private_access(&self, path: &[&str], token: &Token) -> Result<(), CompileError> {
+        if self.state.options.allow_private_access {
+            return Ok(());
         }
 
+        let Some(class) = self
+            .state
+            .class_infos
+            .iter()
+            .find(|class| is_path(&class.name, path))
+        else {
+            return Ok(());
+        };
+        let is_private = class
+            .members
+            .iter()
+            .any(|member| *member.name == *token.source && member.is_static && member.is_private);
+        if is_private && !self.is_within_class(&class.name) {
+            return Err(CompileError {
+                kind: ErrorKind::PrivateAccess,
+                message: format!(
+                    "Cannot access private member '{}' of class '{}'",
+                    token.source, class.name
+                ),
+                line: token.line,
+                column: token.column,
+            });
+        }
         Ok(())
     }
 
-    fn binary(&mut self, token: Token) -> Result<(), CompileError> {
-        let next_precedence = match token.kind.precedence() {
-            Precedence::None
-            | Precedence::Construct
-            | Precedence::Delete
-            | Precedence::Path
-            | Precedence::Primary => unreachable!(),
-            Precedence::Assignment => Precedence::BitwiseOr,
-            Precedence::BitwiseOr => Precedence::BitwiseXor,
-            Precedence::BitwiseXor => Precedence::BitwiseAnd,
-            Precedence::BitwiseAnd => Precedence::Equality,
-            Precedence::Equality => Precedence::Comparison,
-            Precedence::Comparison => Precedence::BitwiseShift,
-            Precedence::BitwiseShift => Precedence::Term,
-            Precedence::Term => Precedence::Factor,
-            Precedence::Factor => Precedence::Unary,
-            Precedence::Unary | Precedence::Call => {
-                return Err(CompileError {
-                    message: "Expected binary operator".to_string(),
-                    line: token.line,
-                    column: token.column,
-                })
-            }
+    // Whether the class being compiled is `name` or one of its subclasses.
+    fn is_within_class(&self, name: &str) -> bool {
+        let Some(class) = &self.state.class else {
+            return false;
         };
-        self.expression_with_precedence(next_precedence)?;
+        *class.name == *name
+            || self
+                .ancestors(class.superclass.as_deref())
+                .iter()
+                .any(|ancestor| *ancestor.name == *name)
+    }
 
-        match token.kind {
-            TokenKind::Ampersand => self.write_action(swf::avm1::types::Action::BitAnd),
-            TokenKind::Bar => self.write_action(swf::avm1::types::Action::BitOr),
-            TokenKind::Caret => self.write_action(swf::avm1::types::Action::BitXor),
-            TokenKind::Percent => self.write_action(swf::avm1::types::Action::Modulo),
-            TokenKind::Plus => self.write_action(swf::avm1::types::Action::Add2),
-            TokenKind::Minus => self.write_action(swf::avm1::types::Action::Subtract),
-            TokenKind::Slash => self.write_action(swf::avm1::types::Action::Divide),
-            TokenKind::Star => self.write_action(swf::avm1::types::Action::Multiply),
-            TokenKind::DoubleEqual => self.write_action(swf::avm1::types::Action::Equals2),
-            TokenKind::TripleEqual => self.write_action(swf::avm1::types::Action::StrictEquals),
-            TokenKind::Greater => self.write_action(swf::avm1::types::Action::Greater),
-            TokenKind::DoubleGreater => self.write_action(swf::avm1::types::Action::BitRShift),
-            TokenKind::TripleGreater => self.write_action(swf::avm1::types::Action::BitURShift),
-            TokenKind::GreaterEqual => {
-                self.write_action(swf::avm1::types::Action::Less);
-                self.write_action(swf::avm1::types::Action::Not);
+    fn super_access(&mut self, token: &Token, access: Access<'_, 'a>) -> Result<(), CompileError> {
+        if !self.state.in_function {
+            return Err(CompileError {
+                kind: ErrorKind::Misplaced,
+                message: "Unexpected 'super' outside of function".to_string(),
+                line: token.line,
+                column: token.column,
+            });
+        }
+
+        match access {
+            Access::Call(arguments, _) | Access::Construct(arguments) => {
+                // Call the super constructor as a method with an undefined name.
+                self.arguments(arguments)?;
+                self.get_variable("super");
+                self.push(swf::avm1::types::Value::Undefined);
+                self.state.super_called = true;
+                if let Access::Construct(_) = access {
+                    self.write_action(swf::avm1::types::Action::NewMethod);
+                } else {
+                    self.write_action(swf::avm1::types::Action::CallMethod);
+                }
             }
-            TokenKind::Less => self.write_action(swf::avm1::types::Action::Less),
-            TokenKind::DoubleLess => self.write_action(swf::avm1::types::Action::BitLShift),
-            TokenKind::LessEqual => {
-                self.write_action(swf::avm1::types::Action::Greater);
-                self.write_action(swf::avm1::types::Action::Not);
+            _ => {
+                // Methods are called through `member`.
+                self.get_variable("super");
+                self.value_access(access)?;
             }
-            TokenKind::InstanceOf => self.write_action(swf::avm1::types::Action::InstanceOf),
-            _ => unreachable!(),
         }
 
         Ok(())
     }
 
-    fn builtin(
-        &mut self,
-        action: swf::avm1::types::Action,
-        arity: usize,
-    ) -> Result<(), CompileError> {
-        self.expect(TokenKind::LeftParen, "Expected '('")?;
-        self.comma_separated(|c| c.expression(), TokenKind::RightParen, Some(arity))?;
-        self.write_action(action);
-        Ok(())
+    // super();
+    fn super_call(&mut self) {
+        self.push(swf::avm1::types::Value::Int(0));
+        self.get_variable("super");
+        self.push(swf::avm1::types::Value::Undefined);
+        self.write_action(swf::avm1::types::Action::CallMethod);
+        self.write_action(swf::avm1::types::Action::Pop);
     }
 
-    fn expression_with_precedence(&mut self, precedence: Precedence) -> Result<(), CompileError> {
-        let token = self.read_token()?;
-        match token.kind {
-            TokenKind::LeftParen => self.grouping()?,
-            TokenKind::LeftSquareBrace => self.array()?,
-            TokenKind::LeftBrace => self.object()?,
-            TokenKind::New => self.construct()?,
-            TokenKind::Delete => self.delete()?,
-            TokenKind::Plus
-            | TokenKind::Minus
-            | TokenKind::Tilda
-            | TokenKind::Bang
-            | TokenKind::Throw
-            | TokenKind::Typeof => self.unary(token.kind)?,
-            TokenKind::DoublePlus | TokenKind::DoubleMinus => self.prefix(token.kind)?,
-            TokenKind::Number => {
-                let (source_without_radix, radix) = if let Some(source) = token
-                    .source
-                    .strip_prefix("0b")
-                    .or(token.source.strip_prefix("0B"))
-                {
-                    (source, 2)
-                } else if let Some(source) = token
-                    .source
-                    .strip_prefix("0o")
-                    .or(token.source.strip_prefix("0O"))
-                {
-                    (source, 8)
-                } else if let Some(source) = token
-                    .source
-                    .strip_prefix("0x")
-                    .or(token.source.strip_prefix("0X"))
-                {
-                    (source, 16)
-                } else {
-                    (token.source, 10)
-                };
+    fn identifier(
+        &mut self,
+        token: &Token<'a>,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        let name = token.source;
+        let is_assign = matches!(access, Access::Assign(..) | Access::Increment(_));
 
-                if let Ok(integer) = i32::from_str_radix(source_without_radix, radix) {
-                    self.push(swf::avm1::types::Value::Int(integer));
-                } else if radix != 10 {
-                    let integer =
-                        i64::from_str_radix(source_without_radix, radix).map_err(|_| {
-                            CompileError {
-                                message: "Number too large".to_string(),
-                                line: token.line,
-                                column: token.column,
-                            }
-                        })?;
-                    self.push(swf::avm1::types::Value::Double(integer as f64));
-                } else {
-                    let double = token.source.parse().unwrap();
-                    self.push(swf::avm1::types::Value::Double(double));
-                }
-            }
-            TokenKind::String => {
-                let string = &token.source[1..token.source.len() - 1];
-                self.push(swf::avm1::types::Value::Str(string.into()));
-            }
-            TokenKind::False => self.push(swf::avm1::types::Value::Bool(false)),
-            TokenKind::Null => self.push(swf::avm1::types::Value::Null),
-            TokenKind::True => self.push(swf::avm1::types::Value::Bool(true)),
-            TokenKind::Undefined => self.push(swf::avm1::types::Value::Undefined),
-            TokenKind::Function => self.function_expression()?,
-            TokenKind::Identifier => match token.source {
-                "call" => self.builtin(swf::avm1::types::Action::Call, 1)?,
-                "duplicateMovieClip" => self.builtin(swf::avm1::types::Action::CloneSprite, 3)?,
-                "chr" => self.builtin(swf::avm1::types::Action::AsciiToChar, 1)?,
-                "eval" => self.builtin(swf::avm1::types::Action::GetVariable, 1)?,
-                "getTimer" => self.builtin(swf::avm1::types::Action::GetTime, 0)?,
-                "int" => self.builtin(swf::avm1::types::Action::ToInteger, 1)?,
-                "length" => self.builtin(swf::avm1::types::Action::StringLength, 1)?,
-                "mbchr" => self.builtin(swf::avm1::types::Action::MBAsciiToChar, 1)?,
-                "mblength" => self.builtin(swf::avm1::types::Action::MBStringLength, 1)?,
-                "mbord" => self.builtin(swf::avm1::types::Action::MBCharToAscii, 1)?,
-                "mbsubstring" => self.builtin(swf::avm1::types::Action::MBStringExtract, 3)?,
-                "nextFrame" => self.builtin(swf::avm1::types::Action::NextFrame, 0)?,
-                "ord" => self.builtin(swf::avm1::types::Action::CharToAscii, 1)?,
-                "play" => self.builtin(swf::avm1::types::Action::Play, 0)?,
-                "prevFrame" => self.builtin(swf::avm1::types::Action::PreviousFrame, 0)?,
-                "random" => self.builtin(swf::avm1::types::Action::RandomNumber, 1)?,
-                "stop" => self.builtin(swf::avm1::types::Action::Stop, 0)?,
-                "stopAllSounds" => self.builtin(swf::avm1::types::Action::StopSounds, 0)?,
-                "stopDrag" => self.builtin(swf::avm1::types::Action::EndDrag, 0)?,
-                "targetPath" => self.builtin(swf::avm1::types::Action::TargetPath, 1)?,
-                "toggleHighQuality" => self.builtin(swf::avm1::types::Action::ToggleQuality, 0)?,
-                variable_name => self.variable_access(variable_name, precedence)?,
-            },
-            TokenKind::Eof => {
-                return Err(CompileError {
-                    message: "Unexpected end of file".to_string(),
-                    line: token.line,
-                    column: token.column,
-                })
-            }
-            _ => {
+        if let Some(value) = self.const_value(name).map(<[u8]>::to_vec) {
+            if is_assign {
                 return Err(CompileError {
-                    message: format!("Unexpected '{}'", token.source),
+                    kind: ErrorKind::ConstAssignment,
+                    message: format!("Cannot assign to constant '{}'", name),
                     line: token.line,
                     column: token.column,
-                })
+                });
             }
+            self.code.extend_actions(&value);
+            return self.value_access(access);
         }
 
-        while self.peek_token().kind.precedence() >= precedence {
-            let token = self.read_token()?;
-            match token.kind {
-                TokenKind::Dot => self.dot(precedence)?,
-                TokenKind::LeftSquareBrace => self.member_access(precedence)?,
-                _ => self.binary(token)?,
-            }
+        if self.class_member(name).is_some() {
+            return self.class_member_access(name, access, discard);
         }
 
-        if precedence.can_assign() {
-            let token = self.peek_token();
-            if token.kind == TokenKind::Equal {
-                return Err(CompileError {
-                    message: "Invalid assignment target".to_string(),
-                    line: token.line,
-                    column: token.column,
-                });
-            }
+        if let Some((path, _)) = self.class_reference(&[name]) {
+            self.global_path(&path[..path.len() - 1]);
+            return self.property_access(path[path.len() - 1], access, discard);
         }
 
-        if precedence.is_construct() {
-            let token = self.peek_token();
-            if token.kind.precedence() < Precedence::Construct
-                && token.kind.precedence() != Precedence::None
-            {
-                return Err(CompileError {
-                    message: "Invalid construct target".to_string(),
-                    line: token.line,
-                    column: token.column,
-                });
+        match name {
+            "Infinity" if !is_assign => {
+                self.push(swf::avm1::types::Value::Double(f64::INFINITY));
+                self.value_access(access)
             }
-        }
-
-        if precedence.is_delete() {
-            let token = self.peek_token();
-            if token.kind.precedence() < Precedence::Call
-                && token.kind.precedence() != Precedence::None
-            {
-                return Err(CompileError {
-                    message: "Invalid delete target".to_string(),
+            "NaN" if !is_assign => {
+                self.push(swf::avm1::types::Value::Double(f64::NAN));
+                self.value_access(access)
+            }
+            "super" => self.super_access(token, access),
+            _ if is_builtin(name) => match access {
+                Access::Call(arguments, end) => self.builtin_call(token, arguments, end, discard),
+                Access::Construct(_) => self.variable_access(token, access, discard),
+                _ => Err(CompileError {
+                    kind: ErrorKind::Syntax,
+                    message: "Expected '('".to_string(),
                     line: token.line,
                     column: token.column,
-                });
+                }),
+            },
+            _ => {
+                self.check_declared(token, &access)?;
+                if !self.is_declared_locally(name) {
+                    self.check_property(token);
+                    if let Access::Call(arguments, end) = access {
+                        self.check_arity(name, arguments, end);
+                    }
+                }
+                self.variable_access(token, access, discard)
             }
         }
-
-        Ok(())
-    }
-
-    fn expression(&mut self) -> Result<(), CompileError> {
-        self.expression_with_precedence(Precedence::Assignment)
     }
 
-    fn trace_statement(&mut self) -> Result<(), CompileError> {
-        self.expect(TokenKind::LeftParen, "Expected '(' before expression")?;
-        self.expression()?;
-        self.expect(TokenKind::RightParen, "Expected ')' after expression")?;
-        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
-        self.write_action(swf::avm1::types::Action::Trace);
-        Ok(())
+    // Whether `name` is a variable or function declared in the source, rather than a global.
+    fn is_declared_locally(&self, name: &str) -> bool {
+        self.state.declared.contains(&name)
+            || self.state.catch_vars.contains(&name)
+            || self.state.locals.contains(&name)
     }
 
-    fn variable_declaration(&mut self) -> Result<(), CompileError> {
-        let variable = self.expect(TokenKind::Identifier, "Expected variable name")?;
-        self.push(swf::avm1::types::Value::Str(variable.source.into()));
-        if self.consume(TokenKind::Equal)? {
-            self.expression()?;
-            self.write_action(swf::avm1::types::Action::DefineLocal);
-        } else {
-            self.write_action(swf::avm1::types::Action::DefineLocal2);
+    // Warn about accessing a property whose name is close to that of a movie clip property, but not
+    // the same, such as `_xScale`.
+    fn check_property(&mut self, token: &Token) {
+        let name = token.source;
+        let is_known = !name.starts_with('_')
+            || property_index(name).is_some()
+            || SPECIAL_VARIABLES
+                .iter()
+                .any(|(special, _, _)| *special == name)
+            || name.starts_with("_level");
+        if is_known {
+            return;
         }
-        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
-        Ok(())
-    }
-
-    fn function_body(&mut self, name: &str) -> Result<(), CompileError> {
-        let mut params = Vec::new();
-        self.expect(TokenKind::LeftParen, "Expected '('")?;
-        loop {
-            if self.consume(TokenKind::RightParen)? {
-                break;
-            }
-            let parameter = self.expect(TokenKind::Identifier, "Expected parameter name")?;
-            params.push(parameter.source.into());
-            if !self.consume(TokenKind::Comma)? {
-                self.expect(TokenKind::RightParen, "Expected ')'")?;
-                break;
-            }
+        if let Some(property) = suggest::closest(name, PROPERTIES.iter().copied()) {
+            let message = format!(
+                "Unknown movie clip property '{}', did you mean '{}'?",
+                name, property
+            );
+            self.state
+                .warn(Lint::Property, message, token.line, token.column);
         }
-
-        self.expect(TokenKind::LeftBrace, "Expected '{'")?;
-        let actions = self.nested(|c| c.block_statement())?;
-        self.write_action(swf::avm1::types::Action::DefineFunction(
-            swf::avm1::types::DefineFunction {
-                name: name.into(),
-                params,
-                actions: &actions,
-            },
-        ));
-        Ok(())
     }
 
-    fn function_declaration(&mut self) -> Result<(), CompileError> {
-        let name = self.expect(TokenKind::Identifier, "Expected function name")?;
-        self.function_body(name.source)
+    // Warn about a call of the global function or static method `path` with a wrong number of
+    // arguments.
+    fn check_arity(&mut self, path: &str, arguments: &[Expression<'a>], end: &Token) {
+        let Some((min, max)) = signatures::arity(path) else {
+            return;
+        };
+        if (min..=max).contains(&arguments.len()) {
+            return;
+        }
+        let token = arguments.get(max).map_or(end, Expression::token);
+        let message = signatures::arity_message(path, min, max, arguments.len());
+        self.state
+            .warn(Lint::Arity, message, token.line, token.column);
     }
 
-    fn function_expression(&mut self) -> Result<(), CompileError> {
-        let token = self.peek_token();
-        if token.kind == TokenKind::Identifier {
+    // Warn when reading a variable that is not declared before, nor built in. In strict mode, error
+    // when accessing one at all.
+    fn check_declared(&mut self, token: &Token<'a>, access: &Access) -> Result<(), CompileError> {
+        if !self.state.strict
+            && self
+                .state
+                .options
+                .lints
+                .severity(Lint::Undeclared)
+                .is_none()
+        {
+            return Ok(());
+        }
+        let name = token.source;
+        let is_declared = self.state.declared.contains(&name)
+            || self.state.catch_vars.contains(&name)
+            || SPECIAL_VARIABLES
+                .iter()
+                .any(|(special, _, _)| *special == name)
+            || self.explicit_register(name).is_some()
+            || PROPERTIES.contains(&name)
+            || GLOBALS.contains(&name)
+            || name
+                .strip_prefix("_level")
+                .is_some_and(|level| level.parse::<u32>().is_ok())
+            || self
+                .state
+                .class_infos
+                .iter()
+                .any(|class| *class.name == *name);
+        // Plain assignments declare variables of the timeline, rather than read them.
+        let is_read =
+            !matches!(access, Access::Assign(operator, _) if operator.kind == TokenKind::Equal);
+        if is_declared || !(self.state.strict || is_read) {
+            return Ok(());
+        }
+        let message = suggest::did_you_mean(
+            format!("Undeclared variable '{}'", name),
+            name,
+            self.state
+                .declared
+                .iter()
+                .chain(&self.state.catch_vars)
+                .chain(GLOBALS)
+                .copied(),
+        );
+        if self.state.strict {
             return Err(CompileError {
-                message: "Function expression must be anonymous".to_string(),
+                kind: ErrorKind::Unknown,
+                message,
                 line: token.line,
                 column: token.column,
             });
         }
-        self.function_body("")
-    }
-
-    fn expression_statement(&mut self) -> Result<(), CompileError> {
-        self.expression()?;
-        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
-        self.write_action(swf::avm1::types::Action::Pop);
+        self.state
+            .warn(Lint::Undeclared, message, token.line, token.column);
         Ok(())
     }
 
-    fn block_statement(&mut self) -> Result<(), CompileError> {
-        while !matches!(
-            self.peek_token().kind,
-            TokenKind::RightBrace | TokenKind::Eof
-        ) {
-            self.declaration()?;
+    // `object.name`, where `object` may be part of a dotted class name such as `a.b.C`.
+    fn member(
+        &mut self,
+        object: &Expression<'a>,
+        name: &Token<'a>,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        // Before SWF 5, only properties such as `_x` can be read and written, by target.
+        let feature = match (&access, access.update_operator()) {
+            (Access::Call(..), _) => Some("Calling a method".to_string()),
+            _ if property_index(name.source).is_none() => Some("Accessing a member".to_string()),
+            (_, Some(operator)) => Some(format!("'{}' on a property", operator.source)),
+            _ => None,
+        };
+        if let Some(feature) = feature {
+            self.require_swf_version(5, &feature, name.line, name.column)?;
         }
+        self.check_property(name);
+        let names = dotted_names(object);
+        if let (Some([class]), Access::Call(arguments, end)) = (names.as_deref(), &access) {
+            let is_global = self.const_value(class.source).is_none()
+                && self.class_member(class.source).is_none()
+                && !self.is_declared_locally(class.source);
+            if is_global {
+                let path = format!("{}.{}", class.source, name.source);
+                self.check_arity(&path, arguments, end);
+            }
+        }
+        if let Some(names) = &names {
+            let root = names[0].source;
+            if self.const_value(root).is_none() && self.class_member(root).is_none() {
+                let mut full: Vec<_> = names.iter().map(|token| token.source).collect();
+                full.push(name.source);
+                if let Some((mut path, dots)) = self.class_reference(&full) {
+                    self.global_path(&path[..path.len() - 1]);
+                    let class = path[path.len() - 1];
+                    if dots + 1 == full.len() {
+                        return self.property_access(class, access, discard);
+                    }
 
-        self.expect(TokenKind::RightBrace, "Expected '}' after block")?;
-        Ok(())
-    }
-
-    fn if_statement(&mut self) -> Result<(), CompileError> {
-        self.expect(TokenKind::LeftParen, "Expected '(' after if")?;
-        self.expression()?;
-        self.expect(TokenKind::RightParen, "Expected ')' after condition")?;
-        self.write_action(swf::avm1::types::Action::Not);
-
-        let if_body = self.nested(|c| c.statement())?;
-        self.write_action(swf::avm1::types::Action::If(swf::avm1::types::If {
-            offset: if_body.len().try_into().unwrap(),
-        }));
-        self.action_data.extend(if_body);
-
-        if self.consume(TokenKind::Else)? {
-            let else_body = self.nested(|c| c.statement())?;
-            self.write_action(swf::avm1::types::Action::Jump(swf::avm1::types::Jump {
-                offset: else_body.len().try_into().unwrap(),
-            }));
-            self.action_data.extend(else_body);
+                    self.property_access(class, Access::Get, false)?;
+                    for token in &names[dots + 1..] {
+                        self.check_private_access(&path, token)?;
+                        self.property_access(token.source, Access::Get, false)?;
+                        path.push(token.source);
+                    }
+                    self.check_private_access(&path, name)?;
+                    return self.property_access(name.source, access, discard);
+                }
+            }
         }
 
-        Ok(())
+        self.expression(object)?;
+        if let Some(names) = &names {
+            let path: Vec<_> = names.iter().map(|token| token.source).collect();
+            self.check_private_access(&path, name)?;
+        }
+        self.property_access(name.source, access, discard)
     }
 
-    fn label(&self) -> Label {
-        Label {
-            position: self.action_data.len(),
+    fn property_access(
+        &mut self,
+        name: &str,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        match access {
+            Access::Call(arguments, _) => {
+                // TODO: Error when calling a property?
+                self.method_arguments(arguments)?;
+                self.push(swf::avm1::types::Value::Str(name.into()));
+                self.write_action(swf::avm1::types::Action::CallMethod);
+            }
+            Access::Construct(arguments) => {
+                self.method_arguments(arguments)?;
+                self.push(swf::avm1::types::Value::Str(name.into()));
+                self.write_action(swf::avm1::types::Action::NewMethod);
+            }
+            Access::Delete => {
+                // TODO: Error when deleting a property?
+                self.push(swf::avm1::types::Value::Str(name.into()));
+                self.write_action(swf::avm1::types::Action::Delete);
+            }
+            _ => {
+                let property = property_index(name);
+                let push = |this: &mut Self| match property {
+                    Some(property) => this.push(swf::avm1::types::Value::Int(property)),
+                    None => this.push(swf::avm1::types::Value::Str(name.into())),
+                };
+                let duplicate = |this: &mut Self| {
+                    this.write_action(swf::avm1::types::Action::PushDuplicate);
+                    push(this);
+                    this.write_action(swf::avm1::types::Action::StackSwap);
+                };
+                let get = |this: &mut Self| match property {
+                    Some(_) => this.write_action(swf::avm1::types::Action::GetProperty),
+                    None => this.write_action(swf::avm1::types::Action::GetMember),
+                };
+                let set = |this: &mut Self, keep: bool| match property {
+                    Some(_) => this.write_set(swf::avm1::types::Action::SetProperty, keep),
+                    None => this.write_set(swf::avm1::types::Action::SetMember, keep),
+                };
+                self.access(push, duplicate, get, set, access, discard)?;
+            }
         }
-    }
 
-    fn jump(&mut self, label: &Label) {
-        const JUMP_SIZE: usize = 5;
+        Ok(())
+    }
 
-        let offset = self.action_data.len() - label.position;
-        self.write_action(swf::avm1::types::Action::Jump(swf::avm1::types::Jump {
-            offset: -i16::try_from(offset + JUMP_SIZE).unwrap(),
+    // `object[index]`, with the object already on the stack.
+    fn index_access(
+        &mut self,
+        index: &Expression<'a>,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        match access {
+            Access::Call(arguments, _) => {
+                self.method_arguments(arguments)?;
+                self.expression(index)?;
+                self.write_action(swf::avm1::types::Action::CallMethod);
+            }
+            Access::Construct(arguments) => {
+                self.method_arguments(arguments)?;
+                self.expression(index)?;
+                self.write_action(swf::avm1::types::Action::NewMethod);
+            }
+            Access::Delete => {
+                self.expression(index)?;
+                self.write_action(swf::avm1::types::Action::Delete);
+            }
+            _ => {
+                self.expression(index)?;
+                let push = |_this: &mut Self| {};
+                // Keep the index in register 0 while duplicating the object along with it.
+                let duplicate = |this: &mut Self| {
+                    this.write_action(swf::avm1::types::Action::StoreRegister(
+                        swf::avm1::types::StoreRegister { register: 0 },
+                    ));
+                    this.write_action(swf::avm1::types::Action::Pop);
+                    this.write_action(swf::avm1::types::Action::PushDuplicate);
+                    this.push(swf::avm1::types::Value::Register(0));
+                    this.write_action(swf::avm1::types::Action::StackSwap);
+                    this.push(swf::avm1::types::Value::Register(0));
+                };
+                let get = |this: &mut Self| this.write_action(swf::avm1::types::Action::GetMember);
+                let set = |this: &mut Self, keep: bool| {
+                    this.write_set(swf::avm1::types::Action::SetMember, keep)
+                };
+                self.access(push, duplicate, get, set, access, discard)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // `callee(arguments)`, where `eval(name)` may be assigned to.
+    fn call(
+        &mut self,
+        callee: &Expression<'a>,
+        arguments: &[Expression<'a>],
+        end: &Token<'a>,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        if let Expression::Identifier(token) = callee {
+            if token.source == "eval" && self.is_builtin_name(token.source) {
+                return self.eval_access(arguments, end, access, discard);
+            }
+        }
+
+        let discard = discard && matches!(access, Access::Get);
+        self.target(callee, Access::Call(arguments, end), discard)?;
+        self.value_access(access)
+    }
+
+    // Compile `expression`, used as `access`.
+    fn target(
+        &mut self,
+        expression: &Expression<'a>,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        match expression {
+            Expression::Identifier(token) => self.identifier(token, access, discard),
+            Expression::Member { object, name } => self.member(object, name, access, discard),
+            Expression::Index { object, index } => {
+                let token = index.token();
+                self.require_swf_version(5, "Accessing a member", token.line, token.column)?;
+                self.expression(object)?;
+                self.index_access(index, access, discard)
+            }
+            Expression::Call {
+                callee,
+                arguments,
+                end,
+            } => self.call(callee, arguments, end, access, discard),
+            _ => {
+                self.expression(expression)?;
+                self.value_access(access)
+            }
+        }
+    }
+
+    fn literal(&mut self, token: &Token) -> Result<(), CompileError> {
+        match token.kind {
+            TokenKind::Number => {
+                let value = number_value(token)?;
+                self.push(value);
+            }
+            TokenKind::String => {
+                let string = &token.source[1..token.source.len() - 1];
+                // The `Push` action records its size in 16 bits, including a type and terminator.
+                if string.len() > usize::from(u16::MAX) - 2 {
+                    return Err(CompileError {
+                        kind: ErrorKind::TooLarge,
+                        message: "String too long".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
+                self.push(swf::avm1::types::Value::Str(string.into()));
+            }
+            TokenKind::False => self.push(swf::avm1::types::Value::Bool(false)),
+            TokenKind::Null => self.push(swf::avm1::types::Value::Null),
+            TokenKind::True => self.push(swf::avm1::types::Value::Bool(true)),
+            TokenKind::Undefined => self.push(swf::avm1::types::Value::Undefined),
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn unary(
+        &mut self,
+        operator: &Token<'a>,
+        operand: &Expression<'a>,
+    ) -> Result<(), CompileError> {
+        // Fold signs of constant numbers.
+        if matches!(operator.kind, TokenKind::Plus | TokenKind::Minus) {
+            if let Some(number) = self.number_constant(operand)? {
+                let number = match operator.kind {
+                    TokenKind::Minus => -number,
+                    _ => number,
+                };
+                self.push(number_push_value(number));
+                return Ok(());
+            }
+        }
+
+        if matches!(
+            operator.kind,
+            TokenKind::Plus | TokenKind::Tilda | TokenKind::Typeof
+        ) {
+            let feature = format!("'{}'", operator.source);
+            self.require_swf_version(5, &feature, operator.line, operator.column)?;
+        }
+        match operator.kind {
+            TokenKind::Minus => self.push(swf::avm1::types::Value::Int(0)),
+            TokenKind::Tilda => self.push(swf::avm1::types::Value::Double(u32::MAX.into())),
+            _ => {}
+        }
+
+        self.expression(operand)?;
+
+        match operator.kind {
+            TokenKind::Plus => self.write_action(swf::avm1::types::Action::ToNumber),
+            TokenKind::Minus => self.write_action(swf::avm1::types::Action::Subtract),
+            TokenKind::Tilda => self.write_action(swf::avm1::types::Action::BitXor),
+            TokenKind::Bang | TokenKind::Not => self.write_action(swf::avm1::types::Action::Not),
+            TokenKind::Throw => {
+                self.require_swf_version(7, "'throw'", operator.line, operator.column)?;
+                self.write_action(swf::avm1::types::Action::Throw);
+            }
+            TokenKind::Typeof => self.write_action(swf::avm1::types::Action::TypeOf),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    // The value of `expression` if it is a constant number, such as `5`, `(5)`, `-5` or `Infinity`.
+    fn number_constant(&self, expression: &Expression<'a>) -> Result<Option<f64>, CompileError> {
+        let number = match expression {
+            Expression::Literal(token) if token.kind == TokenKind::Number => {
+                match number_value(token)? {
+                    swf::avm1::types::Value::Int(integer) => Some(integer.into()),
+                    swf::avm1::types::Value::Double(double) => Some(double),
+                    _ => unreachable!(),
+                }
+            }
+            // Unless shadowed, as in `identifier`.
+            Expression::Identifier(token)
+                if matches!(token.source, "Infinity" | "NaN")
+                    && self.const_value(token.source).is_none()
+                    && self.class_member(token.source).is_none()
+                    && self.class_reference(&[token.source]).is_none() =>
+            {
+                match token.source {
+                    "Infinity" => Some(f64::INFINITY),
+                    _ => Some(f64::NAN),
+                }
+            }
+            Expression::Grouping { expression, .. } => self.number_constant(expression)?,
+            Expression::Unary { operator, operand } => match operator.kind {
+                TokenKind::Plus => self.number_constant(operand)?,
+                TokenKind::Minus => self.number_constant(operand)?.map(|number| -number),
+                _ => None,
+            },
+            _ => None,
+        };
+        Ok(number)
+    }
+
+    fn prefix(
+        &mut self,
+        operator: &Token<'a>,
+        variable: &Token<'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        if self.const_value(variable.source).is_some() {
+            return Err(CompileError {
+                kind: ErrorKind::ConstAssignment,
+                message: format!("Cannot assign to constant '{}'", variable.source),
+                line: variable.line,
+                column: variable.column,
+            });
+        }
+        let register = self.register_index(variable.source);
+
+        if let Some(register) = register {
+            self.push(swf::avm1::types::Value::Register(register));
+        } else {
+            self.push(swf::avm1::types::Value::Str(variable.source.into()));
+            self.push(swf::avm1::types::Value::Str(variable.source.into()));
+            self.write_action(swf::avm1::types::Action::GetVariable);
+        }
+
+        self.increment(operator);
+
+        let keep = self.keep_assigned_value(discard, operator)?;
+        if let Some(register) = register {
+            self.write_action(swf::avm1::types::Action::StoreRegister(
+                swf::avm1::types::StoreRegister { register },
+            ));
+            if !keep {
+                self.write_action(swf::avm1::types::Action::Pop);
+            }
+        } else {
+            self.write_set(swf::avm1::types::Action::SetVariable, keep);
+        }
+
+        Ok(())
+    }
+
+    fn binary(
+        &mut self,
+        operator: &Token<'a>,
+        left: &Expression<'a>,
+        right: &Expression<'a>,
+    ) -> Result<(), CompileError> {
+        if self.state.options.swf_version < 5 && self.swf4_binary(operator, left, right)? {
+            return Ok(());
+        }
+        if matches!(
+            operator.kind,
+            TokenKind::Percent
+                | TokenKind::Ampersand
+                | TokenKind::Bar
+                | TokenKind::Caret
+                | TokenKind::DoubleLess
+                | TokenKind::DoubleGreater
+                | TokenKind::TripleGreater
+        ) {
+            let feature = format!("'{}'", operator.source);
+            self.require_swf_version(5, &feature, operator.line, operator.column)?;
+        }
+
+        self.expression(left)?;
+
+        if matches!(
+            operator.kind,
+            TokenKind::DoubleAmpersand | TokenKind::DoubleBar
+        ) {
+            // Short-circuit: keep the left operand if it is falsy (`&&`) or truthy (`||`).
+            let end = self.new_label();
+            self.write_action(swf::avm1::types::Action::PushDuplicate);
+            if operator.kind == TokenKind::DoubleAmpersand {
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            self.branch(end);
+            self.write_action(swf::avm1::types::Action::Pop);
+            self.expression(right)?;
+            self.define_label(end);
+            return Ok(());
+        }
+
+        self.expression(right)?;
+
+        match operator.kind {
+            TokenKind::Ampersand => self.write_action(swf::avm1::types::Action::BitAnd),
+            TokenKind::Bar => self.write_action(swf::avm1::types::Action::BitOr),
+            TokenKind::Caret => self.write_action(swf::avm1::types::Action::BitXor),
+            TokenKind::Percent => self.write_action(swf::avm1::types::Action::Modulo),
+            TokenKind::Plus => self.add(),
+            TokenKind::Minus => self.write_action(swf::avm1::types::Action::Subtract),
+            TokenKind::Slash => self.write_action(swf::avm1::types::Action::Divide),
+            TokenKind::Star => self.write_action(swf::avm1::types::Action::Multiply),
+            TokenKind::DoubleEqual => self.equals(),
+            TokenKind::TripleEqual => {
+                self.require_swf_version(6, "'==='", operator.line, operator.column)?;
+                self.write_action(swf::avm1::types::Action::StrictEquals);
+            }
+            TokenKind::BangEqual => {
+                self.equals();
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            TokenKind::BangDoubleEqual => {
+                self.require_swf_version(6, "'!=='", operator.line, operator.column)?;
+                self.write_action(swf::avm1::types::Action::StrictEquals);
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            TokenKind::Greater => self.greater(),
+            TokenKind::DoubleGreater => self.write_action(swf::avm1::types::Action::BitRShift),
+            TokenKind::TripleGreater => self.write_action(swf::avm1::types::Action::BitURShift),
+            TokenKind::GreaterEqual => {
+                self.less();
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            TokenKind::Less => self.less(),
+            TokenKind::DoubleLess => self.write_action(swf::avm1::types::Action::BitLShift),
+            TokenKind::LessEqual => {
+                self.greater();
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            TokenKind::InstanceOf => {
+                self.require_swf_version(6, "'instanceof'", operator.line, operator.column)?;
+                self.write_action(swf::avm1::types::Action::InstanceOf);
+            }
+            TokenKind::In => {
+                self.require_swf_version(6, "'in'", operator.line, operator.column)?;
+                self.in_operator();
+            }
+            // Flash 4 string and logical operators.
+            TokenKind::Add => self.write_action(swf::avm1::types::Action::StringAdd),
+            TokenKind::And => self.write_action(swf::avm1::types::Action::And),
+            TokenKind::Or => self.write_action(swf::avm1::types::Action::Or),
+            TokenKind::Eq => self.write_action(swf::avm1::types::Action::StringEquals),
+            TokenKind::Ne => {
+                self.write_action(swf::avm1::types::Action::StringEquals);
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            TokenKind::Lt => self.write_action(swf::avm1::types::Action::StringLess),
+            TokenKind::Gt => self.string_greater(),
+            TokenKind::Le => {
+                self.string_greater();
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            TokenKind::Ge => {
+                self.write_action(swf::avm1::types::Action::StringLess);
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    // Compile a binary operator of Flash 4 for SWF 4, which only has its untyped actions: arithmetic
+    // and comparisons convert their operands to numbers, and strings have their own operators. It
+    // lacks `StackSwap` too, so `a > b` is compared as `b < a`. If reordering them could change the
+    // result, `a` is first kept in the variable `$asc_left<n>`, which stays on the timeline, as SWF 4
+    // cannot delete variables. Return whether the operator is one of Flash 4.
+    fn swf4_binary(
+        &mut self,
+        operator: &Token<'a>,
+        left: &Expression<'a>,
+        right: &Expression<'a>,
+    ) -> Result<bool, CompileError> {
+        if matches!(
+            operator.kind,
+            TokenKind::DoubleAmpersand | TokenKind::DoubleBar
+        ) {
+            // Short-circuit like in later versions, but without `PushDuplicate` to keep the left
+            // operand, so the result is a boolean, as with `And` and `Or`.
+            let short_circuit = self.new_label();
+            let end = self.new_label();
+            self.expression(left)?;
+            if operator.kind == TokenKind::DoubleAmpersand {
+                self.write_action(swf::avm1::types::Action::Not);
+            }
+            self.branch(short_circuit);
+            self.expression(right)?;
+            self.write_action(swf::avm1::types::Action::Not);
+            self.write_action(swf::avm1::types::Action::Not);
+            self.jump(end);
+            self.define_label(short_circuit);
+            self.push(swf::avm1::types::Value::Bool(
+                operator.kind == TokenKind::DoubleBar,
+            ));
+            self.define_label(end);
+            return Ok(true);
+        }
+
+        let (first, second, actions): (_, _, &[swf::avm1::types::Action]) = match operator.kind {
+            TokenKind::Plus => (left, right, &[swf::avm1::types::Action::Add]),
+            TokenKind::Minus => (left, right, &[swf::avm1::types::Action::Subtract]),
+            TokenKind::Star => (left, right, &[swf::avm1::types::Action::Multiply]),
+            TokenKind::Slash => (left, right, &[swf::avm1::types::Action::Divide]),
+            TokenKind::DoubleEqual => (left, right, &[swf::avm1::types::Action::Equals]),
+            TokenKind::BangEqual => (
+                left,
+                right,
+                &[
+                    swf::avm1::types::Action::Equals,
+                    swf::avm1::types::Action::Not,
+                ],
+            ),
+            TokenKind::Less => (left, right, &[swf::avm1::types::Action::Less]),
+            TokenKind::GreaterEqual => (
+                left,
+                right,
+                &[
+                    swf::avm1::types::Action::Less,
+                    swf::avm1::types::Action::Not,
+                ],
+            ),
+            TokenKind::Greater => (right, left, &[swf::avm1::types::Action::Less]),
+            TokenKind::LessEqual => (
+                right,
+                left,
+                &[
+                    swf::avm1::types::Action::Less,
+                    swf::avm1::types::Action::Not,
+                ],
+            ),
+            TokenKind::And => (left, right, &[swf::avm1::types::Action::And]),
+            TokenKind::Or => (left, right, &[swf::avm1::types::Action::Or]),
+            TokenKind::Add => (left, right, &[swf::avm1::types::Action::StringAdd]),
+            TokenKind::Eq => (left, right, &[swf::avm1::types::Action::StringEquals]),
+            TokenKind::Ne => (
+                left,
+                right,
+                &[
+                    swf::avm1::types::Action::StringEquals,
+                    swf::avm1::types::Action::Not,
+                ],
+            ),
+            TokenKind::Lt => (left, right, &[swf::avm1::types::Action::StringLess]),
+            TokenKind::Ge => (
+                left,
+                right,
+                &[
+                    swf::avm1::types::Action::StringLess,
+                    swf::avm1::types::Action::Not,
+                ],
+            ),
+            TokenKind::Gt => (right, left, &[swf::avm1::types::Action::StringLess]),
+            TokenKind::Le => (
+                right,
+                left,
+                &[
+                    swf::avm1::types::Action::StringLess,
+                    swf::avm1::types::Action::Not,
+                ],
+            ),
+            _ => return Ok(false),
+        };
+        let is_swapped = matches!(
+            operator.kind,
+            TokenKind::Greater | TokenKind::LessEqual | TokenKind::Gt | TokenKind::Le
+        );
+        let is_literal = |expression: &Expression| matches!(expression, Expression::Literal(_));
+        let is_reorderable =
+            is_literal(left) || is_literal(right) || (is_pure(left) && is_pure(right));
+        if is_swapped && !is_reorderable {
+            let name = format!("$asc_left{}", self.state.swf4_comparisons);
+            self.push(swf::avm1::types::Value::Str(name.as_str().into()));
+            self.expression(left)?;
+            self.write_action(swf::avm1::types::Action::SetVariable);
+            self.state.swf4_comparisons += 1;
+            let result = self.expression(right);
+            self.state.swf4_comparisons -= 1;
+            result?;
+            self.push(swf::avm1::types::Value::Str(name.as_str().into()));
+            self.write_action(swf::avm1::types::Action::GetVariable);
+        } else {
+            self.expression(first)?;
+            self.expression(second)?;
+        }
+        for action in actions {
+            self.write_action(action.clone());
+        }
+        Ok(true)
+    }
+
+    // Increment or decrement the value on top of the stack by `operator`, as `value + 1` in SWF 4,
+    // which lacks `Increment` and `Decrement`.
+    fn increment(&mut self, operator: &Token) {
+        let increment = operator.kind == TokenKind::DoublePlus;
+        if self.state.options.swf_version < 5 {
+            self.push(swf::avm1::types::Value::Int(1));
+            if increment {
+                self.write_action(swf::avm1::types::Action::Add);
+            } else {
+                self.write_action(swf::avm1::types::Action::Subtract);
+            }
+        } else if increment {
+            self.write_action(swf::avm1::types::Action::Increment);
+        } else {
+            self.write_action(swf::avm1::types::Action::Decrement);
+        }
+    }
+
+    // Add the two values on top of the stack with `Add2`, or in SWF 4, which lacks it, as numbers.
+    fn add(&mut self) {
+        if self.state.options.swf_version < 5 {
+            self.write_action(swf::avm1::types::Action::Add);
+        } else {
+            self.write_action(swf::avm1::types::Action::Add2);
+        }
+    }
+
+    // Like `add`, for equality.
+    fn equals(&mut self) {
+        if self.state.options.swf_version < 5 {
+            self.write_action(swf::avm1::types::Action::Equals);
+        } else {
+            self.write_action(swf::avm1::types::Action::Equals2);
+        }
+    }
+
+    // Like `add`, for comparison.
+    fn less(&mut self) {
+        if self.state.options.swf_version < 5 {
+            self.write_action(swf::avm1::types::Action::Less);
+        } else {
+            self.write_action(swf::avm1::types::Action::Less2);
+        }
+    }
+
+    // Compare the two values on top of the stack with `Greater`, or before SWF 6, which lacks it,
+    // with `Less2` with the values swapped.
+    fn greater(&mut self) {
+        if self.state.options.swf_version < 6 {
+            self.write_action(swf::avm1::types::Action::StackSwap);
+            self.less();
+        } else {
+            self.write_action(swf::avm1::types::Action::Greater);
+        }
+    }
+
+    // Like `greater`, for strings.
+    fn string_greater(&mut self) {
+        if self.state.options.swf_version < 6 {
+            self.write_action(swf::avm1::types::Action::StackSwap);
+            self.write_action(swf::avm1::types::Action::StringLess);
+        } else {
+            self.write_action(swf::avm1::types::Action::StringGreater);
+        }
+    }
+
+    // Fail unless the targeted SWF version has `feature`, which first appeared in `version`.
+    fn require_swf_version(
+        &self,
+        version: u8,
+        feature: &str,
+        line: usize,
+        column: usize,
+    ) -> Result<(), CompileError> {
+        if self.state.options.swf_version >= version {
+            return Ok(());
+        }
+        Err(CompileError {
+            kind: ErrorKind::Unsupported,
+            message: format!(
+                "{} requires SWF version {} or later, but version {} is targeted",
+                feature, version, self.state.options.swf_version
+            ),
+            line,
+            column,
+        })
+    }
+
+    // Compile the arguments of a built-in function in order, checking their count.
+    fn builtin_arguments(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        arity: usize,
+    ) -> Result<(), CompileError> {
+        if arguments.len() != arity {
+            let token = arguments.get(arity).map_or(end, Expression::token);
+            return Err(CompileError {
+                kind: ErrorKind::Arity,
+                message: format!("Expected {} argument(s), got {}", arity, arguments.len()),
+                line: token.line,
+                column: token.column,
+            });
+        }
+
+        for argument in arguments {
+            self.expression(argument)?;
+        }
+        Ok(())
+    }
+
+    fn builtin(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        action: swf::avm1::types::Action,
+        arity: usize,
+    ) -> Result<(), CompileError> {
+        self.builtin_arguments(arguments, end, arity)?;
+        self.write_action(action);
+        Ok(())
+    }
+
+    fn builtin_call(
+        &mut self,
+        token: &Token<'a>,
+        arguments: &[Expression<'a>],
+        end: &Token<'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        match token.source {
+            "call" => self.builtin(arguments, end, swf::avm1::types::Action::Call, 1),
+            "duplicateMovieClip" => {
+                self.builtin(arguments, end, swf::avm1::types::Action::CloneSprite, 3)
+            }
+            "chr" => self.builtin(arguments, end, swf::avm1::types::Action::AsciiToChar, 1),
+            "eval" => self.eval_access(arguments, end, Access::Get, discard),
+            "getProperty" => self.property_builtin(arguments, end, false),
+            "getTimer" => self.builtin(arguments, end, swf::avm1::types::Action::GetTime, 0),
+            "gotoAndPlay" => self.goto_builtin(arguments, end, true),
+            "gotoAndStop" => self.goto_builtin(arguments, end, false),
+            "int" => self.builtin(arguments, end, swf::avm1::types::Action::ToInteger, 1),
+            "length" => self.builtin(arguments, end, swf::avm1::types::Action::StringLength, 1),
+            "loadMovie" => self.load_builtin(arguments, end, false, false),
+            "loadMovieNum" => self.load_builtin(arguments, end, false, true),
+            "loadVariables" => self.load_builtin(arguments, end, true, false),
+            "loadVariablesNum" => self.load_builtin(arguments, end, true, true),
+            "mbchr" => self.builtin(arguments, end, swf::avm1::types::Action::MBAsciiToChar, 1),
+            "mblength" => self.builtin(arguments, end, swf::avm1::types::Action::MBStringLength, 1),
+            "mbord" => self.builtin(arguments, end, swf::avm1::types::Action::MBCharToAscii, 1),
+            "mbsubstring" => {
+                self.builtin(arguments, end, swf::avm1::types::Action::MBStringExtract, 3)
+            }
+            "nextFrame" => self.builtin(arguments, end, swf::avm1::types::Action::NextFrame, 0),
+            // The compiled movie has a single scene, which is both the next and the previous one,
+            // so these go to its first frame.
+            "nextScene" | "prevScene" => {
+                self.builtin_arguments(arguments, end, 0)?;
+                self.write_action(swf::avm1::types::Action::GotoFrame(
+                    swf::avm1::types::GotoFrame { frame: 0 },
+                ));
+                Ok(())
+            }
+            "ord" => self.builtin(arguments, end, swf::avm1::types::Action::CharToAscii, 1),
+            "play" => self.builtin(arguments, end, swf::avm1::types::Action::Play, 0),
+            "prevFrame" => self.builtin(arguments, end, swf::avm1::types::Action::PreviousFrame, 0),
+            "random" => self.builtin(arguments, end, swf::avm1::types::Action::RandomNumber, 1),
+            "set" => self.set_builtin(token, arguments, end, discard),
+            "setProperty" => self.property_builtin(arguments, end, true),
+            "startDrag" => self.start_drag_builtin(arguments, end),
+            "stop" => self.builtin(arguments, end, swf::avm1::types::Action::Stop, 0),
+            "stopAllSounds" => {
+                self.builtin(arguments, end, swf::avm1::types::Action::StopSounds, 0)
+            }
+            "stopDrag" => self.builtin(arguments, end, swf::avm1::types::Action::EndDrag, 0),
+            "targetPath" => self.builtin(arguments, end, swf::avm1::types::Action::TargetPath, 1),
+            "toggleHighQuality" => {
+                self.builtin(arguments, end, swf::avm1::types::Action::ToggleQuality, 0)
+            }
+            _ => unreachable!(),
+        }?;
+
+        if !builtin_has_value(token.source) && self.keep_value(discard) {
+            // Stand in for the missing value.
+            self.push(swf::avm1::types::Value::Undefined);
+        }
+        Ok(())
+    }
+
+    // eval(name), which may be assigned to.
+    fn eval_access(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        access: Access<'_, 'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        self.builtin_arguments(arguments, end, 1)?;
+        if let Some(operator) = access.update_operator() {
+            let feature = format!("'{}' on 'eval'", operator.source);
+            self.require_swf_version(5, &feature, operator.line, operator.column)?;
+        }
+        let push = |_this: &mut Self| {};
+        let duplicate =
+            |this: &mut Self| this.write_action(swf::avm1::types::Action::PushDuplicate);
+        let get = |this: &mut Self| this.write_action(swf::avm1::types::Action::GetVariable);
+        let set = |this: &mut Self, keep: bool| {
+            this.write_set(swf::avm1::types::Action::SetVariable, keep)
+        };
+        self.access(push, duplicate, get, set, access, discard)
+    }
+
+    // set(name, value)
+    fn set_builtin(
+        &mut self,
+        token: &Token,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        self.builtin_arguments(arguments, end, 2)?;
+        let keep = self.keep_assigned_value(discard, token)?;
+        self.write_set(swf::avm1::types::Action::SetVariable, keep);
+        Ok(())
+    }
+
+    // gotoAndPlay([scene,] frame) and gotoAndStop([scene,] frame), where `scene` is the number of
+    // frames before the scene.
+    fn goto_builtin(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        set_playing: bool,
+    ) -> Result<(), CompileError> {
+        let (scene, frame) = match arguments {
+            [frame] => (None, frame),
+            [scene, frame] => (Some(scene), frame),
+            _ => {
+                let token = arguments.get(2).map_or(end, Expression::token);
+                return Err(CompileError {
+                    kind: ErrorKind::Arity,
+                    message: format!("Expected 1 or 2 argument(s), got {}", arguments.len()),
+                    line: token.line,
+                    column: token.column,
+                });
+            }
+        };
+
+        let mut scene_offset = 0;
+        if let Some(scene) = scene {
+            let token = match scene {
+                Expression::Literal(token) if token.kind == TokenKind::Number => token,
+                Expression::Literal(token) if token.kind == TokenKind::String => {
+                    return Err(CompileError {
+                        kind: ErrorKind::Unsupported,
+                        message: "Expected scene offset, as the movie has a single scene"
+                            .to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
+                _ => {
+                    let token = scene.token();
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidValue,
+                        message: "Expected scene offset".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
+            };
+            scene_offset = match number_value(token)? {
+                swf::avm1::types::Value::Int(offset) => offset.try_into().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| CompileError {
+                kind: ErrorKind::InvalidValue,
+                message: "Invalid scene offset".to_string(),
+                line: token.line,
+                column: token.column,
+            })?;
+        }
+
+        match frame {
+            Expression::Literal(token) if token.kind == TokenKind::Number => {
+                let frame = match number_value(token)? {
+                    swf::avm1::types::Value::Int(frame) => u16::try_from(frame - 1)
+                        .ok()
+                        .and_then(|frame| frame.checked_add(scene_offset)),
+                    _ => None,
+                }
+                .ok_or_else(|| CompileError {
+                    kind: ErrorKind::InvalidValue,
+                    message: "Invalid frame number".to_string(),
+                    line: token.line,
+                    column: token.column,
+                })?;
+                self.write_action(swf::avm1::types::Action::GotoFrame(
+                    swf::avm1::types::GotoFrame { frame },
+                ));
+            }
+            Expression::Literal(token) if token.kind == TokenKind::String => {
+                // Labels are unique across scenes, so a scene offset has nothing to apply to.
+                if let Some(scene) = scene {
+                    let token = scene.token();
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidValue,
+                        message: "Expected no scene offset along with a frame label".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
+                let label = &token.source[1..token.source.len() - 1];
+                self.write_action(swf::avm1::types::Action::GotoLabel(
+                    swf::avm1::types::GotoLabel {
+                        label: label.into(),
+                    },
+                ));
+            }
+            _ => {
+                self.expression(frame)?;
+                self.write_action(swf::avm1::types::Action::GotoFrame2(
+                    swf::avm1::types::GotoFrame2 {
+                        set_playing,
+                        scene_offset,
+                    },
+                ));
+                return Ok(());
+            }
+        }
+
+        if set_playing {
+            self.write_action(swf::avm1::types::Action::Play);
+        }
+        Ok(())
+    }
+
+    // loadMovie(url, target[, method]) and loadVariables(url, target[, method]), or their `Num`
+    // variants taking a level number instead of a target.
+    fn load_builtin(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        is_load_vars: bool,
+        is_level: bool,
+    ) -> Result<(), CompileError> {
+        let (url, target, method) = match arguments {
+            [url, target] => (url, target, None),
+            [url, target, method] => (url, target, Some(method)),
+            _ => {
+                let token = arguments.get(3).map_or(end, Expression::token);
+                return Err(CompileError {
+                    kind: ErrorKind::Arity,
+                    message: format!("Expected 2 or 3 argument(s), got {}", arguments.len()),
+                    line: token.line,
+                    column: token.column,
+                });
+            }
+        };
+
+        self.expression(url)?;
+        match target {
+            _ if !is_level => self.expression(target)?,
+            Expression::Literal(token) if token.kind == TokenKind::Number => {
+                let level = match number_value(token)? {
+                    swf::avm1::types::Value::Double(double) => number_push_value(double),
+                    value => value,
+                };
+                let swf::avm1::types::Value::Int(level) = level else {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidValue,
+                        message: "Invalid level number".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                };
+                let level = format!("_level{}", level);
+                self.push(swf::avm1::types::Value::Str(level.as_str().into()));
+            }
+            _ => {
+                self.push(swf::avm1::types::Value::Str("_level".into()));
+                self.expression(target)?;
+                self.write_action(swf::avm1::types::Action::StringAdd);
+            }
+        }
+
+        let method = match method {
+            Some(Expression::Literal(token)) if token.kind == TokenKind::String => {
+                match &token.source[1..token.source.len() - 1] {
+                    method if method.eq_ignore_ascii_case("GET") => {
+                        swf::avm1::types::SendVarsMethod::Get
+                    }
+                    method if method.eq_ignore_ascii_case("POST") => {
+                        swf::avm1::types::SendVarsMethod::Post
+                    }
+                    _ => {
+                        return Err(CompileError {
+                            kind: ErrorKind::InvalidValue,
+                            message: "Expected 'GET' or 'POST'".to_string(),
+                            line: token.line,
+                            column: token.column,
+                        })
+                    }
+                }
+            }
+            Some(method) => {
+                let token = method.token();
+                return Err(CompileError {
+                    kind: ErrorKind::InvalidValue,
+                    message: "Expected method".to_string(),
+                    line: token.line,
+                    column: token.column,
+                });
+            }
+            None => swf::avm1::types::SendVarsMethod::None,
+        };
+
+        let get_url = match (is_load_vars, is_level) {
+            (false, false) => swf::avm1::types::GetUrl2::for_load_movie(method),
+            (false, true) => swf::avm1::types::GetUrl2::for_get_url(method),
+            (true, _) => {
+                let mut get_url = swf::avm1::types::GetUrl2::for_load_vars(method);
+                get_url.set_is_target_sprite(!is_level);
+                get_url
+            }
+        };
+        self.write_action(swf::avm1::types::Action::GetUrl2(get_url));
+        Ok(())
+    }
+
+    // startDrag(target[, lockCenter[, left, top, right, bottom]])
+    fn start_drag_builtin(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+    ) -> Result<(), CompileError> {
+        if !matches!(arguments.len(), 1 | 2 | 6) {
+            // Any argument past the sixth is extra, otherwise some are missing.
+            let token = arguments.get(6).map_or(end, Expression::token);
+            return Err(CompileError {
+                kind: ErrorKind::Arity,
+                message: format!("Expected 1, 2 or 6 argument(s), got {}", arguments.len()),
+                line: token.line,
+                column: token.column,
+            });
+        }
+
+        // The action pops the target, the lock flag, the constraint flag and then the constraints.
+        let target = &arguments[0];
+        let lock_center = arguments.get(1);
+        let constraints = arguments.get(2..).unwrap_or_default();
+        for constraint in constraints {
+            self.expression(constraint)?;
+        }
+        self.push(swf::avm1::types::Value::Bool(!constraints.is_empty()));
+        match lock_center {
+            Some(lock_center) => self.expression(lock_center)?,
+            None => self.push(swf::avm1::types::Value::Bool(false)),
+        }
+        self.expression(target)?;
+        self.write_action(swf::avm1::types::Action::StartDrag);
+        Ok(())
+    }
+
+    // getProperty(target, property) and setProperty(target, property, value)
+    fn property_builtin(
+        &mut self,
+        arguments: &[Expression<'a>],
+        end: &Token,
+        is_set: bool,
+    ) -> Result<(), CompileError> {
+        let arity = if is_set { 3 } else { 2 };
+        if arguments.len() != arity {
+            let token = arguments.get(arity).map_or(end, Expression::token);
+            return Err(CompileError {
+                kind: ErrorKind::Arity,
+                message: format!("Expected {} argument(s), got {}", arity, arguments.len()),
+                line: token.line,
+                column: token.column,
+            });
+        }
+
+        self.expression(&arguments[0])?;
+        let Expression::Identifier(token) = &arguments[1] else {
+            let token = arguments[1].token();
+            return Err(CompileError {
+                kind: ErrorKind::InvalidValue,
+                message: "Expected property name".to_string(),
+                line: token.line,
+                column: token.column,
+            });
+        };
+        let Some(property) = property_index(token.source) else {
+            return Err(CompileError {
+                kind: ErrorKind::Unknown,
+                message: suggest::did_you_mean(
+                    format!("Unknown property '{}'", token.source),
+                    token.source,
+                    PROPERTIES.iter().copied(),
+                ),
+                line: token.line,
+                column: token.column,
+            });
+        };
+        self.push(swf::avm1::types::Value::Int(property));
+        if is_set {
+            self.expression(&arguments[2])?;
+            self.write_action(swf::avm1::types::Action::SetProperty);
+        } else {
+            self.write_action(swf::avm1::types::Action::GetProperty);
+        }
+        Ok(())
+    }
+
+    // Compile `expression`, whose value is unused by its statement if `discard` is set.
+    fn compile_expression(
+        &mut self,
+        expression: &Expression<'a>,
+        discard: bool,
+    ) -> Result<(), CompileError> {
+        match expression {
+            Expression::Literal(token) => self.literal(token)?,
+            Expression::Identifier(_)
+            | Expression::Member { .. }
+            | Expression::Index { .. }
+            | Expression::Call { .. } => self.target(expression, Access::Get, discard)?,
+            Expression::Constant { namespace, name } => {
+                let value = self.constant(namespace, name)?;
+                self.push(constant_value(&value));
+            }
+            Expression::Grouping { expression, .. } => self.expression(expression)?,
+            Expression::Array { bracket, elements } => {
+                self.require_swf_version(5, "An array literal", bracket.line, bracket.column)?;
+                self.arguments(elements)?;
+                self.write_action(swf::avm1::types::Action::InitArray);
+            }
+            Expression::Object { brace, properties } => {
+                self.require_swf_version(5, "An object literal", brace.line, brace.column)?;
+                self.object(properties)?;
+            }
+            Expression::Function(function) => self.function_body(function, "")?,
+            Expression::New {
+                keyword,
+                callee,
+                arguments,
+            } => {
+                self.require_swf_version(5, "'new'", keyword.line, keyword.column)?;
+                let arguments = arguments.as_deref().unwrap_or_default();
+                self.target(callee, Access::Construct(arguments), false)?;
+            }
+            Expression::Delete { keyword, target } => {
+                self.require_swf_version(5, "'delete'", keyword.line, keyword.column)?;
+                self.target(target, Access::Delete, false)?;
+            }
+            Expression::Unary { operator, operand } => self.unary(operator, operand)?,
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => self.binary(operator, left, right)?,
+            Expression::Assign {
+                operator,
+                target,
+                value,
+            } => self.target(target, Access::Assign(operator, value), discard)?,
+            Expression::Prefix { operator, name } => self.prefix(operator, name, discard)?,
+            Expression::Postfix { operator, target } => {
+                self.target(target, Access::Increment(operator), discard)?
+            }
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression<'a>) -> Result<(), CompileError> {
+        self.compile_expression(expression, false)
+    }
+
+    fn trace_statement(&mut self, arguments: &[Expression<'a>]) -> Result<(), CompileError> {
+        let (first, rest) = arguments.split_first().unwrap();
+        self.expression(first)?;
+        // Join multiple arguments with spaces.
+        for argument in rest {
+            self.push(swf::avm1::types::Value::Str(" ".into()));
+            self.write_action(swf::avm1::types::Action::StringAdd);
+            self.expression(argument)?;
+            self.write_action(swf::avm1::types::Action::StringAdd);
+        }
+        if self.state.options.trace_call {
+            self.push(swf::avm1::types::Value::Int(1));
+            self.push(swf::avm1::types::Value::Str("trace".into()));
+            self.write_action(swf::avm1::types::Action::CallFunction);
+            self.write_action(swf::avm1::types::Action::Pop);
+        } else {
+            self.write_action(swf::avm1::types::Action::Trace);
+        }
+        Ok(())
+    }
+
+    // The register accessed by `name`, either holding a variable or explicitly named.
+    fn register_index(&self, name: &str) -> Option<u8> {
+        self.variable_register(name)
+            .or_else(|| self.explicit_register(name))
+    }
+
+    // The register that variable `name` of the current function is kept in, if any.
+    fn variable_register(&self, name: &str) -> Option<u8> {
+        let (_, register) = self
+            .state
+            .registers
+            .iter()
+            .rfind(|(variable, _)| *variable == name)?;
+        Some(*register)
+    }
+
+    // Push the value of variable `name`, from its register if it is kept in one.
+    fn get_variable(&mut self, name: &'a str) {
+        if let Some(register) = self.variable_register(name) {
+            self.push(swf::avm1::types::Value::Register(register));
+        } else {
+            self.lookup(name);
+            self.push(swf::avm1::types::Value::Str(name.into()));
+            self.write_action(swf::avm1::types::Action::GetVariable);
+        }
+    }
+
+    // Record that the current function looks up variable `name` by name, so that it is not
+    // suppressed.
+    fn lookup(&mut self, name: &'a str) {
+        if SPECIAL_VARIABLES
+            .iter()
+            .any(|(special, _, _)| *special == name)
+            && !self.state.lookups.contains(&name)
+        {
+            self.state.lookups.push(name);
+        }
+    }
+
+    // The register explicitly named by `name`, either `@rN` or, unless disabled, `registerN`.
+    fn explicit_register(&self, name: &str) -> Option<u8> {
+        let index = match name.strip_prefix("@r") {
+            Some(index) => index,
+            None if self.state.options.explicit_registers => return None,
+            None => name.strip_prefix("register")?,
+        };
+        index.parse().ok()
+    }
+
+    fn declare_local(&mut self, name: &'a str) {
+        if self.state.in_function {
+            self.state.locals.push(name);
+        }
+        self.state.declared.push(name);
+    }
+
+    fn variable_declaration(
+        &mut self,
+        variable: &Token<'a>,
+        value: Option<&Expression<'a>>,
+    ) -> Result<(), CompileError> {
+        self.declare_local(variable.source);
+        if let Some(register) = self.variable_register(variable.source) {
+            // Without a value, the declaration leaves the register as it is.
+            if let Some(value) = value {
+                self.expression(value)?;
+                self.write_action(swf::avm1::types::Action::StoreRegister(
+                    swf::avm1::types::StoreRegister { register },
+                ));
+                self.write_action(swf::avm1::types::Action::Pop);
+            }
+            return Ok(());
+        }
+
+        // SWF 4 has no functions, and so no local variables either.
+        if self.state.options.swf_version < 5 {
+            if let Some(value) = value {
+                self.push(swf::avm1::types::Value::Str(variable.source.into()));
+                self.expression(value)?;
+                self.write_action(swf::avm1::types::Action::SetVariable);
+            }
+            return Ok(());
+        }
+
+        self.push(swf::avm1::types::Value::Str(variable.source.into()));
+        if let Some(value) = value {
+            self.expression(value)?;
+            self.write_action(swf::avm1::types::Action::DefineLocal);
+        } else {
+            self.write_action(swf::avm1::types::Action::DefineLocal2);
+        }
+        Ok(())
+    }
+
+    // The actions pushing the value of constant `name`, unless shadowed by a local variable.
+    fn const_value(&self, name: &str) -> Option<&[u8]> {
+        if self.state.locals.contains(&name) {
+            return None;
+        }
+        let (_, value) = self
+            .state
+            .consts
+            .iter()
+            .rfind(|(constant, _)| *constant == name)?;
+        Some(value)
+    }
+
+    // const NAME = value;
+    fn const_declaration(
+        &mut self,
+        name: &Token<'a>,
+        value: &Expression<'a>,
+    ) -> Result<(), CompileError> {
+        let value = self.nested(|c| c.const_expression(value))?.assemble()?;
+        self.state.consts.push((name.source, value));
+        Ok(())
+    }
+
+    // A literal, possibly signed, or another constant.
+    fn const_expression(&mut self, value: &Expression<'a>) -> Result<(), CompileError> {
+        match value {
+            Expression::Identifier(token) => {
+                let Some(value) = self.const_value(token.source).map(<[u8]>::to_vec) else {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidValue,
+                        message: "Expected constant value".to_string(),
+                        line: token.line,
+                        column: token.column,
+                    });
+                };
+                self.code.extend_actions(&value);
+                Ok(())
+            }
+            _ => self.expression(value),
+        }
+    }
+
+    fn function_body(&mut self, function: &Function<'a>, name: &str) -> Result<(), CompileError> {
+        let keyword = &function.keyword;
+        self.require_swf_version(5, "'function'", keyword.line, keyword.column)?;
+        log::debug!(
+            "{}:{}: Compiling function '{}'",
+            keyword.line,
+            keyword.column,
+            if name.is_empty() { "<anonymous>" } else { name }
+        );
+        let locals = self.state.locals.len();
+        let declared = self.state.declared.len();
+        let consts = self.state.consts.len();
+
+        // Keep parameters and local variables in registers, except for those that the body
+        // accesses by name. Register 0 is left to the compiler, and explicitly named registers to
+        // the body.
+        let scope = Scope::new(function);
+        self.warn_unused(function, &scope);
+        self.warn_shadowed(&scope);
+        self.warn_redeclared(&scope);
+        let reserved: Vec<_> = scope
+            .names()
+            .filter_map(|name| self.explicit_register(name))
+            .collect();
+        let registers = std::mem::take(&mut self.state.registers);
+        let register_count = std::mem::take(&mut self.state.register_count);
+        let lookups = std::mem::take(&mut self.state.lookups);
+
+        // Preload the special variables that the body uses into the first registers, unless the
+        // body names them explicitly.
+        let preloads: Vec<_> = SPECIAL_VARIABLES
+            .iter()
+            .filter(|(name, _, _)| scope.uses(name) && self.is_register_candidate(&scope, name))
+            .collect();
+        let mut flags = swf::avm1::types::FunctionFlags::empty();
+        if (1..=preloads.len()).all(|register| !reserved.contains(&(register as u8))) {
+            for (register, (name, preload, _)) in (1..).zip(preloads) {
+                self.state.registers.push((name, register));
+                self.use_register(register);
+                flags |= *preload;
+            }
+        }
+
+        let mut free_registers = (1..u8::MAX)
+            .filter(|register| !reserved.contains(register))
+            .skip(self.state.registers.len());
+        let mut params = Vec::new();
+        self.state
+            .declared
+            .extend(hoisted_functions(&function.body));
+        for parameter in &function.parameters {
+            self.state.locals.push(parameter.source);
+            self.state.declared.push(parameter.source);
+            let register = if self.is_register_candidate(&scope, parameter.source) {
+                free_registers.next()
+            } else {
+                None
+            };
+            if let Some(register) = register {
+                self.state.registers.push((parameter.source, register));
+                self.use_register(register);
+            }
+            params.push(swf::avm1::types::FunctionParam {
+                name: parameter.source.into(),
+                register_index: register.and_then(std::num::NonZeroU8::new),
+            });
+        }
+        for &name in scope.vars() {
+            if self.is_register_candidate(&scope, name) && self.variable_register(name).is_none() {
+                let Some(register) = free_registers.next() else {
+                    break;
+                };
+                self.state.registers.push((name, register));
+                self.use_register(register);
+            }
+        }
+
+        let loops = std::mem::take(&mut self.state.loops);
+        let label = self.state.label.take();
+        let jump_barrier = self.state.jump_barrier.take();
+        let catch_vars = std::mem::take(&mut self.state.catch_vars);
+        let in_function = std::mem::replace(&mut self.state.in_function, true);
+        let implicit_super = std::mem::take(&mut self.state.implicit_super);
+        let super_called = std::mem::take(&mut self.state.super_called);
+        let discarded = std::mem::take(&mut self.discarded);
+        let start = self.code.start_function();
+        self.statements(&function.body)?;
+        if implicit_super && !self.state.super_called {
+            let super_call = self.nested(|c| {
+                c.super_call();
+                Ok(())
+            })?;
+            self.code.prepend_function(start, super_call);
+        }
+        self.discarded = discarded;
+        let function_register_count =
+            std::mem::replace(&mut self.state.register_count, register_count);
+
+        // Suppress the special variables that the body never looks up by name.
+        for (name, preload, suppress) in SPECIAL_VARIABLES {
+            if !flags.contains(preload)
+                && !scope.is_dynamic()
+                && !self.state.lookups.contains(&name)
+            {
+                flags |= suppress;
+            }
+        }
+
+        self.state.lookups = lookups;
+        let function_registers = std::mem::replace(&mut self.state.registers, registers);
+        self.state.super_called = super_called;
+        self.state.loops = loops;
+        self.state.label = label;
+        self.state.jump_barrier = jump_barrier;
+        self.state.catch_vars = catch_vars;
+        self.state.in_function = in_function;
+        self.state.locals.truncate(locals);
+        self.state.declared.truncate(declared);
+        self.state.consts.truncate(consts);
+        // Before SWF 7, which lacks `DefineFunction2`, functions keep nothing in registers.
+        let action = if self.state.options.swf_version < 7 {
+            swf::avm1::types::Action::DefineFunction(swf::avm1::types::DefineFunction {
+                name: name.into(),
+                params: params.iter().map(|param| param.name).collect(),
+                actions: &[],
+            })
+        } else {
+            swf::avm1::types::Action::DefineFunction2(swf::avm1::types::DefineFunction2 {
+                name: name.into(),
+                register_count: function_register_count.try_into().unwrap_or(u8::MAX),
+                params,
+                flags,
+                actions: &[],
+            })
+        };
+        self.code.end_function(start, &action);
+
+        // Methods are named after their classes, which are compiled outside of functions.
+        let symbol_name = match (&function.name, &self.state.class) {
+            (Some(method), Some(class)) if !in_function => {
+                Some(format!("{}.{}", class.name, method.source))
+            }
+            (name, _) => name.as_ref().map(|name| name.source.to_string()),
+        };
+        let variables = scope.vars().iter().filter(|name| {
+            !function_registers
+                .iter()
+                .any(|(register_name, _)| register_name == *name)
+        });
+        let symbols = FunctionSymbols {
+            name: symbol_name,
+            file: None,
+            line: function.keyword.line,
+            column: function.keyword.column,
+            offset: 0,
+            body: 0..0,
+            parameters: function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.source.to_string())
+                .collect(),
+            registers: function_registers
+                .iter()
+                .map(|(name, register)| (name.to_string(), *register))
+                .collect(),
+            variables: variables.map(|name| name.to_string()).collect(),
+        };
+        self.code.describe_function(start, symbols);
+        Ok(())
+    }
+
+    // Whether `name` can be kept in a register throughout the body of a function with `scope`, if
+    // registers are enabled and the targeted SWF version has functions with registers.
+    fn is_register_candidate(&self, scope: &Scope, name: &str) -> bool {
+        self.state.options.optimizations.registers
+            && self.state.options.swf_version >= 7
+            && scope.is_register_candidate(name)
+    }
+
+    // Warn about the parameters and local variables of a function that it never reads. Names
+    // starting with `_` are left unused on purpose, and `arguments` may read any parameter.
+    fn warn_unused(&mut self, function: &Function<'a>, scope: &Scope<'a>) {
+        let parameters = function
+            .parameters
+            .iter()
+            .filter(|_| !scope.uses("arguments"))
+            .map(|parameter| ("parameter", parameter));
+        let vars = scope.unread_vars().map(|var| ("variable", var));
+        let unused: Vec<_> = parameters
+            .filter(|(_, parameter)| !scope.is_read(parameter.source))
+            .chain(vars)
+            .filter(|(_, token)| !token.source.starts_with('_'))
+            .map(|(kind, token)| {
+                (
+                    format!("Unused {} '{}'", kind, token.source),
+                    token.line,
+                    token.column,
+                )
+            })
+            .collect();
+        for (message, line, column) in unused {
+            self.state.warn(Lint::Unused, message, line, column);
+        }
+    }
+
+    // Warn about the declarations of a function that hide those of the same name outside of it.
+    fn warn_shadowed(&mut self, scope: &Scope<'a>) {
+        let shadowed: Vec<_> = scope
+            .bindings()
+            .filter(|binding| self.state.declared.contains(&binding.source))
+            .map(|binding| {
+                (
+                    format!("Declaration of '{}' shadows an outer one", binding.source),
+                    binding.line,
+                    binding.column,
+                )
+            })
+            .collect();
+        for (message, line, column) in shadowed {
+            self.state.warn(Lint::Shadow, message, line, column);
+        }
+    }
+
+    // Warn about names declared again in the same scope, pointing at their first declarations.
+    fn warn_redeclared(&mut self, scope: &Scope<'a>) {
+        for (first, again) in scope.redeclarations() {
+            let note = Diagnostic::note(
+                format!("'{}' is first declared here", first.source),
+                first.line,
+                first.column,
+            );
+            self.state.warn_with_notes(
+                Lint::Redeclared,
+                format!("Redeclaration of '{}'", again.source),
+                again.line,
+                again.column,
+                vec![note],
+            );
+        }
+    }
+
+    fn function_declaration(&mut self, function: &Function<'a>) -> Result<(), CompileError> {
+        let name = function.name.as_ref().unwrap();
+        self.declare_local(name.source);
+        self.function_body(function, name.source)
+    }
+
+    fn expression_statement(&mut self, expression: &Expression<'a>) -> Result<(), CompileError> {
+        self.compile_expression(expression, true)?;
+        if !std::mem::take(&mut self.discarded) {
+            self.write_action(swf::avm1::types::Action::Pop);
+        }
+        Ok(())
+    }
+
+    fn statements(&mut self, statements: &[Statement<'a>]) -> Result<(), CompileError> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn if_statement(
+        &mut self,
+        condition: &Expression<'a>,
+        body: &Statement<'a>,
+        else_body: Option<&Statement<'a>>,
+    ) -> Result<(), CompileError> {
+        let else_label = self.new_label();
+        self.expression(condition)?;
+        self.write_action(swf::avm1::types::Action::Not);
+        self.branch(else_label);
+        self.statement(body)?;
+
+        if let Some(else_body) = else_body {
+            let end = self.new_label();
+            self.jump(end);
+            self.define_label(else_label);
+            self.statement(else_body)?;
+            self.define_label(end);
+        } else {
+            self.define_label(else_label);
+        }
+
+        Ok(())
+    }
+
+    fn in_operator(&mut self) {
+        // Keep the key in register 0 and look for it among the enumerated names.
+        self.write_action(swf::avm1::types::Action::StackSwap);
+        self.write_action(swf::avm1::types::Action::StoreRegister(
+            swf::avm1::types::StoreRegister { register: 0 },
+        ));
+        self.write_action(swf::avm1::types::Action::Pop);
+        self.write_action(swf::avm1::types::Action::Enumerate2);
+
+        let start = self.new_label();
+        let not_found = self.new_label();
+        let drain = self.new_label();
+        let found = self.new_label();
+
+        self.define_label(start);
+        self.write_action(swf::avm1::types::Action::PushDuplicate);
+        self.push(swf::avm1::types::Value::Null);
+        self.write_action(swf::avm1::types::Action::Equals2);
+        self.branch(not_found);
+        self.code.enumerated(1);
+        self.push(swf::avm1::types::Value::Register(0));
+        self.write_action(swf::avm1::types::Action::Equals2);
+        self.write_action(swf::avm1::types::Action::Not);
+        self.branch(start);
+
+        // Found: pop the remaining names, up to and including the terminating `null`.
+        self.drain_names(drain);
+        self.push(swf::avm1::types::Value::Bool(true));
+        self.jump(found);
+
+        self.define_label(not_found);
+        self.write_action(swf::avm1::types::Action::Pop);
+        self.push(swf::avm1::types::Value::Bool(false));
+        self.define_label(found);
+    }
+
+    fn for_statement(
+        &mut self,
+        is_declaration: bool,
+        variable: &Token<'a>,
+        object: &Expression<'a>,
+        body: &Statement<'a>,
+    ) -> Result<(), CompileError> {
+        self.require_swf_version(6, "'for-in'", variable.line, variable.column)?;
+        if is_declaration {
+            self.declare_local(variable.source);
+        }
+        self.expression(object)?;
+        self.write_action(swf::avm1::types::Action::Enumerate2);
+
+        // Test for the terminating `null` at the bottom, after a jump to it on entry.
+        let start = self.new_label();
+        let condition = self.new_label();
+        let end = self.new_label();
+
+        self.jump(condition);
+        self.define_label(start);
+        self.code.enumerated(1);
+
+        // Store the enumerated name (left on the stack) into the loop variable.
+        if let Some(register) = self.register_index(variable.source) {
+            self.write_action(swf::avm1::types::Action::StoreRegister(
+                swf::avm1::types::StoreRegister { register },
+            ));
+            self.write_action(swf::avm1::types::Action::Pop);
+        } else {
+            self.push(swf::avm1::types::Value::Str(variable.source.into()));
+            self.write_action(swf::avm1::types::Action::StackSwap);
+            if is_declaration {
+                self.write_action(swf::avm1::types::Action::DefineLocal);
+            } else {
+                self.write_action(swf::avm1::types::Action::SetVariable);
+            }
+        }
+
+        let has_break = self.loop_body(end, condition, true, |c| c.statement(body))?;
+
+        self.define_label(condition);
+        self.write_action(swf::avm1::types::Action::PushDuplicate);
+        self.push(swf::avm1::types::Value::Null);
+        self.write_action(swf::avm1::types::Action::Equals2);
+        self.write_action(swf::avm1::types::Action::Not);
+        self.branch(start);
+
+        if has_break {
+            // Pop the remaining names, up to and including the terminating `null`.
+            self.drain_names(end);
+        } else {
+            // Pop the `null` that terminates the enumeration.
+            self.define_label(end);
+            self.write_action(swf::avm1::types::Action::Pop);
+        }
+
+        Ok(())
+    }
+
+    // Pop the names that `Enumerate2` left on the stack, in a loop at `label`, up to and including
+    // the terminating `null`.
+    fn drain_names(&mut self, label: Label) {
+        self.define_label(label);
+        self.code.enumerated(1);
+        self.push(swf::avm1::types::Value::Null);
+        self.write_action(swf::avm1::types::Action::Equals2);
+        self.write_action(swf::avm1::types::Action::Not);
+        self.branch(label);
+        self.code.enumerated(-1);
+    }
+
+    fn while_statement(
+        &mut self,
+        condition: &Expression<'a>,
+        body: &Statement<'a>,
+    ) -> Result<(), CompileError> {
+        let start = self.new_label();
+        let end = self.new_label();
+
+        if is_always_true(condition) {
+            self.define_label(start);
+            self.loop_body(end, start, false, |c| c.statement(body))?;
+            self.jump(start);
+        } else {
+            // Test the condition at the bottom, after a jump to it on entry.
+            let condition_label = self.new_label();
+            self.jump(condition_label);
+            self.define_label(start);
+            self.loop_body(end, condition_label, false, |c| c.statement(body))?;
+            self.define_label(condition_label);
+            self.expression(condition)?;
+            self.branch(start);
+        }
+        self.define_label(end);
+
+        Ok(())
+    }
+
+    fn do_while_statement(
+        &mut self,
+        body: &Statement<'a>,
+        condition: &Expression<'a>,
+    ) -> Result<(), CompileError> {
+        let start = self.new_label();
+        let condition_label = self.new_label();
+        let end = self.new_label();
+
+        self.define_label(start);
+        self.loop_body(end, condition_label, false, |c| c.statement(body))?;
+        self.define_label(condition_label);
+        if is_always_true(condition) {
+            self.jump(start);
+        } else {
+            self.expression(condition)?;
+            self.branch(start);
+        }
+        self.define_label(end);
+
+        Ok(())
+    }
+
+    fn loop_jump(
+        &mut self,
+        kind: LoopJumpKind,
+        keyword: &Token<'a>,
+        label: Option<&Token<'a>>,
+    ) -> Result<(), CompileError> {
+        if self.state.loops.is_empty() {
+            return Err(CompileError {
+                kind: ErrorKind::Misplaced,
+                message: format!("Unexpected '{}' outside of loop", keyword.source),
+                line: keyword.line,
+                column: keyword.column,
+            });
+        }
+
+        let depth = match label {
+            Some(label) => {
+                let index = self
+                    .state
+                    .loops
+                    .iter()
+                    .rposition(|l| l.label == Some(label.source))
+                    .ok_or_else(|| CompileError {
+                        kind: ErrorKind::Unknown,
+                        message: suggest::did_you_mean(
+                            format!("Unknown label '{}'", label.source),
+                            label.source,
+                            self.state.loops.iter().filter_map(|l| l.label),
+                        ),
+                        line: label.line,
+                        column: label.column,
+                    })?;
+                index + 1
+            }
+            None => self.state.loops.len(),
+        };
+
+        if let Some((barrier, name)) = self.state.jump_barrier {
+            if depth <= barrier {
+                return Err(CompileError {
+                    kind: ErrorKind::Misplaced,
+                    message: format!("Cannot jump out of {} block", name),
+                    line: keyword.line,
+                    column: keyword.column,
+                });
+            }
+        }
+
+        // Pop the names of the `for..in` loops that the jump leaves.
+        let enumerations = self.state.loops[depth..]
+            .iter()
+            .filter(|l| l.enumerates)
+            .count();
+        for _ in 0..enumerations {
+            let drain = self.new_label();
+            self.drain_names(drain);
+        }
+
+        let target = &mut self.state.loops[depth - 1];
+        let target = match kind {
+            LoopJumpKind::Break => {
+                target.has_break = true;
+                target.break_label
+            }
+            LoopJumpKind::Continue => target.continue_label,
+        };
+        self.jump(target);
+        Ok(())
+    }
+
+    fn labeled_statement(
+        &mut self,
+        label: &Token<'a>,
+        body: &Statement<'a>,
+    ) -> Result<(), CompileError> {
+        if self
+            .state
+            .loops
+            .iter()
+            .any(|l| l.label == Some(label.source))
+        {
+            return Err(CompileError {
+                kind: ErrorKind::Duplicate,
+                message: format!("Duplicate label '{}'", label.source),
+                line: label.line,
+                column: label.column,
+            });
+        }
+
+        self.state.label = Some(label.source);
+        self.statement(body)
+    }
+
+    fn try_statement(
+        &mut self,
+        body: &[Statement<'a>],
+        catch: Option<&Catch<'a>>,
+        finally: Option<&[Statement<'a>]>,
+    ) -> Result<(), CompileError> {
+        let (line, column) = self.code.position();
+        self.require_swf_version(7, "'try'", line, column)?;
+        let (try_body, catch_body, finally_body) = self.closed_block("try", |c| {
+            let try_body = c.nested(|c| c.statements(body))?;
+            let try_body = c.block(try_body, "Try block")?;
+
+            let catch_body = match catch {
+                Some(catch) => {
+                    c.state.catch_vars.push(catch.variable.source);
+                    let catch_body = c.nested(|c| c.statements(&catch.body));
+                    c.state.catch_vars.pop();
+                    Some((&catch.variable, c.block(catch_body?, "Catch block")?))
+                }
+                None => None,
+            };
+
+            let finally_body = match finally {
+                Some(finally) => {
+                    let finally_body = c.nested(|c| c.statements(finally))?;
+                    Some(c.block(finally_body, "Finally block")?)
+                }
+                None => None,
+            };
+
+            Ok((try_body, catch_body, finally_body))
+        })?;
+
+        self.write_action(swf::avm1::types::Action::Try(swf::avm1::types::Try {
+            try_body: &try_body.0,
+            catch_body: catch_body.as_ref().map(|(catch_var, (catch_body, _))| {
+                let catch_var = if let Some(register) = self.register_index(catch_var.source) {
+                    swf::avm1::types::CatchVar::Register(register)
+                } else {
+                    swf::avm1::types::CatchVar::Var(catch_var.source.into())
+                };
+                (catch_var, catch_body.as_ref())
+            }),
+            finally_body: finally_body
+                .as_ref()
+                .map(|(finally_body, _)| finally_body.as_slice()),
         }));
+        // The bodies of the blocks end the action, in order.
+        let blocks = std::iter::once(&try_body)
+            .chain(catch_body.as_ref().map(|(_, catch_body)| catch_body))
+            .chain(&finally_body);
+        let mut length: usize = blocks
+            .clone()
+            .map(|(action_data, _)| action_data.len())
+            .sum();
+        for (action_data, debug) in blocks {
+            self.code.map_embedded(length, debug);
+            length -= action_data.len();
+        }
+        Ok(())
+    }
+
+    fn throw_statement(
+        &mut self,
+        keyword: &Token<'a>,
+        value: Option<&Expression<'a>>,
+    ) -> Result<(), CompileError> {
+        self.require_swf_version(7, "'throw'", keyword.line, keyword.column)?;
+        if let Some(value) = value {
+            self.expression(value)?;
+        } else {
+            // Rethrow the exception of the enclosing catch block.
+            let Some(&catch_var) = self.state.catch_vars.last() else {
+                return Err(CompileError {
+                    kind: ErrorKind::Misplaced,
+                    message: "Expected expression after throw outside of catch block".to_string(),
+                    line: keyword.line,
+                    column: keyword.column,
+                });
+            };
+            if let Some(register) = self.register_index(catch_var) {
+                self.push(swf::avm1::types::Value::Register(register));
+            } else {
+                self.push(swf::avm1::types::Value::Str(catch_var.into()));
+                self.write_action(swf::avm1::types::Action::GetVariable);
+            }
+        }
+        self.write_action(swf::avm1::types::Action::Throw);
+        Ok(())
+    }
+
+    fn tell_target_statement(
+        &mut self,
+        target: &Expression<'a>,
+        body: &Statement<'a>,
+    ) -> Result<(), CompileError> {
+        match target {
+            Expression::Literal(token) if token.kind == TokenKind::String => {
+                let target = &token.source[1..token.source.len() - 1];
+                self.write_action(swf::avm1::types::Action::SetTarget(
+                    swf::avm1::types::SetTarget {
+                        target: target.into(),
+                    },
+                ));
+            }
+            _ => {
+                self.expression(target)?;
+                self.write_action(swf::avm1::types::Action::SetTarget2);
+            }
+        }
+
+        self.closed_block("tellTarget", |c| c.statement(body))?;
+
+        self.write_action(swf::avm1::types::Action::SetTarget(
+            swf::avm1::types::SetTarget { target: "".into() },
+        ));
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement<'a>) -> Result<(), CompileError> {
+        if let Some(token) = statement.token() {
+            self.code.statement(token.line, token.column);
+        }
+        match statement {
+            Statement::Empty => Ok(()),
+            Statement::Expression(expression) => self.expression_statement(expression),
+            Statement::Block(statements) => self.statements(statements),
+            Statement::Var { name, value } => self.variable_declaration(name, value.as_ref()),
+            Statement::Const { name, value } => self.const_declaration(name, value),
+            Statement::Function(function) => self.function_declaration(function),
+            Statement::If {
+                condition,
+                body,
+                else_body,
+                ..
+            } => self.if_statement(condition, body, else_body.as_deref()),
+            Statement::ForIn {
+                is_declaration,
+                variable,
+                object,
+                body,
+            } => self.for_statement(*is_declaration, variable, object, body),
+            Statement::While {
+                condition, body, ..
+            } => self.while_statement(condition, body),
+            Statement::DoWhile {
+                body, condition, ..
+            } => self.do_while_statement(body, condition),
+            Statement::Break { keyword, label } => {
+                self.loop_jump(LoopJumpKind::Break, keyword, label.as_ref())
+            }
+            Statement::Continue { keyword, label } => {
+                self.loop_jump(LoopJumpKind::Continue, keyword, label.as_ref())
+            }
+            Statement::Labeled { label, body } => self.labeled_statement(label, body),
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => self.try_statement(body, catch.as_ref(), finally.as_deref()),
+            Statement::Throw { keyword, value } => self.throw_statement(keyword, value.as_ref()),
+            Statement::TellTarget { target, body, .. } => self.tell_target_statement(target, body),
+            Statement::Trace { arguments, .. } => self.trace_statement(arguments),
+            Statement::Class(class) => self.class_declaration(class),
+            Statement::Intrinsic(intrinsic) => {
+                self.intrinsic_declaration(intrinsic);
+                Ok(())
+            }
+            Statement::Import(import) => self.import_declaration(import),
+            Statement::Conditional {
+                namespace,
+                name,
+                body,
+            } => self.conditional_declaration(namespace, name, body),
+        }
+    }
+
+    fn import_declaration(&mut self, import: &Import<'a>) -> Result<(), CompileError> {
+        let keyword = &import.keyword;
+        let path: Vec<_> = import.path.iter().map(|token| token.source).collect();
+        let file: std::path::PathBuf = path.iter().collect();
+        let file = file.with_extension("as");
+        let is_compiled = self.is_class(&path);
+        let name = self.state.interner.intern_path(&path);
+        self.state.imports.push(path);
+        if is_compiled {
+            return Ok(());
+        }
+        self.state.classes.push(name.clone());
+
+        let Some((file, source)) = self.state.options.classpath.iter().find_map(|directory| {
+            let file = directory.join(&file);
+            let source = SourceText::open(&file).ok()?;
+            Some((file, source))
+        }) else {
+            return Err(CompileError {
+                kind: ErrorKind::Import,
+                message: format!("Cannot find class '{}' in classpath", name),
+                line: keyword.line,
+                column: keyword.column,
+            });
+        };
+
+        // Compile the class in place, sharing the set of already compiled classes.
+        log::debug!("Importing class '{}' from {}", name, file.display());
+        let mut preprocessor = Preprocessor::new(source.as_str());
+        preprocessor.inherit_defines(import.defines.clone());
+        let mut parser = self.state.options.parser(preprocessor);
+        let mut state = CompilerState::new(&self.state.options);
+        let index = self.state.files.len();
+        self.state.files.push(file.clone());
+        state.files = std::mem::take(&mut self.state.files);
+        state.classes = std::mem::take(&mut self.state.classes);
+        state.class_infos = std::mem::take(&mut self.state.class_infos);
+        state.interner = std::mem::take(&mut self.state.interner);
+        state.timings = self.state.timings.take();
+        // Lend the visitor, shortening its lifetime to that of the imported source.
+        state.visitor = self
+            .state
+            .visitor
+            .as_deref_mut()
+            .map(|visitor| visitor as &mut dyn VisitMut);
+        let mut compiler = Compiler::new(&mut state);
+        let result = parse(&mut parser, &mut compiler.state.timings, Some(&file))
+            // The first error of the imported file stands for the others.
+            .map_err(|errors| errors.into_iter().next().unwrap())
+            .and_then(|mut program| {
+                compiler.state.strict = compiler.state.options.strict || parser.is_strict();
+                if let Some(visitor) = &mut compiler.state.visitor {
+                    visitor.visit_program(&mut program);
+                }
+                let start = Instant::now();
+                let result = compiler.program(&program);
+                compiler.state.record_phase("Compiling", Some(&file), start);
+                result
+            });
+        let code = std::mem::take(&mut compiler.code);
+        let result = result.and_then(|()| {
+            let start = Instant::now();
+            let result = compiler.assemble(code);
+            compiler
+                .state
+                .record_phase("Assembling", Some(&file), start);
+            result
+        });
+        state.suppress(source.as_str());
+        self.state.files = state.files;
+        self.state.classes = state.classes;
+        self.state.class_infos = state.class_infos;
+        self.state.interner = state.interner;
+        self.state.timings = state.timings;
+        let diagnostics = state.diagnostics.into_iter().map(|diagnostic| Diagnostic {
+            message: format!(
+                "{}:{}:{}: {}",
+                file.display(),
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.message
+            ),
+            line: keyword.line,
+            column: keyword.column,
+            ..diagnostic
+        });
+        self.state.diagnostics.extend(diagnostics);
+        let (action_data, debug) = result.map_err(|error| CompileError {
+            kind: error.kind,
+            message: format!(
+                "{}:{}:{}: {}",
+                file.display(),
+                error.line,
+                error.column,
+                error.message
+            ),
+            line: keyword.line,
+            column: keyword.column,
+        })?;
+        // The statements of the imported file map to it, and those of its imports to theirs.
+        self.code.extend_mapped(&action_data, &debug.in_file(index));
+        Ok(())
+    }
+
+    // object.addProperty(name, object.__get__name, object.__set__name);
+    fn add_property(&mut self, accessor: &Accessor) {
+        self.write_action(swf::avm1::types::Action::PushDuplicate);
+        for (kind, defined) in [("set", accessor.has_setter), ("get", accessor.has_getter)] {
+            if defined {
+                self.write_action(swf::avm1::types::Action::PushDuplicate);
+                let method_name = format!("__{}__{}", kind, accessor.name);
+                self.push(swf::avm1::types::Value::Str(method_name.as_str().into()));
+                self.write_action(swf::avm1::types::Action::GetMember);
+            } else {
+                self.write_action(swf::avm1::types::Action::DefineFunction(
+                    swf::avm1::types::DefineFunction {
+                        name: "".into(),
+                        params: Vec::new(),
+                        actions: &[],
+                    },
+                ));
+            }
+            self.write_action(swf::avm1::types::Action::StackSwap);
+        }
+        self.push(swf::avm1::types::Value::Str(accessor.name.into()));
+        self.write_action(swf::avm1::types::Action::StackSwap);
+        self.push(swf::avm1::types::Value::Int(3));
+        self.write_action(swf::avm1::types::Action::StackSwap);
+        self.push(swf::avm1::types::Value::Str("addProperty".into()));
+        self.write_action(swf::avm1::types::Action::CallMethod);
+        self.write_action(swf::avm1::types::Action::Pop);
     }
 
-    fn for_statement(&mut self) -> Result<(), CompileError> {
-        self.expect(TokenKind::LeftParen, "Expected '(' after for")?;
-        self.expect(TokenKind::Var, "Expected 'var'")?;
-        let variable = self.expect(TokenKind::Identifier, "Expected variable name")?;
-        self.expect(TokenKind::In, "Expected 'in'")?;
-        self.expression()?;
-        self.write_action(swf::avm1::types::Action::Enumerate2);
-        self.expect(TokenKind::RightParen, "Expected ')'")?;
+    // An `intrinsic class`, declaring the members of a class implemented by the player.
+    fn intrinsic_declaration(&mut self, intrinsic: &Intrinsic<'a>) {
+        let path: Vec<_> = intrinsic.path.iter().map(|token| token.source).collect();
+        let superclass = intrinsic.superclass.as_ref().map(|superclass| {
+            let superclass: Vec<_> = superclass.iter().map(|token| token.source).collect();
+            self.state.interner.intern_path(&superclass)
+        });
 
-        let body = self.nested(|c| {
-            c.in_loop = true;
-            c.statement()
-        })?;
+        let mut members: Vec<Member> = Vec::new();
+        for member in &intrinsic.members {
+            if !members.iter().any(|info| *info.name == *member.name.source) {
+                members.push(Member {
+                    name: self.state.interner.intern(member.name.source),
+                    is_static: member.is_static,
+                    is_private: member.is_private,
+                });
+            }
+        }
 
-        const JUMP_SIZE: usize = 5;
-        let offset = body.len() + JUMP_SIZE * 2;
+        self.state.class_infos.push(ClassInfo {
+            name: self.state.interner.intern_path(&path),
+            superclass,
+            members,
+        });
+    }
 
-        let start = self.label();
-        self.write_action(swf::avm1::types::Action::PushDuplicate);
-        self.push(swf::avm1::types::Value::Null);
-        self.write_action(swf::avm1::types::Action::Equals2);
-        self.write_action(swf::avm1::types::Action::If(swf::avm1::types::If {
-            offset: offset.try_into().unwrap(),
-        }));
+    fn class_declaration(&mut self, class: &ast::Class<'a>) -> Result<(), CompileError> {
+        if let Some(superclass) = &class.superclass {
+            let token = &superclass[0];
+            self.require_swf_version(7, "'extends'", token.line, token.column)?;
+        }
+        let path: Vec<_> = class.path.iter().map(|token| token.source).collect();
+        let name = *path.last().unwrap();
+        let full_name = self.state.interner.intern_path(&path);
+        log::debug!("Compiling class '{}'", full_name);
+        if !self.state.classes.contains(&full_name) {
+            self.state.classes.push(full_name.clone());
+        }
 
-        self.push(swf::avm1::types::Value::Str(variable.source.into()));
+        let superclass = class.superclass.as_ref().map(|superclass| {
+            let superclass: Vec<_> = superclass.iter().map(|token| token.source).collect();
+            let mut same_package = path[..path.len() - 1].to_vec();
+            same_package.extend(&superclass);
+            match self.import_path(superclass[0]) {
+                Some(path) if superclass.len() == 1 => path.to_vec(),
+                _ if self.is_class(&same_package) => same_package,
+                _ => superclass,
+            }
+        });
+
+        let mut members = Vec::new();
+        let mut static_members = Vec::new();
+        for member in &class.members {
+            if member.is_static {
+                static_members.push(member.name().source);
+            } else {
+                members.push(member.name().source);
+            }
+        }
+        let superclass_name = superclass
+            .as_ref()
+            .map(|superclass| self.state.interner.intern_path(superclass));
+        self.state.class = Some(Class {
+            path: path.clone(),
+            name: full_name.clone(),
+            superclass: superclass_name.clone(),
+            members,
+            static_members,
+        });
+
+        // Each member expects its object on top of the stack, and leaves it there.
+        let mut constructor = None;
+        let mut statics = Code::new();
+        let mut prototype = Code::new();
+        let mut member_infos = Vec::new();
+        let mut accessors: Vec<Accessor> = Vec::new();
+        for member in &class.members {
+            let is_static = member.is_static;
+            let is_private = member.is_private;
+
+            match &member.kind {
+                ClassMemberKind::Var {
+                    name: member,
+                    value,
+                } => {
+                    member_infos.push(Member {
+                        name: self.state.interner.intern(member.source),
+                        is_static,
+                        is_private,
+                    });
+                    if let Some(value) = value {
+                        let initializer = self.nested(|c| {
+                            c.write_action(swf::avm1::types::Action::PushDuplicate);
+                            c.push(swf::avm1::types::Value::Str(member.source.into()));
+                            c.expression(value)?;
+                            c.write_action(swf::avm1::types::Action::SetMember);
+                            Ok(())
+                        })?;
+                        if is_static {
+                            statics.append(initializer);
+                        } else {
+                            prototype.append(initializer);
+                        }
+                    }
+                }
+                ClassMemberKind::Method { accessor, function } => {
+                    let member = function.name.as_ref().unwrap();
+                    if !member_infos.iter().any(|info| *info.name == *member.source) {
+                        member_infos.push(Member {
+                            name: self.state.interner.intern(member.source),
+                            is_static,
+                            is_private,
+                        });
+                    }
+                    if let Some(kind) = accessor {
+                        let kind = kind.source;
+                        let accessor = match accessors
+                            .iter_mut()
+                            .find(|accessor| accessor.name == member.source)
+                        {
+                            Some(accessor) if accessor.is_static == is_static => accessor,
+                            Some(_) => {
+                                return Err(CompileError {
+                                    kind: ErrorKind::Duplicate,
+                                    message: format!(
+                                        "Conflicting accessors for '{}'",
+                                        member.source
+                                    ),
+                                    line: member.line,
+                                    column: member.column,
+                                })
+                            }
+                            None => {
+                                accessors.push(Accessor {
+                                    name: member.source,
+                                    is_static,
+                                    has_getter: false,
+                                    has_setter: false,
+                                });
+                                accessors.last_mut().unwrap()
+                            }
+                        };
+                        let defined = if kind == "get" {
+                            std::mem::replace(&mut accessor.has_getter, true)
+                        } else {
+                            std::mem::replace(&mut accessor.has_setter, true)
+                        };
+                        if defined {
+                            return Err(CompileError {
+                                kind: ErrorKind::Duplicate,
+                                message: format!("Duplicate {}ter for '{}'", kind, member.source),
+                                line: member.line,
+                                column: member.column,
+                            });
+                        }
+
+                        let method_name = format!("__{}__{}", kind, member.source);
+                        let method = self.nested(|c| {
+                            c.write_action(swf::avm1::types::Action::PushDuplicate);
+                            c.push(swf::avm1::types::Value::Str(method_name.as_str().into()));
+                            c.function_body(function, "")?;
+                            c.write_action(swf::avm1::types::Action::SetMember);
+                            Ok(())
+                        })?;
+                        if is_static {
+                            statics.append(method);
+                        } else {
+                            prototype.append(method);
+                        }
+                    } else if member.source == name && !is_static {
+                        if constructor.is_some() {
+                            return Err(CompileError {
+                                kind: ErrorKind::Duplicate,
+                                message: "Duplicate constructor".to_string(),
+                                line: member.line,
+                                column: member.column,
+                            });
+                        }
+                        self.state.implicit_super = superclass.is_some();
+                        constructor = Some(self.nested(|c| c.function_body(function, ""))?);
+                    } else {
+                        let method = self.nested(|c| {
+                            c.write_action(swf::avm1::types::Action::PushDuplicate);
+                            c.push(swf::avm1::types::Value::Str(member.source.into()));
+                            c.function_body(function, "")?;
+                            c.write_action(swf::avm1::types::Action::SetMember);
+                            Ok(())
+                        })?;
+                        if is_static {
+                            statics.append(method);
+                        } else {
+                            prototype.append(method);
+                        }
+                    }
+                }
+            }
+        }
+        for accessor in &accessors {
+            let add_property = self.nested(|c| {
+                c.add_property(accessor);
+                Ok(())
+            })?;
+            if accessor.is_static {
+                statics.append(add_property);
+            } else {
+                prototype.append(add_property);
+            }
+        }
+        self.state.class = None;
+        self.state.class_infos.push(ClassInfo {
+            name: full_name,
+            superclass: superclass_name,
+            members: member_infos,
+        });
+
+        // if (!_global.path) {
+        let defined = self.new_label();
+        self.global_path(&path);
+        self.branch(defined);
+
+        // Create the enclosing packages.
+        for i in 1..path.len() {
+            let exists = self.new_label();
+            self.global_path(&path[..i]);
+            self.branch(exists);
+            self.global_path(&path[..i - 1]);
+            self.push(swf::avm1::types::Value::Str(path[i - 1].into()));
+            self.push(swf::avm1::types::Value::Int(0));
+            self.push(swf::avm1::types::Value::Str("Object".into()));
+            self.write_action(swf::avm1::types::Action::NewObject);
+            self.write_action(swf::avm1::types::Action::SetMember);
+            self.define_label(exists);
+        }
+
+        // _global.path = constructor;
+        match constructor {
+            Some(constructor) => self.code.append(constructor),
+            None => {
+                let actions = self
+                    .nested(|c| {
+                        if superclass.is_some() {
+                            c.super_call();
+                        }
+                        Ok(())
+                    })?
+                    .assemble()?;
+                self.write_action(swf::avm1::types::Action::DefineFunction(
+                    swf::avm1::types::DefineFunction {
+                        name: "".into(),
+                        params: Vec::new(),
+                        actions: &actions,
+                    },
+                ));
+            }
+        }
+        self.write_action(swf::avm1::types::Action::PushDuplicate);
+        self.global_path(&path[..path.len() - 1]);
         self.write_action(swf::avm1::types::Action::StackSwap);
-        self.write_action(swf::avm1::types::Action::DefineLocal);
+        self.push(swf::avm1::types::Value::Str(name.into()));
+        self.write_action(swf::avm1::types::Action::StackSwap);
+        self.write_action(swf::avm1::types::Action::SetMember);
+
+        if let Some(superclass) = superclass {
+            self.write_action(swf::avm1::types::Action::PushDuplicate);
+            self.global_path(&superclass);
+            self.write_action(swf::avm1::types::Action::Extends);
+        }
 
-        self.action_data.extend(body);
-        self.jump(&start);
+        self.code.append(statics);
+        self.push(swf::avm1::types::Value::Str("prototype".into()));
+        self.write_action(swf::avm1::types::Action::GetMember);
+        self.code.append(prototype);
 
+        // ASSetPropFlags(prototype, null, 1);
+        self.push(swf::avm1::types::Value::Int(1));
+        self.write_action(swf::avm1::types::Action::StackSwap);
+        self.push(swf::avm1::types::Value::Null);
+        self.write_action(swf::avm1::types::Action::StackSwap);
+        self.push(swf::avm1::types::Value::Int(3));
+        self.push(swf::avm1::types::Value::Str("ASSetPropFlags".into()));
+        self.write_action(swf::avm1::types::Action::CallFunction);
+        self.write_action(swf::avm1::types::Action::Pop);
+        // }
+        self.define_label(defined);
         Ok(())
     }
 
-    fn while_statement(&mut self) -> Result<(), CompileError> {
-        self.expect(TokenKind::LeftParen, "Expected '(' after while")?;
-        let condition = self.nested(|c| c.expression())?;
-        self.expect(TokenKind::RightParen, "Expected ')' after condition")?;
+    // The value of a conditional compilation constant such as `CONFIG::DEBUG`.
+    fn constant(&self, namespace: &Token, name: &Token) -> Result<String, CompileError> {
+        let name = format!("{}::{}", namespace.source, name.source);
+        match self
+            .state
+            .options
+            .constants
+            .iter()
+            .find(|(constant, _)| *constant == name)
+        {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(CompileError {
+                kind: ErrorKind::Unknown,
+                message: suggest::did_you_mean(
+                    format!("Undefined constant '{}'", name),
+                    &name,
+                    self.state
+                        .options
+                        .constants
+                        .iter()
+                        .map(|(constant, _)| constant.as_str()),
+                ),
+                line: namespace.line,
+                column: namespace.column,
+            }),
+        }
+    }
 
-        let body = self.nested(|c| {
-            c.in_loop = true;
-            c.statement()
-        })?;
+    // A declaration preceded by a constant such as `CONFIG::DEBUG`, compiled only if the constant
+    // is true. A skipped declaration is still checked for errors.
+    fn conditional_declaration(
+        &mut self,
+        namespace: &Token,
+        name: &Token,
+        body: &Statement<'a>,
+    ) -> Result<(), CompileError> {
+        let value = self.constant(namespace, name)?;
+        if is_truthy(&constant_value(&value)) {
+            return self.statement(body);
+        }
 
-        const JUMP_SIZE: usize = 5;
-        let offset = body.len() + JUMP_SIZE * 2;
+        let classes = self.state.classes.len();
+        let class_infos = self.state.class_infos.len();
+        let loops = self.state.loops.clone();
+        let locals = self.state.locals.len();
+        let declared = self.state.declared.len();
+        let consts = self.state.consts.len();
+        self.nested(|c| c.statement(body))?;
+        self.state.classes.truncate(classes);
+        self.state.class_infos.truncate(class_infos);
+        self.state.loops = loops;
+        self.state.locals.truncate(locals);
+        self.state.declared.truncate(declared);
+        self.state.consts.truncate(consts);
+        Ok(())
+    }
 
-        let start = self.label();
-        self.action_data.extend(&condition);
-        self.write_action(swf::avm1::types::Action::Not);
-        self.write_action(swf::avm1::types::Action::If(swf::avm1::types::If {
-            offset: offset.try_into().unwrap(),
-        }));
+    fn handler<E: Copy + std::ops::BitOrAssign>(
+        &mut self,
+        handler: &ast::Handler<'a>,
+        no_events: E,
+        key_press: E,
+        names: &[(&str, E)],
+    ) -> Result<Handler<E>, CompileError> {
+        let mut events = no_events;
+        let mut key = None;
+        for handler_event in &handler.events {
+            match handler_event {
+                Event::KeyPress(key_name) => {
+                    let code = key_code(&key_name.source[1..key_name.source.len() - 1])
+                        .ok_or_else(|| CompileError {
+                            kind: ErrorKind::Unknown,
+                            message: suggest::did_you_mean(
+                                format!("Unknown key {}", key_name.source),
+                                &key_name.source[1..key_name.source.len() - 1],
+                                KEYS.iter().map(|(name, _)| *name),
+                            ),
+                            line: key_name.line,
+                            column: key_name.column,
+                        })?;
+                    events |= key_press;
+                    key = Some(code);
+                }
+                Event::Name(name) => {
+                    let Some((_, event)) = names.iter().find(|(event, _)| *event == name.source)
+                    else {
+                        return Err(CompileError {
+                            kind: ErrorKind::Unknown,
+                            message: suggest::did_you_mean(
+                                format!(
+                                    "Unknown {} event '{}'",
+                                    handler.keyword.source, name.source
+                                ),
+                                name.source,
+                                names.iter().map(|(event, _)| *event),
+                            ),
+                            line: name.line,
+                            column: name.column,
+                        });
+                    };
+                    events |= *event;
+                }
+            }
+        }
+
+        let action_data = self.nested(|c| c.statements(&handler.body))?;
+        let (action_data, debug) = self.assemble(action_data)?;
+        // Button actions record the offset of the next ones in 16 bits.
+        if handler.keyword.source == "on" && action_data.len() > usize::from(u16::MAX) - 4 {
+            return Err(CompileError {
+                kind: ErrorKind::TooLarge,
+                message: "Handler too large".to_string(),
+                line: handler.keyword.line,
+                column: handler.keyword.column,
+            });
+        }
+        Ok(Handler {
+            events,
+            key_code: key,
+            action_data,
+            debug,
+        })
+    }
 
-        self.action_data.extend(body);
-        self.jump(&start);
+    fn program(&mut self, program: &Program<'a>) -> Result<(), CompileError> {
+        let statements = program.items.iter().filter_map(|item| match item {
+            Item::Statement(statement) => Some(statement),
+            _ => None,
+        });
+        self.state
+            .declared
+            .extend(hoisted_functions(statements.clone()));
+        if self
+            .state
+            .options
+            .lints
+            .severity(Lint::Redeclared)
+            .is_some()
+        {
+            self.warn_redeclared(&Scope::of_statements(statements));
+        }
+        for item in &program.items {
+            match item {
+                Item::Statement(statement) => self.statement(statement)?,
+                Item::Handler(handler) if handler.keyword.source == "on" => {
+                    let handler = self.handler(
+                        handler,
+                        swf::ButtonActionCondition::empty(),
+                        swf::ButtonActionCondition::KEY_PRESS,
+                        BUTTON_EVENTS,
+                    )?;
+                    self.state.button_handlers.push(handler);
+                }
+                Item::Handler(handler) => {
+                    let handler = self.handler(
+                        handler,
+                        swf::ClipEventFlag::empty(),
+                        swf::ClipEventFlag::KEY_PRESS,
+                        CLIP_EVENTS,
+                    )?;
+                    self.state.clip_handlers.push(handler);
+                }
+                Item::InitClip(init_clip) => {
+                    let action_data = self.nested(|c| c.statements(&init_clip.body))?;
+                    let (action_data, debug) = self.assemble(action_data)?;
+                    self.state.init_clips.push(InitClip {
+                        order: init_clip.order,
+                        action_data,
+                        debug,
+                    });
+                }
+                Item::Frame(frame) => self.frame(frame)?,
+                Item::Sprite(sprite) => {
+                    let action_data = self.nested(|c| c.statements(&sprite.body))?;
+                    let (action_data, debug) = self.assemble(action_data)?;
+                    self.state.sprites.push(Sprite {
+                        name: sprite
+                            .name
+                            .as_ref()
+                            .map(|token| &token.source[1..token.source.len() - 1]),
+                        frame: self.state.frame,
+                        action_data,
+                        debug,
+                    });
+                }
+                Item::Export(export) => self.export(export)?,
+            }
+        }
 
         Ok(())
     }
 
-    fn continue_statement(&mut self) -> Result<(), CompileError> {
-        if !self.in_loop {
-            // TODO: Tell exact location.
-            let token = self.peek_token();
+    // #export MySymbol 3
+    fn export(&mut self, export: &ast::Export<'a>) -> Result<(), CompileError> {
+        let name = match export.name.kind {
+            TokenKind::String => &export.name.source[1..export.name.source.len() - 1],
+            _ => export.name.source,
+        };
+        if self
+            .state
+            .exports
+            .iter()
+            .any(|exported| exported.name == name)
+        {
             return Err(CompileError {
-                message: format!("Unexpected 'continue' outside of loop"),
-                line: token.line,
-                column: token.column,
+                kind: ErrorKind::InvalidValue,
+                message: format!("Linkage name '{}' is already exported", name),
+                line: export.name.line,
+                column: export.name.column,
             });
         }
+        self.state.exports.push(Export {
+            name,
+            id: export.id,
+            position: (export.name.line, export.name.column),
+        });
+        Ok(())
+    }
+
+    // #frame 2 "label"
+    fn frame(&mut self, frame: &ast::Frame<'a>) -> Result<(), CompileError> {
+        let number = match &frame.number {
+            Some(token) => match token.source.parse() {
+                Ok(number) if number > self.state.frame => number,
+                _ => {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidValue,
+                        message: format!(
+                            "Expected a frame number after the current frame {}, up to {}",
+                            self.state.frame,
+                            u16::MAX
+                        ),
+                        line: token.line,
+                        column: token.column,
+                    });
+                }
+            },
+            None => self
+                .state
+                .frame
+                .checked_add(1)
+                .ok_or_else(|| CompileError {
+                    kind: ErrorKind::TooLarge,
+                    message: "Too many frames".to_string(),
+                    line: frame.directive.line,
+                    column: frame.directive.column,
+                })?,
+        };
+        let label = frame
+            .label
+            .as_ref()
+            .map(|token| &token.source[1..token.source.len() - 1]);
 
-        self.jump(&Label { position: 0 });
-        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+        let next = self.nested(|_| Ok(()))?;
+        let code = std::mem::replace(&mut self.code, next);
+        let (action_data, debug) = self.assemble(code)?;
+        self.state.frames.push(Frame {
+            number: self.state.frame,
+            label: self.state.frame_label,
+            action_data,
+            debug,
+            end: (frame.directive.line, frame.directive.column),
+        });
+        self.state.frame = number;
+        self.state.frame_label = label;
         Ok(())
     }
+}
 
-    fn try_statement(&mut self) -> Result<(), CompileError> {
-        self.expect(TokenKind::LeftBrace, "Expected '{'")?;
-        let try_body = self.nested(|c| c.block_statement())?;
+// An empty sprite, initialized by the `#initclip` block.
+fn init_clip_tags<'a>(
+    init_clip: &'a InitClip,
+    last_id: &mut swf::CharacterId,
+) -> Vec<swf::Tag<'a>> {
+    let sprite_id = *last_id + 1;
+    *last_id += 1;
+
+    let sprite = swf::Sprite {
+        id: sprite_id,
+        num_frames: 1,
+        tags: vec![swf::Tag::ShowFrame],
+    };
 
-        let catch_body = if self.consume(TokenKind::Catch)? {
-            self.expect(TokenKind::LeftParen, "Expected '('")?;
-            let catch_var = self.expect(TokenKind::Identifier, "Expected catch variable")?;
-            self.expect(TokenKind::RightParen, "Expected ')'")?;
+    vec![
+        swf::Tag::DefineSprite(sprite),
+        swf::Tag::DoInitAction(swf::DoInitAction {
+            id: sprite_id,
+            action_data: &init_clip.action_data,
+        }),
+    ]
+}
 
-            self.expect(TokenKind::LeftBrace, "Expected '{'")?;
-            let catch_body = self.nested(|c| c.block_statement())?;
+// A sprite with the actions of the `#sprite` block, placed at `depth`.
+fn sprite_tags<'a>(
+    sprite: &'a Sprite,
+    last_id: &mut swf::CharacterId,
+    depth: swf::Depth,
+) -> Vec<swf::Tag<'a>> {
+    let sprite_id = *last_id + 1;
+    *last_id += 1;
 
-            Some((catch_var, catch_body))
-        } else {
-            None
-        };
+    let define_sprite = swf::Sprite {
+        id: sprite_id,
+        num_frames: 1,
+        tags: vec![swf::Tag::DoAction(&sprite.action_data), swf::Tag::ShowFrame],
+    };
 
-        let finally_body = if self.consume(TokenKind::Finally)? {
-            self.expect(TokenKind::LeftBrace, "Expected '{'")?;
-            Some(self.nested(|c| c.block_statement())?)
-        } else {
-            None
-        };
+    let place_object = swf::PlaceObject {
+        version: 2,
+        action: swf::PlaceObjectAction::Place(sprite_id),
+        depth,
+        matrix: None,
+        color_transform: None,
+        ratio: None,
+        name: sprite.name.map(Into::into),
+        clip_depth: None,
+        class_name: None,
+        filters: None,
+        background_color: None,
+        blend_mode: None,
+        clip_actions: None,
+        is_image: false,
+        is_bitmap_cached: None,
+        is_visible: None,
+        amf_data: None,
+    };
 
-        // TODO: Validate existence of catch/finally?
+    vec![
+        swf::Tag::DefineSprite(define_sprite),
+        swf::Tag::PlaceObject(Box::new(place_object)),
+    ]
+}
 
-        self.write_action(swf::avm1::types::Action::Try(swf::avm1::types::Try {
-            try_body: &try_body,
-            catch_body: catch_body.as_ref().map(|(catch_var, catch_body)| {
-                let catch_var = if let Some(register) = register_index(catch_var.source) {
-                    swf::avm1::types::CatchVar::Register(register)
-                } else {
-                    swf::avm1::types::CatchVar::Var(catch_var.source.into())
-                };
-                (catch_var, catch_body.as_ref())
-            }),
-            finally_body: finally_body.as_deref(),
-        }));
-        Ok(())
+// `password` hashed with `salt` as the `Protect` and `EnableDebugger2` tags hold it, or empty if
+// empty.
+fn password_hash(password: &str, salt: &str) -> String {
+    if password.is_empty() {
+        return String::new();
     }
+    md5::crypt(password.as_bytes(), salt.as_bytes())
+}
 
-    fn statement(&mut self) -> Result<(), CompileError> {
-        if self.consume(TokenKind::LeftBrace)? {
-            self.block_statement()
-        } else if self.consume(TokenKind::If)? {
-            self.if_statement()
-        } else if self.consume(TokenKind::For)? {
-            self.for_statement()
-        } else if self.consume(TokenKind::While)? {
-            self.while_statement()
-        } else if self.consume(TokenKind::Continue)? {
-            self.continue_statement()
-        } else if self.consume(TokenKind::Try)? {
-            self.try_statement()
-        } else if self.consume(TokenKind::Trace)? {
-            self.trace_statement()
-        } else {
-            self.expression_statement()
-        }
+// Parse a file with `parser`, timing it if `timings` is set. `file` is the imported file, or `None`
+// for the compiled source.
+fn parse<'a>(
+    parser: &mut Parser<'a>,
+    timings: &mut Option<Timings>,
+    file: Option<&std::path::Path>,
+) -> Result<Program<'a>, Vec<CompileError>> {
+    let start = Instant::now();
+    if timings.is_some() {
+        parser.time_scanning();
     }
-
-    fn declaration(&mut self) -> Result<(), CompileError> {
-        if self.consume(TokenKind::Var)? {
-            self.variable_declaration()
-        } else if self.consume(TokenKind::Function)? {
-            self.function_declaration()
-        } else {
-            self.statement()
-        }
+    let program = parser.parse();
+    if let Some(timings) = timings {
+        timings.record_parsing(file, start, parser.scan_time().unwrap_or_default());
     }
+    program
+}
+
+// Declare the members of the built-in classes.
+fn intrinsic_classes(state: &mut CompilerState) {
+    const INTRINSICS: &str = include_str!("intrinsics.as");
+    let program = Parser::new(Preprocessor::new(INTRINSICS)).parse().unwrap();
+    let mut intrinsics = CompilerState::new(&CompileOptions::default());
+    intrinsics.interner = std::mem::take(&mut state.interner);
+    Compiler::new(&mut intrinsics).program(&program).unwrap();
+    state.class_infos = intrinsics.class_infos;
+    state.interner = intrinsics.interner;
+}
+
+// A button covering the stage, running the `on(...)` handlers.
+fn button_tags<'a>(
+    handlers: &'a [Handler<swf::ButtonActionCondition>],
+    stage_size: &swf::Rectangle,
+    last_id: &mut swf::CharacterId,
+    depth: swf::Depth,
+) -> Vec<swf::Tag<'a>> {
+    let shape_id = *last_id + 1;
+    let button_id = *last_id + 2;
+    *last_id += 2;
+
+    let width = stage_size.x_max - stage_size.x_min;
+    let height = stage_size.y_max - stage_size.y_min;
+    let shape = swf::Shape {
+        version: 1,
+        id: shape_id,
+        shape_bounds: stage_size.clone(),
+        edge_bounds: stage_size.clone(),
+        flags: swf::ShapeFlag::empty(),
+        styles: swf::ShapeStyles {
+            fill_styles: vec![swf::FillStyle::Color(swf::Color::from_rgb(0, 0))],
+            line_styles: vec![],
+        },
+        shape: vec![
+            swf::ShapeRecord::StyleChange(Box::new(swf::StyleChangeData {
+                move_to: Some((stage_size.x_min, stage_size.y_min)),
+                fill_style_0: None,
+                fill_style_1: Some(1),
+                line_style: None,
+                new_styles: None,
+            })),
+            swf::ShapeRecord::StraightEdge {
+                delta_x: width,
+                delta_y: swf::Twips::ZERO,
+            },
+            swf::ShapeRecord::StraightEdge {
+                delta_x: swf::Twips::ZERO,
+                delta_y: height,
+            },
+            swf::ShapeRecord::StraightEdge {
+                delta_x: -width,
+                delta_y: swf::Twips::ZERO,
+            },
+            swf::ShapeRecord::StraightEdge {
+                delta_x: swf::Twips::ZERO,
+                delta_y: -height,
+            },
+        ],
+    };
+
+    let button = swf::Button {
+        id: button_id,
+        is_track_as_menu: false,
+        records: vec![swf::ButtonRecord {
+            states: swf::ButtonState::all(),
+            id: shape_id,
+            depth: 1,
+            matrix: swf::Matrix::IDENTITY,
+            color_transform: swf::ColorTransform::default(),
+            filters: vec![],
+            blend_mode: swf::BlendMode::Normal,
+        }],
+        actions: handlers
+            .iter()
+            .map(|handler| swf::ButtonAction {
+                conditions: handler.events,
+                key_code: handler.key_code,
+                action_data: &handler.action_data,
+            })
+            .collect(),
+    };
+
+    let place_object = swf::PlaceObject {
+        version: 2,
+        action: swf::PlaceObjectAction::Place(button_id),
+        depth,
+        matrix: None,
+        color_transform: None,
+        ratio: None,
+        name: None,
+        clip_depth: None,
+        class_name: None,
+        filters: None,
+        background_color: None,
+        blend_mode: None,
+        clip_actions: None,
+        is_image: false,
+        is_bitmap_cached: None,
+        is_visible: None,
+        amf_data: None,
+    };
+
+    vec![
+        swf::Tag::DefineShape(shape),
+        swf::Tag::DefineButton2(Box::new(button)),
+        swf::Tag::PlaceObject(Box::new(place_object)),
+    ]
+}
+
+// An empty movie clip, running the `onClipEvent(...)` handlers.
+fn clip_tags<'a>(
+    handlers: &'a [Handler<swf::ClipEventFlag>],
+    last_id: &mut swf::CharacterId,
+    depth: swf::Depth,
+) -> Vec<swf::Tag<'a>> {
+    let sprite_id = *last_id + 1;
+    *last_id += 1;
+
+    let sprite = swf::Sprite {
+        id: sprite_id,
+        num_frames: 1,
+        tags: vec![swf::Tag::ShowFrame],
+    };
+
+    let place_object = swf::PlaceObject {
+        version: 2,
+        action: swf::PlaceObjectAction::Place(sprite_id),
+        depth,
+        matrix: None,
+        color_transform: None,
+        ratio: None,
+        name: None,
+        clip_depth: None,
+        class_name: None,
+        filters: None,
+        background_color: None,
+        blend_mode: None,
+        clip_actions: Some(
+            handlers
+                .iter()
+                .map(|handler| swf::ClipAction {
+                    events: handler.events,
+                    key_code: handler.key_code,
+                    action_data: &handler.action_data,
+                })
+                .collect(),
+        ),
+        is_image: false,
+        is_bitmap_cached: None,
+        is_visible: None,
+        amf_data: None,
+    };
+
+    vec![
+        swf::Tag::DefineSprite(sprite),
+        swf::Tag::PlaceObject(Box::new(place_object)),
+    ]
+}
+
+// The actions of each of the handlers of the character `id`, which the tag `tag` holds.
+fn handler_blocks<'h, E>(
+    tag: &'static str,
+    id: swf::CharacterId,
+    handlers: &'h [Handler<E>],
+) -> impl Iterator<Item = ActionBlock> + 'h {
+    handlers.iter().enumerate().map(move |(index, handler)| {
+        ActionBlock::new(tag, Some(id), Some(index), handler.debug.clone())
+    })
+}
+
+// Compile `source` into a SWF file written to `output`, returning warnings about the source, or
+// the errors along with them.
+pub fn compile<W: std::io::Write>(
+    source: &str,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    compile_with_options(source, &CompileOptions::default(), output)
+}
+
+pub fn compile_with_options<W: std::io::Write>(
+    source: &str,
+    options: &CompileOptions,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    compile_program(source, options, None, None, output)
+}
+
+// Compile the source that `reader` reads. The source is read as a whole, since tokens borrow
+// from it.
+pub fn compile_reader<R: std::io::Read, W: std::io::Write>(
+    reader: R,
+    options: &CompileOptions,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let source = SourceText::from_reader(reader).map_err(|error| {
+        vec![Diagnostic::from(CompileError {
+            kind: ErrorKind::Io,
+            message: format!("Cannot read source: {}", error),
+            line: 0,
+            column: 0,
+        })]
+    })?;
+    compile_with_options(source.as_str(), options, output)
+}
+
+// Compile the source file at `path`, read whole into memory.
+pub fn compile_file<W: std::io::Write>(
+    path: &std::path::Path,
+    options: &CompileOptions,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let source = SourceText::open(path).map_err(|error| {
+        vec![Diagnostic::from(CompileError {
+            kind: ErrorKind::Io,
+            message: format!("Cannot read {}: {}", path.display(), error),
+            line: 0,
+            column: 0,
+        })]
+    })?;
+    compile_with_options(source.as_str(), options, output)
+}
+
+// Compile like `compile_with_options`, also collecting statistics about the compiled code.
+pub fn compile_with_stats<W: std::io::Write>(
+    source: &str,
+    options: &CompileOptions,
+    output: W,
+) -> Result<(Vec<Diagnostic>, CompileStats), Vec<Diagnostic>> {
+    let mut stats = CompileStats::default();
+    let warnings = compile_program(source, options, None, Some(&mut stats), output)?;
+    Ok((warnings, stats))
+}
 
-    fn compile(&mut self) -> Result<(), CompileError> {
-        // Initialize `self.current`.
-        self.read_token()?;
+// Compile like `compile_with_options`, letting `visitor` transform the syntax tree of each file.
+pub fn compile_with_visitor<W: std::io::Write>(
+    source: &str,
+    options: &CompileOptions,
+    visitor: &mut dyn VisitMut,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    compile_program(source, options, Some(visitor), None, output)
+}
 
-        while self.peek_token().kind != TokenKind::Eof {
-            self.declaration()?;
+// Compile `source` into the action data of its main timeline alone, without a SWF file around it,
+// for embedding into tags of other files, returning warnings about the source along with it. Fails
+// if the source also has frames, button or clip event handlers, init clips or sprites, which need
+// tags of their own.
+pub fn compile_to_actions(
+    source: &str,
+    options: &CompileOptions,
+) -> Result<(Vec<Diagnostic>, Vec<u8>), Vec<Diagnostic>> {
+    compile_actions(source, options, None, false, |state, action_data, _| {
+        if let Some(export) = state.exports.first() {
+            let (line, column) = export.position;
+            return Err(CompileError {
+                kind: ErrorKind::Unsupported,
+                message: format!(
+                    "Exporting '{}' needs a tag of its own, and so a SWF file",
+                    export.name
+                ),
+                line,
+                column,
+            });
         }
+        if let Some(frame) = state.frames.first() {
+            let (line, column) = frame.end;
+            return Err(CompileError {
+                kind: ErrorKind::Unsupported,
+                message: "Frames need tags of their own, and so a SWF file".to_string(),
+                line,
+                column,
+            });
+        }
+        let handlers = state
+            .button_handlers
+            .iter()
+            .map(|handler| &handler.debug)
+            .chain(state.clip_handlers.iter().map(|handler| &handler.debug));
+        let init_clips = state.init_clips.iter().map(|init_clip| &init_clip.debug);
+        let sprites = state.sprites.iter().map(|sprite| &sprite.debug);
+        let Some(debug) = handlers.chain(init_clips).chain(sprites).next() else {
+            return Ok(action_data);
+        };
+        let (line, column) = debug
+            .mappings
+            .first()
+            .map_or((0, 0), |mapping| (mapping.line, mapping.column));
+        Err(CompileError {
+            kind: ErrorKind::Unsupported,
+            message:
+                "Event handlers, init clips and sprites need tags of their own, and so a SWF file"
+                    .to_string(),
+            line,
+            column,
+        })
+    })
+    .map(|(action_data, warnings)| (warnings, action_data))
+}
 
-        Ok(())
+// Compile `source` into the actions of its main timeline like `compile_to_actions`, as a list.
+pub fn compile_to_action_list(
+    source: &str,
+    options: &CompileOptions,
+) -> Result<(Vec<Diagnostic>, ActionList), Vec<Diagnostic>> {
+    let (warnings, action_data) = compile_to_actions(source, options)?;
+    Ok((warnings, ActionList::new(action_data)))
+}
+
+fn compile_program<W: std::io::Write>(
+    source: &str,
+    options: &CompileOptions,
+    visitor: Option<&mut dyn VisitMut>,
+    stats: Option<&mut CompileStats>,
+    mut output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let min_version = options.compression.min_swf_version();
+    if options.swf_version < min_version {
+        return Err(vec![Diagnostic::from(CompileError {
+            kind: ErrorKind::Unsupported,
+            message: format!(
+                "Cannot compress SWF version {} with {}, which requires version {} or later",
+                options.swf_version,
+                options.compression.name(),
+                min_version
+            ),
+            line: 0,
+            column: 0,
+        })]);
     }
+    if let Some(salt) = options
+        .password_salt
+        .as_deref()
+        .filter(|salt| !md5::is_salt(salt))
+    {
+        return Err(vec![Diagnostic::from(CompileError {
+            kind: ErrorKind::InvalidValue,
+            message: format!(
+                "Invalid password salt '{}', expected 1 to 8 of the characters ./0-9A-Za-z",
+                salt
+            ),
+            line: 0,
+            column: 0,
+        })]);
+    }
+    if (options.max_recursion_depth.is_some() || options.script_timeout.is_some())
+        && options.swf_version < 7
+    {
+        return Err(vec![Diagnostic::from(CompileError {
+            kind: ErrorKind::Unsupported,
+            message: format!(
+                "Cannot limit scripts in SWF version {}, which requires version 7 or later",
+                options.swf_version
+            ),
+            line: 0,
+            column: 0,
+        })]);
+    }
+    let timed = stats.is_some();
+    let (_, warnings) = compile_actions(
+        source,
+        options,
+        visitor,
+        timed,
+        |state, action_data, debug| {
+            write_program(state, action_data, debug, stats, &mut output).map_err(CompileError::from)
+        },
+    )?;
+    Ok(warnings)
 }
 
-pub fn compile<W: std::io::Write>(source: &str, output: W) -> Result<(), CompileError> {
-    let mut state = CompilerState::new(source);
+// Compile `source` into the action data of its main timeline, and finish with `finish`, which takes
+// the rest of the compiled program from the state of the compiler. Returns the result of `finish`
+// along with warnings about the source, or the errors along with them.
+fn compile_actions<T>(
+    source: &str,
+    options: &CompileOptions,
+    mut visitor: Option<&mut dyn VisitMut>,
+    timed: bool,
+    finish: impl FnOnce(&mut CompilerState, Vec<u8>, DebugInfo) -> Result<T, CompileError>,
+) -> Result<(T, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let prelude: String = options
+        .defines
+        .iter()
+        .map(|(name, value)| format!("#define {} {}\n", name, value))
+        .collect();
+    let mut preprocessor = Preprocessor::new(source);
+    preprocessor.set_prelude(&prelude);
+    let mut timings = timed.then(Timings::new);
+    let mut parser = options.parser(preprocessor);
+    let mut program = parse(&mut parser, &mut timings, None)
+        .map_err(|errors| errors.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    if let Some(visitor) = &mut visitor {
+        visitor.visit_program(&mut program);
+    }
+    let mut state = CompilerState::new(options);
+    state.timings = timings;
+    state.strict = options.strict || parser.is_strict();
+    let start = Instant::now();
+    intrinsic_classes(&mut state);
+    state.record_phase("Declaring intrinsics", None, start);
+    state.visitor = visitor.map(|visitor| visitor as &mut dyn VisitMut);
     let mut compiler = Compiler::new(&mut state);
-    compiler.compile()?;
+    let start = Instant::now();
+    if let Err(error) = compiler.program(&program) {
+        state.diagnostics.push(error.into());
+        state.suppress(source);
+        return Err(sorted(state.diagnostics));
+    }
+    compiler.state.record_phase("Compiling", None, start);
+    let code = std::mem::take(&mut compiler.code);
+    let start = Instant::now();
+    let (action_data, debug) = match compiler.assemble(code) {
+        Ok(assembled) => assembled,
+        Err(error) => {
+            state.diagnostics.push(error.into());
+            state.suppress(source);
+            return Err(sorted(state.diagnostics));
+        }
+    };
+    compiler.state.record_phase("Assembling", None, start);
+    state.suppress(source);
+    // Warnings made errors fail compiling too.
+    let has_errors = state
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error);
+    if has_errors {
+        return Err(sorted(state.diagnostics));
+    }
+    match finish(&mut state, action_data, debug) {
+        Ok(result) => Ok((result, sorted(state.diagnostics))),
+        Err(error) => {
+            state.diagnostics.push(error.into());
+            Err(sorted(state.diagnostics))
+        }
+    }
+}
 
-    const SWF_VERSION: u8 = 32;
+// Write the SWF file of the compiled program, with `action_data` as its main timeline, to `output`.
+fn write_program<W: std::io::Write>(
+    state: &mut CompilerState,
+    action_data: Vec<u8>,
+    debug: DebugInfo,
+    stats: Option<&mut CompileStats>,
+    output: W,
+) -> Result<(), swf::error::Error> {
+    let options = &state.options;
     let header = swf::Header {
-        compression: swf::Compression::None,
-        version: SWF_VERSION,
+        compression: match options.compression {
+            Compression::None => swf::Compression::None,
+            Compression::Zlib => swf::Compression::Zlib,
+            Compression::Lzma => swf::Compression::Lzma,
+        },
+        version: options.swf_version,
         stage_size: swf::Rectangle {
             x_min: swf::Twips::new(0),
-            x_max: swf::Twips::new(100),
+            x_max: swf::Twips::new(options.stage_width),
             y_min: swf::Twips::new(0),
-            y_max: swf::Twips::new(100),
+            y_max: swf::Twips::new(options.stage_height),
         },
-        frame_rate: swf::Fixed8::ONE,
-        num_frames: 0,
+        frame_rate: swf::Fixed8::from_f32(options.frame_rate),
+        num_frames: state.frame,
     };
-    let tags = vec![
+    let salt = options
+        .password_salt
+        .clone()
+        .unwrap_or_else(md5::random_salt);
+    let hash = |password: &str| password_hash(password, &salt);
+    let protect = options.protect.as_deref().map(hash);
+    let debugger_password = options.debugger_password.as_deref().map(hash);
+    let mut tags = vec![
         swf::Tag::FileAttributes(swf::FileAttributes::empty()),
-        swf::Tag::SetBackgroundColor(swf::Color::from_rgb(0xeeeeee, 255)),
-        swf::Tag::DoAction(&compiler.action_data),
-        swf::Tag::ShowFrame,
+        swf::Tag::SetBackgroundColor(swf::Color::from_rgb(options.background_color, 255)),
     ];
-    swf::write_swf(&header, &tags, output).unwrap();
+    if let Some(password) = &protect {
+        let password = (!password.is_empty()).then(|| password.as_str().into());
+        tags.push(swf::Tag::Protect(password));
+    }
+    if let Some(password) = &debugger_password {
+        tags.push(swf::Tag::EnableDebugger(password.as_str().into()));
+    }
+    if options.max_recursion_depth.is_some() || options.script_timeout.is_some() {
+        tags.push(swf::Tag::ScriptLimits {
+            max_recursion_depth: options.max_recursion_depth.unwrap_or(256),
+            timeout_in_seconds: options.script_timeout.unwrap_or(15),
+        });
+    }
+    let mut last_id = 0;
+    let mut source_map = SourceMap {
+        files: std::mem::take(&mut state.files),
+        blocks: Vec::new(),
+    };
+    if !state.button_handlers.is_empty() {
+        let button_id = last_id + 2;
+        tags.extend(button_tags(
+            &state.button_handlers,
+            &header.stage_size,
+            &mut last_id,
+            1,
+        ));
+        let blocks = handler_blocks("DefineButton2", button_id, &state.button_handlers);
+        source_map.blocks.extend(blocks);
+    }
+    if !state.clip_handlers.is_empty() {
+        let sprite_id = last_id + 1;
+        tags.extend(clip_tags(&state.clip_handlers, &mut last_id, 2));
+        let blocks = handler_blocks("PlaceObject2", sprite_id, &state.clip_handlers);
+        source_map.blocks.extend(blocks);
+    }
+    state.init_clips.sort_by_key(|init_clip| init_clip.order);
+    for init_clip in &state.init_clips {
+        let debug = init_clip.debug.clone();
+        let block = ActionBlock::new("DoInitAction", Some(last_id + 1), None, debug);
+        source_map.blocks.push(block);
+        tags.extend(init_clip_tags(init_clip, &mut last_id));
+    }
+    let mut exports = (!state.exports.is_empty()).then(|| {
+        let assets = state
+            .exports
+            .iter()
+            .map(|export| swf::ExportedAsset {
+                id: export.id,
+                name: export.name.into(),
+            })
+            .collect();
+        swf::Tag::ExportAssets(Box::new(assets))
+    });
+    state.frames.push(Frame {
+        number: state.frame,
+        label: state.frame_label,
+        action_data,
+        debug,
+        end: (0, 0),
+    });
+    // Frames without actions in between are left empty.
+    let mut frame_count = 0;
+    let mut sprites = state.sprites.iter().peekable();
+    // Sprites go above the button and the clip of the handlers.
+    let mut depth = 3;
+    for frame in &state.frames {
+        while frame_count + 1 < frame.number {
+            tags.push(swf::Tag::ShowFrame);
+            frame_count += 1;
+        }
+        while let Some(sprite) = sprites.next_if(|sprite| sprite.frame == frame.number) {
+            let debug = sprite.debug.clone();
+            let block = ActionBlock::new("DoAction", Some(last_id + 1), None, debug);
+            source_map.blocks.push(block);
+            tags.extend(sprite_tags(sprite, &mut last_id, depth));
+            depth += 1;
+        }
+        // Exported characters are defined by then, apart from sprites of later frames.
+        tags.extend(exports.take());
+        if let Some(label) = frame.label {
+            tags.push(swf::Tag::FrameLabel(swf::FrameLabel {
+                label: label.into(),
+                is_anchor: false,
+            }));
+        }
+        tags.extend([swf::Tag::DoAction(&frame.action_data), swf::Tag::ShowFrame]);
+        frame_count += 1;
+        let index = usize::from(frame.number - 1);
+        let debug = frame.debug.clone();
+        source_map
+            .blocks
+            .push(ActionBlock::new("DoAction", None, Some(index), debug));
+    }
+    log::debug!("Writing {} tags", tags.len());
+    let start = Instant::now();
+    let mut writer = CountingWriter {
+        inner: output,
+        count: 0,
+    };
+    swf::write_swf(&header, &tags, &mut writer)?;
+    let swf_size = writer.count;
+    state.record_phase("Writing", None, start);
+
+    if let Some(stats) = stats {
+        if let Some(timings) = state.timings.take() {
+            stats.phases = timings.phases;
+            stats.phases.sort_by_key(|phase| phase.start);
+        }
+        stats.swf_size = swf_size;
+        stats.source_map = source_map;
+        let frames = state.frames.iter().map(|frame| &frame.action_data);
+        let sprites = state.sprites.iter().map(|sprite| &sprite.action_data);
+        let handlers = state
+            .button_handlers
+            .iter()
+            .map(|handler| &handler.action_data)
+            .chain(
+                state
+                    .clip_handlers
+                    .iter()
+                    .map(|handler| &handler.action_data),
+            );
+        let init_clips = state
+            .init_clips
+            .iter()
+            .map(|init_clip| &init_clip.action_data);
+        for action_data in frames.chain(handlers).chain(init_clips).chain(sprites) {
+            stats.add_action_data(action_data);
+        }
+    }
     Ok(())
 }
+
+fn sorted(mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    diagnostics
+}
+
+// A writer that counts the bytes written through it, so that the SWF file needs no copy to tell
+// its size.
+struct CountingWriter<W> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: std::io::Write> std::io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The first error of compiling `source`, along with its position.
+    fn error(source: &str, options: &CompileOptions) -> (String, usize, usize) {
+        let diagnostics = compile_to_actions(source, options).err().unwrap();
+        let diagnostic = &diagnostics[0];
+        (
+            diagnostic.message.clone(),
+            diagnostic.line,
+            diagnostic.column,
+        )
+    }
+
+    fn swf4_error(source: &str) -> (String, usize, usize) {
+        let options = CompileOptions {
+            swf_version: 4,
+            ..Default::default()
+        };
+        error(source, &options)
+    }
+
+    fn requires_swf5(feature: &str, column: usize) -> (String, usize, usize) {
+        let message = format!(
+            "{} requires SWF version 5 or later, but version 4 is targeted",
+            feature
+        );
+        (message, 1, column)
+    }
+
+    #[test]
+    fn swf4_member_access() {
+        assert_eq!(
+            swf4_error("o.x = 1;"),
+            requires_swf5("Accessing a member", 3)
+        );
+        assert_eq!(
+            swf4_error("x = a[1];"),
+            requires_swf5("Accessing a member", 7)
+        );
+        assert_eq!(
+            swf4_error("o._x += 1;"),
+            requires_swf5("'+=' on a property", 3)
+        );
+    }
+
+    #[test]
+    fn swf4_delete() {
+        assert_eq!(swf4_error("delete a;"), requires_swf5("'delete'", 1));
+    }
+
+    #[test]
+    fn swf4_assignment_value() {
+        assert_eq!(
+            swf4_error("x = (y = 1);"),
+            requires_swf5("Using the value of '='", 8)
+        );
+        assert_eq!(
+            swf4_error("x = y++;"),
+            requires_swf5("Using the value of '++'", 6)
+        );
+    }
+
+    #[test]
+    fn swf4_literals() {
+        assert_eq!(
+            swf4_error("var q = [1];"),
+            requires_swf5("An array literal", 9)
+        );
+        assert_eq!(
+            swf4_error("q = {a: 1};"),
+            requires_swf5("An object literal", 5)
+        );
+    }
+
+    #[test]
+    fn swf4_calls() {
+        assert_eq!(swf4_error("d = new Date();"), requires_swf5("'new'", 5));
+        assert_eq!(swf4_error("a.b();"), requires_swf5("Calling a method", 3));
+        assert_eq!(swf4_error("f();"), requires_swf5("Calling a function", 1));
+    }
+
+    #[test]
+    fn swf4_comparison_order() {
+        let options = CompileOptions {
+            swf_version: 4,
+            ..Default::default()
+        };
+        let uses_variable = |source| {
+            let (_, actions) = compile_to_actions(source, &options).unwrap();
+            actions.windows(9).any(|window| window == b"$asc_left")
+        };
+        assert!(!uses_variable("trace(a > b);"));
+        assert!(!uses_variable("trace(a._x <= -b);"));
+        assert!(!uses_variable("trace(random(2) > 1);"));
+        assert!(uses_variable("trace(random(2) > getTimer());"));
+    }
+
+    // The first action of compiling `source`.
+    fn first_action(source: &str) -> String {
+        let (_, actions) = compile_to_actions(source, &CompileOptions::default()).unwrap();
+        format!(
+            "{:?}",
+            crate::action_list::decode_actions(&actions).unwrap()[0]
+        )
+    }
+
+    fn push(value: swf::avm1::types::Value) -> String {
+        let push = swf::avm1::types::Push {
+            values: vec![value],
+        };
+        format!("{:?}", swf::avm1::types::Action::Push(push))
+    }
+
+    #[test]
+    fn sign_folding() {
+        use swf::avm1::types::Value;
+        let cases = [
+            ("trace(-5);", Value::Int(-5)),
+            ("trace(-(5));", Value::Int(-5)),
+            ("trace(- -5);", Value::Int(5)),
+            ("trace(+-(2.5));", Value::Double(-2.5)),
+            ("trace(-0);", Value::Double(-0.0)),
+            ("trace(-Infinity);", Value::Double(f64::NEG_INFINITY)),
+            ("trace(-NaN);", Value::Double(f64::NAN)),
+            ("trace(-2147483648);", Value::Int(i32::MIN)),
+            ("trace(-0x80000000);", Value::Int(i32::MIN)),
+            ("trace(2147483648);", Value::Double(2147483648.0)),
+        ];
+        for (source, value) in cases {
+            assert_eq!(first_action(source), push(value), "{}", source);
+        }
+    }
+
+    #[test]
+    fn start_drag_arity() {
+        let options = CompileOptions::default();
+        let message = |count| format!("Expected 1, 2 or 6 argument(s), got {}", count);
+        assert_eq!(error("startDrag();", &options), (message(0), 1, 11));
+        assert_eq!(error("startDrag(a, b, c);", &options), (message(3), 1, 18));
+        assert_eq!(
+            error("startDrag(a, b, 1, 2, 3, 4, 5);", &options),
+            (message(7), 1, 29)
+        );
+    }
+
+    #[test]
+    fn load_levels() {
+        use swf::avm1::types::Value;
+        for source in [
+            "loadMovieNum(\"a.swf\", 1);",
+            "loadMovieNum(\"a.swf\", 0x1);",
+            "loadMovieNum(\"a.swf\", 1.0);",
+        ] {
+            let (_, actions) = compile_to_actions(source, &CompileOptions::default()).unwrap();
+            let actions = crate::action_list::decode_actions(&actions).unwrap();
+            let push = swf::avm1::types::Push {
+                values: vec![Value::Str("a.swf".into()), Value::Str("_level1".into())],
+            };
+            assert_eq!(
+                format!("{:?}", actions[0]),
+                format!("{:?}", swf::avm1::types::Action::Push(push)),
+                "{}",
+                source
+            );
+        }
+        assert_eq!(
+            error("loadMovieNum(\"a.swf\", 1.5);", &CompileOptions::default()),
+            ("Invalid level number".to_string(), 1, 23)
+        );
+    }
+
+    #[test]
+    fn unreachable_statement_positions() {
+        for statement in [
+            "tellTarget(\"/clip\") { play(); }",
+            "if (x) { play(); }",
+            "while (x) { play(); }",
+            "trace(x);",
+        ] {
+            let source = format!("while (true) {{ break; {} }}", statement);
+            let (diagnostics, _) = compile_to_actions(&source, &CompileOptions::default()).unwrap();
+            let positions: Vec<_> = diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.lint == Some(Lint::Unreachable))
+                .map(|diagnostic| (diagnostic.line, diagnostic.column))
+                .collect();
+            assert_eq!(positions, [(1, 23)], "{}", statement);
+        }
+    }
+
+    #[test]
+    fn scenes() {
+        let options = CompileOptions::default();
+        let goto_first_frame = [0x81, 2, 0, 0, 0];
+        for source in ["nextScene();", "prevScene();"] {
+            let (_, actions) = compile_to_actions(source, &options).unwrap();
+            assert!(actions.starts_with(&goto_first_frame));
+        }
+        assert_eq!(
+            error("gotoAndPlay(\"Scene 2\", 1);", &options),
+            (
+                "Expected scene offset, as the movie has a single scene".to_string(),
+                1,
+                13
+            )
+        );
+    }
+}
@@ -0,0 +1,234 @@
+// Messages about a source, from errors that prevent it from compiling to warnings about code that
+// likely does not do what was meant, and the lints that each warning belongs to.
+
+use crate::json;
+use crate::scanner::{CompileError, ErrorKind};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // Additional information about another diagnostic, among its notes.
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+// A kind of warning, which can be enabled, disabled or made an error by its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lint {
+    // A statement that execution never reaches.
+    Unreachable,
+    // A local variable or a parameter whose value is never read.
+    Unused,
+    // A variable read without being declared, nor built in, which is likely undefined.
+    Undeclared,
+    // A call of a global function or a static method of the Flash Player with too few or too many
+    // arguments.
+    Arity,
+    // A property whose name is a near miss of that of a movie clip property, such as `_xScale`.
+    Property,
+    // A parameter, variable or function that has the name of one of an enclosing function or of the
+    // timeline, which it hides.
+    Shadow,
+    // A parameter, variable or function declared again in the same function, which refers to the
+    // same variable rather than a new one.
+    Redeclared,
+    // A name in a comment suppressing warnings that is not that of a lint, such as a misspelled one,
+    // which suppresses nothing.
+    UnknownLint,
+}
+
+impl Lint {
+    pub const ALL: &'static [Self] = &[
+        Self::Unreachable,
+        Self::Unused,
+        Self::Undeclared,
+        Self::Arity,
+        Self::Property,
+        Self::Shadow,
+        Self::Redeclared,
+        Self::UnknownLint,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unreachable => "unreachable",
+            Self::Unused => "unused",
+            Self::Undeclared => "undeclared",
+            Self::Arity => "arity",
+            Self::Property => "property",
+            Self::Shadow => "shadow",
+            Self::Redeclared => "redeclared",
+            Self::UnknownLint => "unknown-lint",
+        }
+    }
+
+    // The code of the lint, which never changes once released.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Unreachable => "W0001",
+            Self::Unused => "W0002",
+            Self::Undeclared => "W0003",
+            Self::Arity => "W0004",
+            Self::Property => "W0005",
+            Self::Shadow => "W0006",
+            Self::Redeclared => "W0007",
+            Self::UnknownLint => "W0008",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+
+    // Whether the lint warns unless disabled, rather than only when enabled, such as by `-Wall`.
+    fn is_default(self) -> bool {
+        match self {
+            Self::Unreachable => true,
+            // Callbacks often take parameters that they have no use for.
+            Self::Unused => false,
+            // Timeline code commonly reads variables that other frames and clips define.
+            Self::Undeclared => false,
+            Self::Arity | Self::Property | Self::UnknownLint => true,
+            // Callbacks commonly reuse names such as `i`, and loops commonly declare their counter
+            // again.
+            Self::Shadow | Self::Redeclared => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+// How each lint is reported.
+#[derive(Clone, Debug, Default)]
+pub struct LintOptions {
+    // Levels of lints, overriding those of the other options. Later ones override earlier ones.
+    pub levels: Vec<(Lint, LintLevel)>,
+    // Warn about all lints, including those that only warn when enabled.
+    pub all: bool,
+    // Report warnings as errors.
+    pub warnings_as_errors: bool,
+}
+
+impl LintOptions {
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        let level = self
+            .levels
+            .iter()
+            .rev()
+            .find(|(other, _)| *other == lint)
+            .map(|(_, level)| *level);
+        match level {
+            Some(level) => level,
+            None if self.all || lint.is_default() => LintLevel::Warn,
+            None => LintLevel::Allow,
+        }
+    }
+
+    // The severity of a warning of `lint`, or `None` if disabled.
+    pub(crate) fn severity(&self, lint: Lint) -> Option<Severity> {
+        match self.level(lint) {
+            LintLevel::Allow => None,
+            LintLevel::Warn if !self.warnings_as_errors => Some(Severity::Warning),
+            LintLevel::Warn | LintLevel::Deny => Some(Severity::Error),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // The kind of an error, unless a warning made an error.
+    pub kind: Option<ErrorKind>,
+    // The lint that a warning belongs to, even if made an error.
+    pub lint: Option<Lint>,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    // The position after the code that the diagnostic is about, if more than a single character.
+    pub end: Option<(usize, usize)>,
+    // Notes about the diagnostic, such as where a related declaration is.
+    pub notes: Vec<Diagnostic>,
+}
+
+impl Diagnostic {
+    // The code of the kind of error or of the lint, such as "E0002" or "W0001".
+    pub fn code(&self) -> Option<&'static str> {
+        match (self.kind, self.lint) {
+            (Some(kind), _) => Some(kind.code()),
+            (None, Some(lint)) => Some(lint.code()),
+            (None, None) => None,
+        }
+    }
+
+    // A note about where some code related to another diagnostic is.
+    pub(crate) fn note(message: String, line: usize, column: usize) -> Self {
+        Self {
+            severity: Severity::Note,
+            kind: None,
+            lint: None,
+            message,
+            line,
+            column,
+            end: None,
+            notes: Vec::new(),
+        }
+    }
+
+    // The diagnostic about the file `filename` as a line of JSON, for editors and build systems.
+    pub fn to_json(&self, filename: &str) -> String {
+        let span = if self.line == 0 {
+            "null".to_string()
+        } else {
+            let (end_line, end_column) = self.end.unwrap_or((self.line, self.column + 1));
+            format!(
+                r#"{{"line":{},"column":{},"end_line":{},"end_column":{}}}"#,
+                self.line, self.column, end_line, end_column
+            )
+        };
+        let notes: Vec<_> = self
+            .notes
+            .iter()
+            .map(|note| note.to_json(filename))
+            .collect();
+        format!(
+            r#"{{"file":{},"span":{},"severity":{},"code":{},"lint":{},"message":{},"notes":[{}]}}"#,
+            json::string(filename),
+            span,
+            json::string(self.severity.name()),
+            json::optional_string(self.code()),
+            json::optional_string(self.lint.map(Lint::name)),
+            json::string(&self.message),
+            notes.join(",")
+        )
+    }
+}
+
+impl From<CompileError> for Diagnostic {
+    fn from(error: CompileError) -> Self {
+        Self {
+            severity: Severity::Error,
+            kind: Some(error.kind),
+            lint: None,
+            message: error.message,
+            line: error.line,
+            column: error.column,
+            end: None,
+            notes: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// Shared copies of the names that outlive the source they come from, such as the names of classes
+// and their members, which imported files declare.
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+#[derive(Default)]
+pub struct Interner {
+    strings: HashSet<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The shared copy of `string`, allocated on first use.
+    pub fn intern(&mut self, string: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.get(string) {
+            return interned.clone();
+        }
+        let interned: Rc<str> = string.into();
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    // The shared copy of the dotted name made of `path`.
+    pub fn intern_path(&mut self, path: &[&str]) -> Rc<str> {
+        self.intern(&path.join("."))
+    }
+}
+
+// Whether the dotted name `name` is made of `path`, without joining it.
+pub fn is_path(name: &str, path: &[&str]) -> bool {
+    name.split('.').eq(path.iter().copied())
+}
@@ -0,0 +1,321 @@
+// The built-in classes of the Flash Player, as seen by the compiler.
+
+dynamic intrinsic class Object {
+    static var prototype:Object;
+    var __proto__:Object;
+    var constructor:Object;
+    function addProperty(name:String, getter:Function, setter:Function):Boolean;
+    function hasOwnProperty(name:String):Boolean;
+    function isPropertyEnumerable(name:String):Boolean;
+    function isPrototypeOf(object:Object):Boolean;
+    static function registerClass(name:String, theClass:Function):Boolean;
+    function toString():String;
+    function unwatch(name:String):Boolean;
+    function valueOf():Object;
+    function watch(name:String, callback:Function, userData:Object):Boolean;
+}
+
+dynamic intrinsic class Function {
+    function apply(thisObject:Object, argArray:Array):Object;
+    function call(thisObject:Object):Object;
+}
+
+dynamic intrinsic class Array {
+    static var CASEINSENSITIVE:Number;
+    static var DESCENDING:Number;
+    static var NUMERIC:Number;
+    static var RETURNINDEXEDARRAY:Number;
+    static var UNIQUESORT:Number;
+    var length:Number;
+    function concat(value:Object):Array;
+    function join(delimiter:String):String;
+    function pop():Object;
+    function push(value:Object):Number;
+    function reverse():Void;
+    function shift():Object;
+    function slice(startIndex:Number, endIndex:Number):Array;
+    function sort(compareFunction:Object, options:Number):Array;
+    function sortOn(fieldName:Object, options:Object):Array;
+    function splice(startIndex:Number, deleteCount:Number, value:Object):Array;
+    function toString():String;
+    function unshift(value:Object):Number;
+}
+
+intrinsic class Boolean {
+    function toString():String;
+    function valueOf():Boolean;
+}
+
+intrinsic class Number {
+    static var MAX_VALUE:Number;
+    static var MIN_VALUE:Number;
+    static var NaN:Number;
+    static var NEGATIVE_INFINITY:Number;
+    static var POSITIVE_INFINITY:Number;
+    function toString(radix:Number):String;
+    function valueOf():Number;
+}
+
+intrinsic class String {
+    var length:Number;
+    function charAt(index:Number):String;
+    function charCodeAt(index:Number):Number;
+    function concat(value:Object):String;
+    static function fromCharCode(code:Number):String;
+    function indexOf(value:String, startIndex:Number):Number;
+    function lastIndexOf(value:String, startIndex:Number):Number;
+    function slice(start:Number, end:Number):String;
+    function split(delimiter:String, limit:Number):Array;
+    function substr(start:Number, length:Number):String;
+    function substring(start:Number, end:Number):String;
+    function toLowerCase():String;
+    function toString():String;
+    function toUpperCase():String;
+    function valueOf():String;
+}
+
+intrinsic class Math {
+    static var E:Number;
+    static var LN10:Number;
+    static var LN2:Number;
+    static var LOG10E:Number;
+    static var LOG2E:Number;
+    static var PI:Number;
+    static var SQRT1_2:Number;
+    static var SQRT2:Number;
+    static function abs(x:Number):Number;
+    static function acos(x:Number):Number;
+    static function asin(x:Number):Number;
+    static function atan(x:Number):Number;
+    static function atan2(y:Number, x:Number):Number;
+    static function ceil(x:Number):Number;
+    static function cos(x:Number):Number;
+    static function exp(x:Number):Number;
+    static function floor(x:Number):Number;
+    static function log(x:Number):Number;
+    static function max(x:Number, y:Number):Number;
+    static function min(x:Number, y:Number):Number;
+    static function pow(x:Number, y:Number):Number;
+    static function random():Number;
+    static function round(x:Number):Number;
+    static function sin(x:Number):Number;
+    static function sqrt(x:Number):Number;
+    static function tan(x:Number):Number;
+}
+
+intrinsic class Date {
+    function getDate():Number;
+    function getDay():Number;
+    function getFullYear():Number;
+    function getHours():Number;
+    function getMilliseconds():Number;
+    function getMinutes():Number;
+    function getMonth():Number;
+    function getSeconds():Number;
+    function getTime():Number;
+    function getTimezoneOffset():Number;
+    function getUTCDate():Number;
+    function getUTCDay():Number;
+    function getUTCFullYear():Number;
+    function getUTCHours():Number;
+    function getUTCMilliseconds():Number;
+    function getUTCMinutes():Number;
+    function getUTCMonth():Number;
+    function getUTCSeconds():Number;
+    function getYear():Number;
+    function setDate(date:Number):Number;
+    function setFullYear(year:Number, month:Number, date:Number):Number;
+    function setHours(hour:Number, minute:Number, second:Number, millisecond:Number):Number;
+    function setMilliseconds(millisecond:Number):Number;
+    function setMinutes(minute:Number, second:Number, millisecond:Number):Number;
+    function setMonth(month:Number, date:Number):Number;
+    function setSeconds(second:Number, millisecond:Number):Number;
+    function setTime(millisecond:Number):Number;
+    function setYear(year:Number):Number;
+    function toString():String;
+    static function UTC(year:Number, month:Number, date:Number, hour:Number, minute:Number, second:Number, millisecond:Number):Number;
+    function valueOf():Number;
+}
+
+dynamic intrinsic class MovieClip {
+    var _alpha:Number;
+    var _currentframe:Number;
+    var _droptarget:String;
+    var _focusrect:Boolean;
+    var _framesloaded:Number;
+    var _height:Number;
+    var _lockroot:Boolean;
+    var _name:String;
+    var _parent:MovieClip;
+    var _quality:String;
+    var _rotation:Number;
+    var _soundbuftime:Number;
+    var _target:String;
+    var _totalframes:Number;
+    var _url:String;
+    var _visible:Boolean;
+    var _width:Number;
+    var _x:Number;
+    var _xmouse:Number;
+    var _xscale:Number;
+    var _y:Number;
+    var _ymouse:Number;
+    var _yscale:Number;
+    var enabled:Boolean;
+    var focusEnabled:Boolean;
+    var hitArea:Object;
+    var tabChildren:Boolean;
+    var tabEnabled:Boolean;
+    var tabIndex:Number;
+    var trackAsMenu:Boolean;
+    var useHandCursor:Boolean;
+    function attachAudio(id:Object):Void;
+    function attachMovie(id:String, name:String, depth:Number, initObject:Object):MovieClip;
+    function beginFill(rgb:Number, alpha:Number):Void;
+    function beginGradientFill(fillType:String, colors:Array, alphas:Array, ratios:Array, matrix:Object):Void;
+    function clear():Void;
+    function createEmptyMovieClip(name:String, depth:Number):MovieClip;
+    function createTextField(instanceName:String, depth:Number, x:Number, y:Number, width:Number, height:Number):TextField;
+    function curveTo(controlX:Number, controlY:Number, anchorX:Number, anchorY:Number):Void;
+    function duplicateMovieClip(name:String, depth:Number, initObject:Object):MovieClip;
+    function endFill():Void;
+    function getBounds(bounds:Object):Object;
+    function getBytesLoaded():Number;
+    function getBytesTotal():Number;
+    function getDepth():Number;
+    function getInstanceAtDepth(depth:Number):MovieClip;
+    function getNextHighestDepth():Number;
+    function getURL(url:String, window:String, method:String):Void;
+    function globalToLocal(point:Object):Void;
+    function gotoAndPlay(frame:Object):Void;
+    function gotoAndStop(frame:Object):Void;
+    function hitTest(x:Object, y:Number, shapeFlag:Boolean):Boolean;
+    function lineStyle(thickness:Number, rgb:Number, alpha:Number):Void;
+    function lineTo(x:Number, y:Number):Void;
+    function loadMovie(url:String, method:String):Void;
+    function loadVariables(url:String, method:String):Void;
+    function localToGlobal(point:Object):Void;
+    function moveTo(x:Number, y:Number):Void;
+    function nextFrame():Void;
+    function play():Void;
+    function prevFrame():Void;
+    function removeMovieClip():Void;
+    function setMask(mask:MovieClip):Void;
+    function startDrag(lockCenter:Boolean, left:Number, top:Number, right:Number, bottom:Number):Void;
+    function stop():Void;
+    function stopDrag():Void;
+    function swapDepths(target:Object):Void;
+    function unloadMovie():Void;
+}
+
+dynamic intrinsic class TextField {
+    var _alpha:Number;
+    var _height:Number;
+    var _name:String;
+    var _parent:MovieClip;
+    var _rotation:Number;
+    var _visible:Boolean;
+    var _width:Number;
+    var _x:Number;
+    var _xscale:Number;
+    var _y:Number;
+    var _yscale:Number;
+    var autoSize:Object;
+    var background:Boolean;
+    var backgroundColor:Number;
+    var border:Boolean;
+    var borderColor:Number;
+    var embedFonts:Boolean;
+    var html:Boolean;
+    var htmlText:String;
+    var length:Number;
+    var maxChars:Number;
+    var multiline:Boolean;
+    var password:Boolean;
+    var selectable:Boolean;
+    var text:String;
+    var textColor:Number;
+    var textHeight:Number;
+    var textWidth:Number;
+    var type:String;
+    var variable:String;
+    var wordWrap:Boolean;
+    function getNewTextFormat():Object;
+    function getTextFormat(beginIndex:Number, endIndex:Number):Object;
+    function removeTextField():Void;
+    function replaceSel(newText:String):Void;
+    function setNewTextFormat(format:Object):Void;
+    function setTextFormat(beginIndex:Object, endIndex:Object, format:Object):Void;
+}
+
+intrinsic class Sound {
+    var duration:Number;
+    var position:Number;
+    function attachSound(id:String):Void;
+    function getBytesLoaded():Number;
+    function getBytesTotal():Number;
+    function getPan():Number;
+    function getVolume():Number;
+    function loadSound(url:String, isStreaming:Boolean):Void;
+    function setPan(value:Number):Void;
+    function setVolume(value:Number):Void;
+    function start(secondOffset:Number, loops:Number):Void;
+    function stop(linkageID:String):Void;
+}
+
+intrinsic class Key {
+    static var BACKSPACE:Number;
+    static var CAPSLOCK:Number;
+    static var CONTROL:Number;
+    static var DELETEKEY:Number;
+    static var DOWN:Number;
+    static var END:Number;
+    static var ENTER:Number;
+    static var ESCAPE:Number;
+    static var HOME:Number;
+    static var INSERT:Number;
+    static var LEFT:Number;
+    static var PGDN:Number;
+    static var PGUP:Number;
+    static var RIGHT:Number;
+    static var SHIFT:Number;
+    static var SPACE:Number;
+    static var TAB:Number;
+    static var UP:Number;
+    static function addListener(listener:Object):Void;
+    static function getAscii():Number;
+    static function getCode():Number;
+    static function isDown(code:Number):Boolean;
+    static function isToggled(code:Number):Boolean;
+    static function removeListener(listener:Object):Boolean;
+}
+
+intrinsic class Mouse {
+    static function addListener(listener:Object):Void;
+    static function hide():Number;
+    static function removeListener(listener:Object):Boolean;
+    static function show():Number;
+}
+
+intrinsic class Stage {
+    static var align:String;
+    static var height:Number;
+    static var scaleMode:String;
+    static var showMenu:Boolean;
+    static var width:Number;
+    static function addListener(listener:Object):Void;
+    static function removeListener(listener:Object):Boolean;
+}
+
+dynamic intrinsic class LoadVars {
+    var contentType:String;
+    var loaded:Boolean;
+    function addRequestHeader(header:Object, headerValue:String):Void;
+    function decode(queryString:String):Void;
+    function getBytesLoaded():Number;
+    function getBytesTotal():Number;
+    function load(url:String):Boolean;
+    function send(url:String, target:String, method:String):Boolean;
+    function sendAndLoad(url:String, target:Object, method:String):Boolean;
+    function toString():String;
+}
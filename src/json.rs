@@ -0,0 +1,25 @@
+// Writing of JSON, for the output that other programs read, such as diagnostics and traces.
+
+// `string` as a JSON string literal.
+pub(crate) fn string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// `value` as a JSON string literal, or `null` if none.
+pub(crate) fn optional_string(value: Option<&str>) -> String {
+    value.map_or("null".to_string(), string)
+}
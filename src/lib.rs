@@ -1,5 +1,43 @@
+mod action_list;
+mod assembler;
+pub mod ast;
+mod batch;
+mod cfg;
 mod compiler;
+mod diagnostic;
+mod interner;
+mod json;
+mod md5;
+mod parser;
+mod patch;
+mod preprocessor;
+mod render;
+mod sarif;
 mod scanner;
+mod scope;
+mod signatures;
+mod source;
+mod source_map;
+mod stack;
+mod stats;
+mod suggest;
+mod suppress;
+mod timing;
+pub mod visit;
 
-pub use compiler::compile;
-pub use scanner::CompileError;
+pub use action_list::{decode_actions, ActionList};
+pub use batch::{compile_parallel, CompiledSwf};
+pub use compiler::{
+    compile, compile_file, compile_reader, compile_to_action_list, compile_to_actions,
+    compile_with_options, compile_with_stats, compile_with_visitor, CompileOptions, Compression,
+    Optimizations,
+};
+pub use diagnostic::{Diagnostic, Lint, LintLevel, LintOptions, Severity};
+pub use patch::{inject_into_swf, patch_swf, Patch};
+pub use render::Renderer;
+pub use sarif::sarif_log;
+pub use scanner::{CompileError, ErrorKind, Token, TokenKind};
+pub use source::SourceText;
+pub use source_map::{ActionBlock, FunctionSymbols, Mapping, SourceMap};
+pub use stats::{CompileStats, FunctionStats};
+pub use timing::{chrome_trace, PhaseTime};
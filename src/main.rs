@@ -1,38 +1,592 @@
-use asc::CompileError;
+use asc::{CompileError, Diagnostic, ErrorKind, LintLevel};
+use std::io::IsTerminal;
+use std::process::ExitCode;
 
 fn usage() {
     let program = std::env::args()
         .next()
         .map_or("asc".into(), std::borrow::Cow::Owned);
-    println!("Usage: {} <file.as>", program);
+    println!("Usage: {} [options] <file.as>...", program);
+    println!();
+    println!(
+        "A single file compiles to test.swf, or the file that -o names, several files each to"
+    );
+    println!("a SWF file next to it.");
+    println!();
+    println!("Options:");
+    println!("  -o <file.swf>           Write the SWF file of a single file to <file.swf>");
+    println!("  --allow-private-access  Do not check access to private members");
+    println!("  --classpath <dir>       Look up imported classes in <dir>");
+    println!("  --trace-call            Compile trace() as a function call");
+    println!("  --explicit-registers    Only access registers as @rN, not registerN");
+    println!("  --max-depth <depth>     Limit the nesting of statements and expressions");
+    println!("  --strict                Make warnings errors and require declaring variables");
+    println!("  --swf-version <version> Write a SWF file of <version>, 32 by default");
+    println!("  --size <width>x<height> Make the stage <width> by <height> pixels");
+    println!("  --frame-rate <fps>      Play <fps> frames per second");
+    println!("  --background <rrggbb>   Color the stage with the hexadecimal color <rrggbb>");
+    println!("  --compression <method>  Compress the SWF file with none, zlib or lzma");
+    println!(
+        "  --protect <password>    Keep tools from importing the file, unless given <password>"
+    );
+    println!("  --debugger-password <password>");
+    println!("                          Let debuggers connect, given <password>");
+    println!("  --password-salt <salt>  Hash passwords with <salt> rather than a random one");
+    println!("  --max-recursion <depth> Let calls nest <depth> deep, 256 by default");
+    println!("  --script-timeout <seconds>");
+    println!("                          Let the actions of a frame run <seconds>, 15 by default");
+    println!("  --inject <file.swf>     Add the actions to a frame of a copy of <file.swf>");
+    println!("  --frame <frame>         Inject into frame <frame>, the first by default");
+    println!("  --replace-frame         Replace the actions of the frame rather than add to them");
+    println!("  --replace-init-clip <id>");
+    println!("                          Replace the init clip actions of sprite <id> instead");
+    println!("  --replace-function <name>");
+    println!("                          Replace the body of the function <name> instead");
+    println!("  -O0                     Keep variables out of registers and actions as written");
+    println!("  -D<name>[=<value>]      Define <name> for the preprocessor");
+    println!("  --define <name>,<value> Define a constant such as CONFIG::DEBUG");
+    println!("  --stats                 Report where the bytes of the SWF file go");
+    println!("  --time                  Report the time spent in each phase of compilation");
+    println!("  --time-trace <file>     Write the phases to <file> as Chrome trace events");
+    println!("  --source-map <file>     Write the source positions of the actions to <file>");
+    println!("  --symbols <file>        Write the names of the functions and variables to <file>");
+    println!("  -v, -vv                 Log the compilation phases, or every token and statement");
+    println!("  --error-format=json     Report diagnostics as a line of JSON each");
+    println!("  --error-format=sarif    Report diagnostics as a SARIF log");
+    println!("  -Wall                   Enable all warnings");
+    println!("  -W<name>, -Wno-<name>   Enable or disable the warning <name>");
+    println!("  -Werror[=<name>]        Make all warnings, or the warning <name>, errors");
+    println!();
+    println!("Warnings:");
+    for lint in asc::Lint::ALL {
+        println!("  {}", lint.name());
+    }
+    println!();
+    println!("A comment // asc-ignore(<name>,...) suppresses warnings on the next line, or in the");
+    println!("whole block when at its start.");
+}
+
+#[derive(Clone, Copy)]
+enum ErrorFormat {
+    // Code frames for people to read.
+    Human,
+    // A line of JSON per diagnostic, for editors and build systems.
+    Json,
+    // A SARIF log of the diagnostics about all files, for code scanning dashboards.
+    Sarif,
+}
+
+// A logger that prints the records of the compiler to stderr.
+struct Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
 }
 
-fn main() -> Result<(), CompileError> {
-    let Some(filename) = std::env::args().nth(1) else {
+fn main() -> ExitCode {
+    let mut options = asc::CompileOptions::default();
+    let mut filenames = Vec::new();
+    let mut verbosity = 0;
+    let mut show_stats = false;
+    let mut show_time = false;
+    let mut time_trace = None;
+    let mut source_map = None;
+    let mut symbols = None;
+    let mut output = None;
+    let mut host = None;
+    let mut frame = None;
+    let mut replace_frame = false;
+    let mut replace = None;
+    let mut error_format = ErrorFormat::Human;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--allow-private-access" => options.allow_private_access = true,
+            "--trace-call" => options.trace_call = true,
+            "--explicit-registers" => options.explicit_registers = true,
+            "--strict" => options.strict = true,
+            "--stats" => show_stats = true,
+            "--time" => show_time = true,
+            "--time-trace" => {
+                let Some(file) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                time_trace = Some(file);
+            }
+            "--source-map" => {
+                let Some(file) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                source_map = Some(file);
+            }
+            "--symbols" => {
+                let Some(file) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                symbols = Some(file);
+            }
+            "-o" => {
+                let Some(file) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                output = Some(file);
+            }
+            "--inject" => {
+                let Some(file) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                host = Some(file);
+            }
+            "--frame" => {
+                let Some(number) = args.next().and_then(|number| number.parse().ok()) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                frame = Some(number);
+            }
+            "--replace-frame" => replace_frame = true,
+            "--replace-init-clip" => {
+                let Some(id) = args.next().and_then(|id| id.parse().ok()) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                replace = Some(asc::Patch::ReplaceInitClip(id));
+            }
+            "--replace-function" => {
+                let Some(name) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                replace = Some(asc::Patch::ReplaceFunction(name));
+            }
+            "--error-format=human" => error_format = ErrorFormat::Human,
+            "--error-format=json" => error_format = ErrorFormat::Json,
+            "--error-format=sarif" => error_format = ErrorFormat::Sarif,
+            "-v" => verbosity += 1,
+            "-vv" => verbosity += 2,
+            "--classpath" => {
+                let Some(directory) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.classpath.push(directory.into());
+            }
+            "--max-depth" => {
+                let Some(depth) = args.next().and_then(|depth| depth.parse().ok()) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.max_depth = Some(depth);
+            }
+            "--swf-version" => {
+                let Some(version) = args.next().and_then(|version| version.parse().ok()) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.swf_version = version;
+            }
+            "--size" => {
+                // In twips, 20 to the pixel.
+                let twips = |pixels: &str| {
+                    let pixels = pixels.parse::<i32>().ok().filter(|pixels| *pixels > 0)?;
+                    pixels.checked_mul(20)
+                };
+                let Some((width, height)) = args.next().and_then(|size| {
+                    let (width, height) = size.split_once('x')?;
+                    Some((twips(width)?, twips(height)?))
+                }) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.stage_width = width;
+                options.stage_height = height;
+            }
+            "--frame-rate" => {
+                // Which the header holds as an 8.8 fixed-point number.
+                let Some(frame_rate) = args
+                    .next()
+                    .and_then(|rate| rate.parse::<f32>().ok())
+                    .filter(|rate| (0.0..=255.0).contains(rate))
+                else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.frame_rate = frame_rate;
+            }
+            "--background" => {
+                let Some(color) = args
+                    .next()
+                    .and_then(|color| u32::from_str_radix(color.trim_start_matches('#'), 16).ok())
+                else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.background_color = color;
+            }
+            "--compression" => {
+                let Some(compression) = args
+                    .next()
+                    .and_then(|name| asc::Compression::from_name(&name))
+                else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.compression = compression;
+            }
+            "--protect" => {
+                let Some(password) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.protect = Some(password);
+            }
+            "--debugger-password" => {
+                let Some(password) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.debugger_password = Some(password);
+            }
+            "--password-salt" => {
+                let Some(salt) = args.next() else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.password_salt = Some(salt);
+            }
+            "--max-recursion" => {
+                let Some(depth) = args.next().and_then(|depth| depth.parse().ok()) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.max_recursion_depth = Some(depth);
+            }
+            "--script-timeout" => {
+                let Some(seconds) = args.next().and_then(|seconds| seconds.parse().ok()) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.script_timeout = Some(seconds);
+            }
+            "-O0" => options.optimizations = asc::Optimizations::none(),
+            "--define" => {
+                let Some((name, value)) = args.next().and_then(|define| {
+                    let (name, value) = define.split_once(',')?;
+                    Some((name.to_string(), value.to_string()))
+                }) else {
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.constants.push((name, value));
+            }
+            "-Wall" => options.lints.all = true,
+            "-Werror" => options.lints.warnings_as_errors = true,
+            _ if arg.starts_with("--error-format=") => {
+                eprintln!("Unknown error format '{}'", &arg[15..]);
+                usage();
+                return ExitCode::FAILURE;
+            }
+            _ if arg.starts_with("-W") => {
+                let name = &arg[2..];
+                let (name, level) = if let Some(name) = name.strip_prefix("error=") {
+                    (name, LintLevel::Deny)
+                } else if let Some(name) = name.strip_prefix("no-") {
+                    (name, LintLevel::Allow)
+                } else {
+                    (name, LintLevel::Warn)
+                };
+                let Some(lint) = asc::Lint::from_name(name) else {
+                    eprintln!("Unknown warning '{}'", name);
+                    usage();
+                    return ExitCode::FAILURE;
+                };
+                options.lints.levels.push((lint, level));
+            }
+            _ if arg.starts_with("-D") => {
+                let define = &arg[2..];
+                let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+                options.defines.push((name.to_string(), value.to_string()));
+            }
+            _ => filenames.push(arg),
+        }
+    }
+
+    if filenames.is_empty() {
+        usage();
+        return ExitCode::SUCCESS;
+    }
+    if host.is_none() && (frame.is_some() || replace_frame || replace.is_some()) {
+        eprintln!("--frame and --replace-* need a SWF file to patch, which --inject names");
         usage();
-        return Ok(());
+        return ExitCode::FAILURE;
+    }
+
+    log::set_logger(&Logger).unwrap();
+    log::set_max_level(match verbosity {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    });
+
+    if filenames.len() > 1 {
+        if host.is_some() {
+            eprintln!("Only a single file can be injected");
+            usage();
+            return ExitCode::FAILURE;
+        }
+        if output.is_some() {
+            eprintln!("Only a single file can be written to the file that -o names");
+            usage();
+            return ExitCode::FAILURE;
+        }
+        return compile_files(&filenames, &options, error_format);
+    }
+    let filename = &filenames[0];
+    with_directory(&mut options, filename);
+    let source = match read_source(filename) {
+        Ok(source) => source,
+        Err(error) => return fail(error, error_format),
+    };
+    let source = source.as_str();
+
+    let output = output.as_deref().unwrap_or("test.swf");
+    // The host is read whole before the output is created, which could be the same file otherwise
+    // cleared before it is read.
+    let host_data = match host.as_deref().map(std::fs::read).transpose() {
+        Ok(host_data) => host_data,
+        Err(error) => {
+            let error = CompileError {
+                message: format!("Cannot read {}: {}", host.unwrap(), error),
+                ..error.into()
+            };
+            return fail(error, error_format);
+        }
+    };
+    if let Some(host) = host.as_deref().filter(|host| is_same_file(host, output)) {
+        let error = CompileError {
+            kind: ErrorKind::Io,
+            message: format!(
+                "Cannot write over {}, the file being patched; name another output with -o",
+                host
+            ),
+            line: 0,
+            column: 0,
+        };
+        return fail(error, error_format);
+    }
+    let file = match std::fs::File::create(output) {
+        Ok(file) => file,
+        Err(error) => return fail(error.into(), error_format),
+    };
+    let writer = std::io::BufWriter::new(file);
+    if let Some(host) = host_data {
+        let patch = match replace {
+            Some(patch) => patch,
+            None if replace_frame => asc::Patch::ReplaceFrame(frame.unwrap_or(1)),
+            None => asc::Patch::Inject(frame.unwrap_or(1)),
+        };
+        let result = asc::patch_swf(source, &options, host.as_slice(), &patch, writer);
+        report(&[(filename, source, &result)], error_format);
+        return exit_code(result.is_ok());
+    }
+    let has_outputs = time_trace.is_some() || source_map.is_some() || symbols.is_some();
+    let result = if show_stats || show_time || has_outputs {
+        asc::compile_with_stats(source, &options, writer).map(|(warnings, stats)| {
+            if show_stats {
+                print_stats(&stats);
+            }
+            if show_time {
+                print_phases(&stats.phases);
+            }
+            if let Some(file) = &time_trace {
+                write_output(file, asc::chrome_trace(&stats.phases));
+            }
+            if let Some(file) = &source_map {
+                write_output(file, stats.source_map.to_json(filename));
+            }
+            if let Some(file) = &symbols {
+                write_output(file, stats.source_map.symbols_to_json(filename));
+            }
+            warnings
+        })
+    } else {
+        asc::compile_with_options(source, &options, writer)
+    };
+    report(&[(filename, source, &result)], error_format);
+    exit_code(result.is_ok())
+}
+
+// Compile several files in parallel, each to a SWF file next to it.
+fn compile_files(
+    filenames: &[String],
+    options: &asc::CompileOptions,
+    error_format: ErrorFormat,
+) -> ExitCode {
+    let sources = filenames
+        .iter()
+        .map(|filename| read_source(filename))
+        .collect::<Result<Vec<_>, _>>();
+    let sources = match sources {
+        Ok(sources) => sources,
+        Err(error) => return fail(error, error_format),
     };
+    let inputs: Vec<_> = filenames
+        .iter()
+        .zip(&sources)
+        .map(|(filename, source)| {
+            let mut options = options.clone();
+            with_directory(&mut options, filename);
+            (source.as_str(), options)
+        })
+        .collect();
 
-    let source = std::fs::read_to_string(&filename).map_err(|error| CompileError {
+    let results: Vec<_> = asc::compile_parallel(&inputs)
+        .into_iter()
+        .zip(filenames)
+        .map(|(result, filename)| {
+            result.map(|compiled| {
+                let output = std::path::Path::new(filename).with_extension("swf");
+                if let Err(error) = std::fs::write(&output, &compiled.swf) {
+                    eprintln!("Cannot write {}: {}", output.display(), error);
+                }
+                compiled.warnings
+            })
+        })
+        .collect();
+    let files: Vec<_> = filenames
+        .iter()
+        .zip(&sources)
+        .zip(&results)
+        .map(|((filename, source), result)| (filename.as_str(), source.as_str(), result))
+        .collect();
+    report(&files, error_format);
+    exit_code(results.iter().all(Result::is_ok))
+}
+
+fn exit_code(is_ok: bool) -> ExitCode {
+    if is_ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// Write a file that accompanies the SWF file, such as a trace.
+fn write_output(file: &str, contents: String) {
+    if let Err(error) = std::fs::write(file, contents) {
+        eprintln!("Cannot write {}: {}", file, error);
+    }
+}
+
+// Report an error that prevents compiling, such as failing to read the source.
+fn fail(error: CompileError, error_format: ErrorFormat) -> ExitCode {
+    match error_format {
+        ErrorFormat::Human => eprintln!("{}", error),
+        // The error is about no file in particular.
+        ErrorFormat::Json => println!("{}", Diagnostic::from(error).to_json("")),
+        ErrorFormat::Sarif => print!("{}", asc::sarif_log(&[("", &[error.into()])])),
+    }
+    ExitCode::FAILURE
+}
+
+// Whether the paths `a` and `b` name the same existing file.
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Classes next to a compiled file come last.
+fn with_directory(options: &mut asc::CompileOptions, filename: &str) {
+    let directory = std::path::Path::new(filename).parent().unwrap();
+    options.classpath.push(directory.to_path_buf());
+}
+
+fn read_source(filename: &str) -> Result<asc::SourceText, CompileError> {
+    asc::SourceText::open(std::path::Path::new(filename)).map_err(|error| CompileError {
+        kind: ErrorKind::Io,
         message: format!("Cannot read {}: {}", filename, error),
         line: 0,
         column: 0,
-    })?;
+    })
+}
 
-    let file = std::fs::File::create("test.swf").unwrap();
-    let writer = std::io::BufWriter::new(file);
-    let result = asc::compile(&source, writer);
-    if let Err(error) = &result {
-        let line = source.lines().nth(error.line - 1).unwrap();
-        println!(
-            "{}:{}:{}: {}:\n\t{}\n\t{}^",
-            filename,
-            error.line,
-            error.column,
-            error.message,
-            line,
-            " ".repeat(error.column - 1)
-        );
-    }
-    result
+type CompileResult = Result<Vec<Diagnostic>, Vec<Diagnostic>>;
+
+// Report the diagnostics about each file, which is named and has its source.
+fn report(files: &[(&str, &str, &CompileResult)], error_format: ErrorFormat) {
+    let files = files.iter().map(|(filename, source, result)| {
+        let (Ok(diagnostics) | Err(diagnostics)) = result;
+        (*filename, *source, diagnostics.as_slice())
+    });
+    match error_format {
+        ErrorFormat::Human => {
+            let renderer = asc::Renderer {
+                color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+                ..Default::default()
+            };
+            for (filename, source, diagnostics) in files {
+                for diagnostic in diagnostics {
+                    println!("{}", renderer.render(filename, source, diagnostic));
+                }
+            }
+        }
+        ErrorFormat::Json => {
+            for (filename, _, diagnostics) in files {
+                for diagnostic in diagnostics {
+                    println!("{}", diagnostic.to_json(filename));
+                }
+            }
+        }
+        ErrorFormat::Sarif => {
+            let files: Vec<_> = files
+                .map(|(filename, _, diagnostics)| (filename, diagnostics))
+                .collect();
+            print!("{}", asc::sarif_log(&files));
+        }
+    }
+}
+
+fn print_stats(stats: &asc::CompileStats) {
+    println!("SWF size: {} bytes", stats.swf_size);
+    println!("Action data: {} bytes", stats.action_data_size);
+    println!("Constant pool: {} strings", stats.constant_pool_size);
+    println!("Functions:");
+    for function in &stats.functions {
+        let name = function.name.as_deref().unwrap_or("<anonymous>");
+        println!("  {:>8} bytes  {}", function.size, name);
+    }
+    println!("Actions:");
+    for (kind, count) in &stats.actions {
+        println!("  {:>8}  {}", count, kind);
+    }
+}
+
+fn print_phases(phases: &[asc::PhaseTime]) {
+    println!("Phases:");
+    for phase in phases {
+        let file = phase
+            .file
+            .as_ref()
+            .map(|file| format!(" {}", file.display()))
+            .unwrap_or_default();
+        let milliseconds = phase.duration.as_secs_f64() * 1000.0;
+        println!("  {:>10.3} ms  {}{}", milliseconds, phase.name, file);
+    }
 }
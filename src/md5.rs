@@ -0,0 +1,140 @@
+// MD5, and the MD5-based crypt of passwords that the `Protect` and `EnableDebugger2` tags hold.
+
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+// The crypt alphabet, in the order of the values of its 6-bit digits.
+const CRYPT_DIGITS: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// The MD5 digest of `data`.
+pub(crate) fn digest(data: &[u8]) -> [u8; 16] {
+    let constants: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    for block in message.chunks_exact(64) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i]));
+        }
+        for (state, value) in state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+// Whether `salt` can salt a crypt: 1 to 8 of its digits.
+pub(crate) fn is_salt(salt: &str) -> bool {
+    (1..=8).contains(&salt.len()) && salt.bytes().all(|byte| CRYPT_DIGITS.contains(&byte))
+}
+
+// A salt of 8 random digits, from the random keys that the standard library seeds hash maps with.
+pub(crate) fn random_salt() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (0..8)
+        .map(|_| {
+            let digit = CRYPT_DIGITS[(bits & 0x3f) as usize];
+            bits >>= 6;
+            char::from(digit)
+        })
+        .collect()
+}
+
+// `password` hashed with the MD5-based crypt and `salt`, as `$1$<salt>$<hash>`. Only the first 8
+// bytes of the salt count.
+pub(crate) fn crypt(password: &[u8], salt: &[u8]) -> String {
+    const MAGIC: &[u8] = b"$1$";
+    let salt = &salt[..salt.len().min(8)];
+
+    let alternate = digest(&[password, salt, password].concat());
+    let mut context = [password, MAGIC, salt].concat();
+    for chunk in password.chunks(16) {
+        context.extend_from_slice(&alternate[..chunk.len()]);
+    }
+    let mut length = password.len();
+    while length != 0 {
+        context.push(if length & 1 != 0 {
+            0
+        } else {
+            password.first().copied().unwrap_or(0)
+        });
+        length >>= 1;
+    }
+    let mut hash = digest(&context);
+
+    // Rounds to slow down guessing.
+    for round in 0..1000 {
+        let mut context = Vec::new();
+        if round & 1 != 0 {
+            context.extend_from_slice(password);
+        } else {
+            context.extend_from_slice(&hash);
+        }
+        if round % 3 != 0 {
+            context.extend_from_slice(salt);
+        }
+        if round % 7 != 0 {
+            context.extend_from_slice(password);
+        }
+        if round & 1 != 0 {
+            context.extend_from_slice(&hash);
+        } else {
+            context.extend_from_slice(password);
+        }
+        hash = digest(&context);
+    }
+
+    let mut crypt = String::from_utf8_lossy(MAGIC).into_owned();
+    crypt.push_str(&String::from_utf8_lossy(salt));
+    crypt.push('$');
+    let mut push_digits = |value: u32, count: usize| {
+        for digit in 0..count {
+            let index = (value >> (6 * digit)) & 0x3f;
+            crypt.push(char::from(CRYPT_DIGITS[index as usize]));
+        }
+    };
+    for (first, second, third) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)] {
+        let value =
+            u32::from(hash[first]) << 16 | u32::from(hash[second]) << 8 | u32::from(hash[third]);
+        push_digits(value, 4);
+    }
+    push_digits(hash[11].into(), 2);
+    crypt
+}
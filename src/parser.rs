@@ -0,0 +1,1529 @@
+use crate::ast::{
+    Catch, Class, ClassMember, ClassMemberKind, Event, Export, Expression, Frame, Function,
+    Handler, Import, InitClip, Intrinsic, IntrinsicMember, Item, Program, Sprite, Statement,
+};
+use crate::preprocessor::Preprocessor;
+use crate::scanner::{CompileError, ErrorKind, Token, TokenKind};
+use crate::suggest;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Precedence {
+    None,
+    Assignment,
+    Or,
+    And,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Equality,
+    Comparison,
+    BitwiseShift,
+    Term,
+    Factor,
+    Unary,
+    Call,
+    Construct,
+    Delete,
+    Path,
+    #[allow(dead_code)]
+    Primary,
+}
+
+impl Precedence {
+    fn can_assign(&self) -> bool {
+        *self <= Self::Assignment
+    }
+}
+
+impl TokenKind {
+    fn precedence(&self) -> Precedence {
+        match self {
+            Self::Dot | Self::LeftSquareBrace => Precedence::Path,
+            Self::LeftParen => Precedence::Call,
+            Self::Bang | Self::Delete | Self::Not | Self::Tilda | Self::Throw | Self::Typeof => {
+                Precedence::Unary
+            }
+            Self::Star | Self::Slash | Self::Percent => Precedence::Factor,
+            Self::Plus | Self::Minus | Self::Add => Precedence::Term,
+            Self::DoubleGreater | Self::TripleGreater | Self::DoubleLess => {
+                Precedence::BitwiseShift
+            }
+            Self::Greater
+            | Self::GreaterEqual
+            | Self::Less
+            | Self::LessEqual
+            | Self::InstanceOf
+            | Self::In
+            | Self::Ge
+            | Self::Gt
+            | Self::Le
+            | Self::Lt => Precedence::Comparison,
+            Self::BangEqual
+            | Self::BangDoubleEqual
+            | Self::DoubleEqual
+            | Self::TripleEqual
+            | Self::Eq
+            | Self::Ne => Precedence::Equality,
+            Self::Ampersand => Precedence::BitwiseAnd,
+            Self::Caret => Precedence::BitwiseXor,
+            Self::Bar => Precedence::BitwiseOr,
+            Self::DoubleAmpersand | Self::And => Precedence::And,
+            Self::DoubleBar | Self::Or => Precedence::Or,
+            _ => Precedence::None,
+        }
+    }
+
+    fn is_assign(&self) -> bool {
+        matches!(
+            self,
+            Self::Equal
+                | Self::PlusEqual
+                | Self::MinusEqual
+                | Self::StarEqual
+                | Self::SlashEqual
+                | Self::PercentEqual
+                | Self::AmpersandEqual
+                | Self::BarEqual
+                | Self::CaretEqual
+                | Self::DoubleGreaterEqual
+                | Self::TripleGreaterEqual
+                | Self::DoubleLessEqual
+        )
+    }
+
+    fn is_reserved(&self) -> bool {
+        matches!(
+            self,
+            Self::False
+                | Self::Null
+                | Self::True
+                | Self::Undefined
+                | Self::Add
+                | Self::And
+                | Self::Break
+                | Self::Catch
+                | Self::Continue
+                | Self::Delete
+                | Self::Do
+                | Self::Else
+                | Self::Eq
+                | Self::Finally
+                | Self::For
+                | Self::Function
+                | Self::Ge
+                | Self::Gt
+                | Self::If
+                | Self::In
+                | Self::InstanceOf
+                | Self::Le
+                | Self::Lt
+                | Self::Ne
+                | Self::New
+                | Self::Not
+                | Self::Or
+                | Self::TellTarget
+                | Self::Throw
+                | Self::Trace
+                | Self::Try
+                | Self::Typeof
+                | Self::Var
+                | Self::While
+        )
+    }
+}
+
+const EXPRESSION_TOO_DEEP: &str = "Expression too deeply nested";
+const STATEMENT_TOO_DEEP: &str = "Statement too deeply nested";
+
+// Words that are scanned as identifiers, but cannot name a declaration.
+fn is_reserved_identifier(name: &str) -> bool {
+    matches!(
+        name,
+        "case"
+            | "class"
+            | "default"
+            | "dynamic"
+            | "extends"
+            | "implements"
+            | "import"
+            | "interface"
+            | "intrinsic"
+            | "private"
+            | "public"
+            | "return"
+            | "static"
+            | "super"
+            | "switch"
+            | "this"
+            | "void"
+            | "with"
+    )
+}
+
+// The kind and text of a token, for messages about what was found instead of what was expected.
+fn describe(token: &Token) -> String {
+    match token.kind {
+        TokenKind::Eof => "end of file".to_string(),
+        TokenKind::Identifier => format!("identifier '{}'", token.source),
+        TokenKind::Number => format!("number '{}'", token.source),
+        TokenKind::String => format!("string {}", token.source),
+        TokenKind::Directive => format!("directive '{}'", token.source),
+        kind if kind.is_reserved() => format!("reserved word '{}'", token.source),
+        _ => format!("'{}'", token.source),
+    }
+}
+
+// An error at `token` of expecting something else, which `message` tells.
+fn expected(message: &str, token: &Token) -> CompileError {
+    CompileError {
+        kind: ErrorKind::Syntax,
+        message: format!("{}, found {}", message, describe(token)),
+        line: token.line,
+        column: token.column,
+    }
+}
+
+// Words that begin statements and declarations, which a misspelled one is likely meant as.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "break",
+    "class",
+    "const",
+    "continue",
+    "do",
+    "dynamic",
+    "for",
+    "function",
+    "if",
+    "import",
+    "intrinsic",
+    "onClipEvent",
+    "tellTarget",
+    "throw",
+    "trace",
+    "try",
+    "var",
+    "while",
+];
+
+// Default for the maximum nesting depth of the syntax tree, which its recursive processing fits in
+// the stack of the main thread with.
+pub const DEFAULT_MAX_DEPTH: usize = 500;
+
+// Builds the syntax tree of a source file, read through the preprocessor.
+pub struct Parser<'a> {
+    preprocessor: Preprocessor<'a>,
+    current: Token<'a>,
+    // The kind of the token before `current`.
+    previous: TokenKind,
+    // Nesting depth of the node being parsed, counting each link of chains like `a.b.c` and
+    // `a + b + c`.
+    depth: usize,
+    max_depth: usize,
+    // Time spent reading tokens, if measured.
+    scan_time: Option<Duration>,
+    // Errors of the declarations skipped so far, which parsing goes on after.
+    errors: Vec<CompileError>,
+    // Whether skipping past an error reached the end of the file or failed to read tokens, which
+    // ends parsing.
+    gave_up: bool,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(preprocessor: Preprocessor<'a>) -> Self {
+        Self {
+            preprocessor,
+            current: Token::INVALID,
+            previous: TokenKind::Eof,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            scan_time: None,
+            errors: Vec::new(),
+            gave_up: false,
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    // Measure the time spent reading tokens, which `scan_time` then tells.
+    pub fn time_scanning(&mut self) {
+        self.scan_time = Some(Duration::ZERO);
+    }
+
+    pub fn scan_time(&self) -> Option<Duration> {
+        self.scan_time
+    }
+
+    // Whether the source asks for strict mode, once parsed.
+    pub fn is_strict(&self) -> bool {
+        self.preprocessor.is_strict()
+    }
+
+    // Go one level deeper into the syntax tree, at the current token.
+    fn descend(&mut self, message: &str) -> Result<(), CompileError> {
+        self.depth += 1;
+        if self.depth > self.max_depth {
+            let token = self.peek_token();
+            return Err(CompileError {
+                kind: ErrorKind::TooDeep,
+                message: message.to_string(),
+                line: token.line,
+                column: token.column,
+            });
+        }
+        Ok(())
+    }
+
+    // Parse a node with `f` one level deeper into the syntax tree.
+    fn nested<T>(
+        &mut self,
+        message: &str,
+        f: impl FnOnce(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<T, CompileError> {
+        let depth = self.depth;
+        let result = self.descend(message).and_then(|()| f(self));
+        self.depth = depth;
+        result
+    }
+
+    // Record the error of a declaration that started at `start`, and skip past it to where the next
+    // one can start: after the next `;` or block, or before the next `}`.
+    fn recover(&mut self, error: CompileError, start: (usize, usize)) -> Result<(), CompileError> {
+        if self.gave_up {
+            return Err(error);
+        }
+        self.record(error.clone());
+        match self.synchronize(start) {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                self.gave_up = true;
+                Err(error)
+            }
+            Err(error) => {
+                self.record(error.clone());
+                self.gave_up = true;
+                Err(error)
+            }
+        }
+    }
+
+    // Record an error, unless at the position of the previous one, which it likely follows from.
+    fn record(&mut self, error: CompileError) {
+        let is_new = self
+            .errors
+            .last()
+            .is_none_or(|last| (last.line, last.column) != (error.line, error.column));
+        if is_new {
+            self.errors.push(error);
+        }
+    }
+
+    // Skip tokens up to where a declaration can start, at least past `start`: after a `;` or a
+    // block, or before a `}` or a keyword that starts a statement. Whether any are left.
+    fn synchronize(&mut self, start: (usize, usize)) -> Result<bool, CompileError> {
+        let mut depth = 0;
+        let is_past_start = |p: &Self| p.position() != start;
+        if self.previous == TokenKind::Semicolon && is_past_start(self) {
+            return Ok(true);
+        }
+        loop {
+            match self.peek_token().kind {
+                TokenKind::Eof => break,
+                TokenKind::Break
+                | TokenKind::Continue
+                | TokenKind::Do
+                | TokenKind::For
+                | TokenKind::Function
+                | TokenKind::If
+                | TokenKind::TellTarget
+                | TokenKind::Throw
+                | TokenKind::Trace
+                | TokenKind::Try
+                | TokenKind::Var
+                | TokenKind::While
+                    if depth == 0 && is_past_start(self) =>
+                {
+                    break
+                }
+                TokenKind::RightBrace if depth == 0 => break,
+                TokenKind::Semicolon if depth == 0 => {
+                    self.read_token()?;
+                    break;
+                }
+                TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.read_token()?;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            self.read_token()?;
+        }
+        if !is_past_start(self) {
+            if self.peek_token().kind == TokenKind::Eof {
+                return Ok(false);
+            }
+            self.read_token()?;
+        }
+        Ok(true)
+    }
+
+    fn position(&self) -> (usize, usize) {
+        let token = self.peek_token();
+        (token.line, token.column)
+    }
+
+    fn read_token(&mut self) -> Result<Token<'a>, CompileError> {
+        let next_token = match &mut self.scan_time {
+            Some(scan_time) => {
+                let start = Instant::now();
+                let next_token = self.preprocessor.read_token();
+                *scan_time += start.elapsed();
+                next_token?
+            }
+            None => self.preprocessor.read_token()?,
+        };
+        let token = std::mem::replace(&mut self.current, next_token);
+        self.previous = token.kind;
+        Ok(token)
+    }
+
+    fn peek_token(&self) -> &Token<'a> {
+        &self.current
+    }
+
+    fn peek_next_token(&self) -> Result<Token<'a>, CompileError> {
+        self.preprocessor.clone().read_token()
+    }
+
+    fn consume(&mut self, kind: TokenKind) -> Result<bool, CompileError> {
+        let token = self.peek_token();
+        if token.kind == kind {
+            self.read_token()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, message: &str) -> Result<Token<'a>, CompileError> {
+        let token = self.peek_token();
+        if token.kind == kind {
+            self.read_token()
+        } else {
+            Err(expected(message, token))
+        }
+    }
+
+    fn identifier(&mut self, message: &str) -> Result<Token<'a>, CompileError> {
+        let token = self.peek_token();
+        if token.kind.is_reserved() {
+            return Err(CompileError {
+                kind: ErrorKind::Syntax,
+                message: format!("{}, found reserved word '{}'", message, token.source),
+                line: token.line,
+                column: token.column,
+            });
+        }
+        self.expect(TokenKind::Identifier, message)
+    }
+
+    fn declaration_name(&mut self, message: &str) -> Result<Token<'a>, CompileError> {
+        let token = self.identifier(message)?;
+        if is_reserved_identifier(token.source) {
+            return Err(CompileError {
+                kind: ErrorKind::Syntax,
+                message: format!("{}, found reserved word '{}'", message, token.source),
+                line: token.line,
+                column: token.column,
+            });
+        }
+        Ok(token)
+    }
+
+    fn comma_separated<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, CompileError>,
+        terminator: TokenKind,
+    ) -> Result<(Vec<T>, Token<'a>), CompileError> {
+        let mut values = Vec::new();
+        let token = loop {
+            if self.peek_token().kind == terminator {
+                break self.read_token()?;
+            }
+
+            values.push(f(self)?);
+
+            if !self.consume(TokenKind::Comma)? {
+                let message = match terminator {
+                    TokenKind::RightParen => "Expected ',' or ')'",
+                    TokenKind::RightSquareBrace => "Expected ',' or ']'",
+                    _ => "Expected ',' or '}'",
+                };
+                break self.expect(terminator, message)?;
+            }
+        };
+        Ok((values, token))
+    }
+
+    fn arguments(&mut self) -> Result<(Vec<Expression<'a>>, Token<'a>), CompileError> {
+        self.comma_separated(|p| p.expression(), TokenKind::RightParen)
+    }
+
+    fn grouping(&mut self, paren: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        let expression = self.expression()?;
+        self.expect(TokenKind::RightParen, "Expected ')' after expression")?;
+        Ok(Expression::Grouping {
+            paren,
+            expression: Box::new(expression),
+        })
+    }
+
+    fn array(&mut self, bracket: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        let (elements, _) =
+            self.comma_separated(|p| p.expression(), TokenKind::RightSquareBrace)?;
+        Ok(Expression::Array { bracket, elements })
+    }
+
+    fn object(&mut self, brace: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        let (properties, _) = self.comma_separated(
+            |p| {
+                let name = p.identifier("Expected property name")?;
+                p.expect(TokenKind::Colon, "Expected ':' after property name")?;
+                Ok((name, p.expression()?))
+            },
+            TokenKind::RightBrace,
+        )?;
+        Ok(Expression::Object { brace, properties })
+    }
+
+    fn construct(&mut self, keyword: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        log::trace!("{}:{}: Construct expression", keyword.line, keyword.column);
+        // Accesses following `new a.b()` apply to the constructed object.
+        let callee = self.expression_with_precedence(Precedence::Construct)?;
+        let arguments = if self.consume(TokenKind::LeftParen)? {
+            Some(self.arguments()?.0)
+        } else {
+            let token = self.peek_token();
+            let is_name = matches!(
+                callee,
+                Expression::Identifier(_) | Expression::Member { .. } | Expression::Index { .. }
+            );
+            if !is_name
+                && token.kind.precedence() < Precedence::Construct
+                && token.kind.precedence() != Precedence::None
+            {
+                return Err(CompileError {
+                    kind: ErrorKind::InvalidTarget,
+                    message: "Invalid construct target".to_string(),
+                    line: token.line,
+                    column: token.column,
+                });
+            }
+            None
+        };
+        Ok(Expression::New {
+            keyword,
+            callee: Box::new(callee),
+            arguments,
+        })
+    }
+
+    fn delete(&mut self, keyword: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        let target = self.expression_with_precedence(Precedence::Delete)?;
+        let token = self.peek_token();
+        if token.kind.precedence() <= Precedence::Call
+            && token.kind.precedence() != Precedence::None
+        {
+            return Err(CompileError {
+                kind: ErrorKind::InvalidTarget,
+                message: "Invalid delete target".to_string(),
+                line: token.line,
+                column: token.column,
+            });
+        }
+        Ok(Expression::Delete {
+            keyword,
+            target: Box::new(target),
+        })
+    }
+
+    // `++name` or `--name`.
+    fn prefix(&mut self, operator: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        let name = self.identifier("Expected variable")?;
+        Ok(Expression::Prefix { operator, name })
+    }
+
+    // `target++` or `target--`, right after an access.
+    fn postfix(&mut self, target: Expression<'a>) -> Result<Expression<'a>, CompileError> {
+        if target.is_assignable()
+            && matches!(
+                self.peek_token().kind,
+                TokenKind::DoublePlus | TokenKind::DoubleMinus
+            )
+        {
+            let operator = self.read_token()?;
+            return Ok(Expression::Postfix {
+                operator,
+                target: Box::new(target),
+            });
+        }
+        Ok(target)
+    }
+
+    fn binary(
+        &mut self,
+        operator: Token<'a>,
+        left: Expression<'a>,
+    ) -> Result<Expression<'a>, CompileError> {
+        let next_precedence = match operator.kind.precedence() {
+            Precedence::None
+            | Precedence::Construct
+            | Precedence::Delete
+            | Precedence::Path
+            | Precedence::Primary => unreachable!(),
+            Precedence::Assignment => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::BitwiseOr,
+            Precedence::BitwiseOr => Precedence::BitwiseXor,
+            Precedence::BitwiseXor => Precedence::BitwiseAnd,
+            Precedence::BitwiseAnd => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::BitwiseShift,
+            Precedence::BitwiseShift => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary | Precedence::Call => {
+                return Err(expected("Expected binary operator", &operator))
+            }
+        };
+
+        let right = self.expression_with_precedence(next_precedence)?;
+        Ok(Expression::Binary {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+
+    fn expression_with_precedence(
+        &mut self,
+        precedence: Precedence,
+    ) -> Result<Expression<'a>, CompileError> {
+        self.nested(EXPRESSION_TOO_DEEP, |p| p.parse_precedence(precedence))
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expression<'a>, CompileError> {
+        let token = self.read_token()?;
+        let mut expression = match token.kind {
+            TokenKind::LeftParen => self.grouping(token)?,
+            TokenKind::LeftSquareBrace => self.array(token)?,
+            TokenKind::LeftBrace => self.object(token)?,
+            TokenKind::New => self.construct(token)?,
+            TokenKind::Delete => self.delete(token)?,
+            TokenKind::Plus
+            | TokenKind::Minus
+            | TokenKind::Tilda
+            | TokenKind::Bang
+            | TokenKind::Not
+            | TokenKind::Throw
+            | TokenKind::Typeof => {
+                let operand = self.expression_with_precedence(Precedence::Unary)?;
+                Expression::Unary {
+                    operator: token,
+                    operand: Box::new(operand),
+                }
+            }
+            TokenKind::DoublePlus | TokenKind::DoubleMinus => self.prefix(token)?,
+            TokenKind::Number
+            | TokenKind::String
+            | TokenKind::False
+            | TokenKind::Null
+            | TokenKind::True
+            | TokenKind::Undefined => Expression::Literal(token),
+            TokenKind::Function => self.function_expression(token)?,
+            TokenKind::Identifier if self.peek_token().kind == TokenKind::DoubleColon => {
+                let name = self.constant()?;
+                Expression::Constant {
+                    namespace: token,
+                    name,
+                }
+            }
+            TokenKind::Identifier => self.postfix(Expression::Identifier(token))?,
+            _ => return Err(expected("Expected expression", &token)),
+        };
+
+        while self.peek_token().kind.precedence() >= precedence {
+            self.descend(EXPRESSION_TOO_DEEP)?;
+            let token = self.read_token()?;
+            expression = match token.kind {
+                TokenKind::Dot => {
+                    let name = self.expect(TokenKind::Identifier, "Expected name")?;
+                    let member = Expression::Member {
+                        object: Box::new(expression),
+                        name,
+                    };
+                    self.postfix(member)?
+                }
+                TokenKind::LeftSquareBrace => {
+                    let index = self.expression()?;
+                    self.expect(TokenKind::RightSquareBrace, "Expected ']'")?;
+                    let member = Expression::Index {
+                        object: Box::new(expression),
+                        index: Box::new(index),
+                    };
+                    self.postfix(member)?
+                }
+                TokenKind::LeftParen => {
+                    let (arguments, end) = self.arguments()?;
+                    let call = Expression::Call {
+                        callee: Box::new(expression),
+                        arguments,
+                        end,
+                    };
+                    self.postfix(call)?
+                }
+                _ => self.binary(token, expression)?,
+            };
+        }
+
+        if precedence.can_assign() && self.peek_token().kind.is_assign() {
+            let token = self.peek_token();
+            if !expression.is_assignable() {
+                return Err(CompileError {
+                    kind: ErrorKind::InvalidTarget,
+                    message: "Invalid assignment target".to_string(),
+                    line: token.line,
+                    column: token.column,
+                });
+            }
+            let operator = self.read_token()?;
+            let value = self.expression()?;
+            expression = Expression::Assign {
+                operator,
+                target: Box::new(expression),
+                value: Box::new(value),
+            };
+        }
+
+        Ok(expression)
+    }
+
+    fn expression(&mut self) -> Result<Expression<'a>, CompileError> {
+        self.expression_with_precedence(Precedence::Assignment)
+    }
+
+    fn trace_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        self.expect(TokenKind::LeftParen, "Expected '(' before expression")?;
+        let mut arguments = vec![self.expression()?];
+        while self.consume(TokenKind::Comma)? {
+            arguments.push(self.expression()?);
+        }
+        self.expect(TokenKind::RightParen, "Expected ')' after expression")?;
+        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+        Ok(Statement::Trace { keyword, arguments })
+    }
+
+    fn variable_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        let name = self.declaration_name("Expected variable name")?;
+        let value = if self.consume(TokenKind::Equal)? {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+        Ok(Statement::Var { name, value })
+    }
+
+    // const NAME = value;
+    fn const_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        let name = self.declaration_name("Expected constant name")?;
+        self.expect(TokenKind::Equal, "Expected '=' after constant name")?;
+        let value = self.const_expression()?;
+        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+        Ok(Statement::Const { name, value })
+    }
+
+    // A literal, possibly signed, or another constant.
+    fn const_expression(&mut self) -> Result<Expression<'a>, CompileError> {
+        let token = self.read_token()?;
+        match token.kind {
+            TokenKind::Plus | TokenKind::Minus if self.peek_token().kind == TokenKind::Number => {
+                let number = self.read_token()?;
+                Ok(Expression::Unary {
+                    operator: token,
+                    operand: Box::new(Expression::Literal(number)),
+                })
+            }
+            TokenKind::Number
+            | TokenKind::String
+            | TokenKind::False
+            | TokenKind::Null
+            | TokenKind::True
+            | TokenKind::Undefined => Ok(Expression::Literal(token)),
+            TokenKind::Identifier => Ok(Expression::Identifier(token)),
+            _ => Err(expected("Expected constant value", &token)),
+        }
+    }
+
+    fn function_body(
+        &mut self,
+        keyword: Token<'a>,
+        name: Option<Token<'a>>,
+    ) -> Result<Function<'a>, CompileError> {
+        self.expect(TokenKind::LeftParen, "Expected '('")?;
+        let mut parameters = Vec::new();
+        loop {
+            if self.consume(TokenKind::RightParen)? {
+                break;
+            }
+            parameters.push(self.declaration_name("Expected parameter name")?);
+            if !self.consume(TokenKind::Comma)? {
+                self.expect(TokenKind::RightParen, "Expected ')'")?;
+                break;
+            }
+        }
+
+        self.expect(TokenKind::LeftBrace, "Expected '{'")?;
+        let body = self.block_statement()?;
+        Ok(Function {
+            keyword,
+            name,
+            parameters,
+            body,
+        })
+    }
+
+    fn function_declaration(&mut self, keyword: Token<'a>) -> Result<Statement<'a>, CompileError> {
+        let name = self.declaration_name("Expected function name")?;
+        let function = self.function_body(keyword, Some(name))?;
+        Ok(Statement::Function(function))
+    }
+
+    fn function_expression(&mut self, keyword: Token<'a>) -> Result<Expression<'a>, CompileError> {
+        let token = self.peek_token();
+        if token.kind == TokenKind::Identifier {
+            return Err(CompileError {
+                kind: ErrorKind::Syntax,
+                message: "Function expression must be anonymous".to_string(),
+                line: token.line,
+                column: token.column,
+            });
+        }
+        let function = self.function_body(keyword, None)?;
+        Ok(Expression::Function(Box::new(function)))
+    }
+
+    fn expression_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let expression = self.expression()?;
+        if let Err(mut error) = self.expect(TokenKind::Semicolon, "Expected ';' after statement") {
+            // A name followed by more than `;` is likely a misspelled keyword, as in `fucntion f()`.
+            if let Expression::Identifier(name) = &expression {
+                error.message = suggest::did_you_mean(
+                    error.message,
+                    name.source,
+                    STATEMENT_KEYWORDS.iter().copied(),
+                );
+            }
+            return Err(error);
+        }
+        Ok(Statement::Expression(expression))
+    }
+
+    fn block_statement(&mut self) -> Result<Vec<Statement<'a>>, CompileError> {
+        let mut statements = Vec::new();
+        while !matches!(
+            self.peek_token().kind,
+            TokenKind::RightBrace | TokenKind::Eof
+        ) {
+            let start = self.position();
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => self.recover(error, start)?,
+            }
+        }
+
+        self.expect(TokenKind::RightBrace, "Expected '}' after block")?;
+        Ok(statements)
+    }
+
+    fn if_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        self.expect(TokenKind::LeftParen, "Expected '(' after if")?;
+        let condition = self.expression()?;
+        self.expect(TokenKind::RightParen, "Expected ')' after condition")?;
+        let body = self.statement()?;
+        let else_body = if self.consume(TokenKind::Else)? {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+        Ok(Statement::If {
+            keyword,
+            condition,
+            body: Box::new(body),
+            else_body,
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        self.expect(TokenKind::LeftParen, "Expected '(' after for")?;
+        let is_declaration = self.consume(TokenKind::Var)?;
+        let variable = self.declaration_name("Expected variable name")?;
+        self.expect(TokenKind::In, "Expected 'in'")?;
+        let object = self.expression()?;
+        self.expect(TokenKind::RightParen, "Expected ')'")?;
+        let body = self.statement()?;
+        Ok(Statement::ForIn {
+            is_declaration,
+            variable,
+            object,
+            body: Box::new(body),
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        self.expect(TokenKind::LeftParen, "Expected '(' after while")?;
+        let condition = self.expression()?;
+        self.expect(TokenKind::RightParen, "Expected ')' after condition")?;
+        let body = self.statement()?;
+        Ok(Statement::While {
+            keyword,
+            condition,
+            body: Box::new(body),
+        })
+    }
+
+    fn do_while_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        let body = self.statement()?;
+        self.expect(TokenKind::While, "Expected 'while' after body")?;
+        self.expect(TokenKind::LeftParen, "Expected '(' after while")?;
+        let condition = self.expression()?;
+        self.expect(TokenKind::RightParen, "Expected ')' after condition")?;
+        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+        Ok(Statement::DoWhile {
+            keyword,
+            body: Box::new(body),
+            condition,
+        })
+    }
+
+    // The label of a `break` or `continue` statement, if any.
+    fn loop_jump_label(&mut self) -> Result<Option<Token<'a>>, CompileError> {
+        let label = if self.peek_token().kind == TokenKind::Identifier {
+            Some(self.read_token()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+        Ok(label)
+    }
+
+    fn labeled_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let label = self.read_token()?;
+        self.expect(TokenKind::Colon, "Expected ':' after label")?;
+
+        let token = self.peek_token();
+        if !matches!(
+            token.kind,
+            TokenKind::For | TokenKind::While | TokenKind::Do
+        ) {
+            return Err(expected("Expected loop after label", token));
+        }
+
+        let body = self.statement()?;
+        Ok(Statement::Labeled {
+            label,
+            body: Box::new(body),
+        })
+    }
+
+    fn try_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        self.expect(TokenKind::LeftBrace, "Expected '{'")?;
+        let body = self.block_statement()?;
+
+        let catch = if self.consume(TokenKind::Catch)? {
+            self.expect(TokenKind::LeftParen, "Expected '('")?;
+            let variable = self.declaration_name("Expected catch variable")?;
+            self.expect(TokenKind::RightParen, "Expected ')'")?;
+            self.expect(TokenKind::LeftBrace, "Expected '{'")?;
+            let body = self.block_statement()?;
+            Some(Catch { variable, body })
+        } else {
+            None
+        };
+
+        let finally = if self.consume(TokenKind::Finally)? {
+            self.expect(TokenKind::LeftBrace, "Expected '{'")?;
+            Some(self.block_statement()?)
+        } else {
+            None
+        };
+
+        // TODO: Validate existence of catch/finally?
+
+        Ok(Statement::Try {
+            keyword,
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn throw_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        let value = if self.consume(TokenKind::Semicolon)? {
+            None
+        } else {
+            let value = self.expression()?;
+            self.expect(TokenKind::Semicolon, "Expected ';' after statement")?;
+            Some(value)
+        };
+        Ok(Statement::Throw { keyword, value })
+    }
+
+    fn tell_target_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        self.expect(TokenKind::LeftParen, "Expected '(' after tellTarget")?;
+        let target = self.expression()?;
+        self.expect(TokenKind::RightParen, "Expected ')' after target")?;
+        let body = self.statement()?;
+        Ok(Statement::TellTarget {
+            keyword,
+            target,
+            body: Box::new(body),
+        })
+    }
+
+    fn statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        self.nested(STATEMENT_TOO_DEEP, |p| p.parse_statement())
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement<'a>, CompileError> {
+        let token = self.peek_token();
+        log::trace!("{}:{}: Statement", token.line, token.column);
+        if self.peek_token().kind == TokenKind::Identifier
+            && self.peek_next_token()?.kind == TokenKind::Colon
+        {
+            self.labeled_statement()
+        } else if self.consume(TokenKind::Semicolon)? {
+            Ok(Statement::Empty)
+        } else if self.consume(TokenKind::LeftBrace)? {
+            Ok(Statement::Block(self.block_statement()?))
+        } else if self.peek_token().kind == TokenKind::If {
+            self.if_statement()
+        } else if self.consume(TokenKind::For)? {
+            self.for_statement()
+        } else if self.peek_token().kind == TokenKind::While {
+            self.while_statement()
+        } else if self.peek_token().kind == TokenKind::Do {
+            self.do_while_statement()
+        } else if self.peek_token().kind == TokenKind::Break {
+            let keyword = self.read_token()?;
+            let label = self.loop_jump_label()?;
+            Ok(Statement::Break { keyword, label })
+        } else if self.peek_token().kind == TokenKind::Continue {
+            let keyword = self.read_token()?;
+            let label = self.loop_jump_label()?;
+            Ok(Statement::Continue { keyword, label })
+        } else if self.peek_token().kind == TokenKind::Try {
+            self.try_statement()
+        } else if self.peek_token().kind == TokenKind::Throw {
+            self.throw_statement()
+        } else if self.peek_token().kind == TokenKind::TellTarget {
+            self.tell_target_statement()
+        } else if self.peek_token().kind == TokenKind::Trace {
+            self.trace_statement()
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    // A dotted name such as `a.b.C`.
+    fn path(&mut self, message: &str) -> Result<Vec<Token<'a>>, CompileError> {
+        let mut path = vec![self.declaration_name(message)?];
+        while self.consume(TokenKind::Dot)? {
+            path.push(self.declaration_name(message)?);
+        }
+        Ok(path)
+    }
+
+    // An optional `extends a.b.C`.
+    fn superclass(&mut self) -> Result<Option<Vec<Token<'a>>>, CompileError> {
+        if self.peek_token().kind != TokenKind::Identifier || self.peek_token().source != "extends"
+        {
+            return Ok(None);
+        }
+
+        self.read_token()?;
+        let mut superclass = vec![self.identifier("Expected superclass name")?];
+        while self.consume(TokenKind::Dot)? {
+            superclass.push(self.identifier("Expected superclass name")?);
+        }
+        Ok(Some(superclass))
+    }
+
+    fn import_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        let mut path = vec![self.identifier("Expected package name")?];
+        while self.consume(TokenKind::Dot)? {
+            let token = self.peek_token();
+            if token.kind == TokenKind::Star {
+                return Err(CompileError {
+                    kind: ErrorKind::Syntax,
+                    message: "Wildcard imports are not supported".to_string(),
+                    line: token.line,
+                    column: token.column,
+                });
+            }
+            path.push(self.identifier("Expected class name")?);
+        }
+        self.expect(TokenKind::Semicolon, "Expected ';' after import")?;
+        Ok(Statement::Import(Import {
+            keyword,
+            path,
+            defines: self.preprocessor.defines(),
+        }))
+    }
+
+    // Parse `static`, `public` and `private` before a class member.
+    fn member_modifiers(&mut self) -> Result<(bool, bool), CompileError> {
+        let mut is_static = false;
+        let mut access = None;
+        while self.peek_token().kind == TokenKind::Identifier {
+            let token = self.peek_token();
+            match token.source {
+                "static" if !is_static => is_static = true,
+                "public" | "private" if access.is_none() => access = Some(token.source),
+                "static" | "public" | "private" => {
+                    return Err(CompileError {
+                        kind: ErrorKind::Syntax,
+                        message: format!("Unexpected '{}' after modifiers", token.source),
+                        line: token.line,
+                        column: token.column,
+                    })
+                }
+                _ => break,
+            }
+            self.read_token()?;
+        }
+        Ok((is_static, access == Some("private")))
+    }
+
+    // Skip an optional `: Type` annotation.
+    fn type_annotation(&mut self) -> Result<(), CompileError> {
+        if self.consume(TokenKind::Colon)? && !self.consume(TokenKind::Function)? {
+            self.identifier("Expected type name")?;
+            while self.consume(TokenKind::Dot)? {
+                self.identifier("Expected type name")?;
+            }
+        }
+        Ok(())
+    }
+
+    // The `get` or `set` before the name of an accessor method.
+    fn accessor(&mut self) -> Result<Option<Token<'a>>, CompileError> {
+        let token = self.peek_token();
+        if matches!(token.source, "get" | "set")
+            && self.peek_next_token()?.kind == TokenKind::Identifier
+        {
+            return Ok(Some(self.read_token()?));
+        }
+        Ok(None)
+    }
+
+    fn intrinsic_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        self.read_token()?;
+        let keyword = self.peek_token();
+        if keyword.kind != TokenKind::Identifier || keyword.source != "class" {
+            return Err(expected("Expected 'class' after 'intrinsic'", keyword));
+        }
+        self.read_token()?;
+
+        let path = self.path("Expected class name")?;
+        let superclass = self.superclass()?;
+        self.expect(TokenKind::LeftBrace, "Expected '{' after class name")?;
+
+        let mut members = Vec::new();
+        while !self.consume(TokenKind::RightBrace)? {
+            let (is_static, is_private) = self.member_modifiers()?;
+            let name = if self.consume(TokenKind::Var)? {
+                let name = self.declaration_name("Expected member name")?;
+                self.type_annotation()?;
+                name
+            } else if self.consume(TokenKind::Function)? {
+                self.accessor()?;
+                let name = self.declaration_name("Expected method name")?;
+                self.expect(TokenKind::LeftParen, "Expected '('")?;
+                if !self.consume(TokenKind::RightParen)? {
+                    loop {
+                        self.declaration_name("Expected parameter name")?;
+                        self.type_annotation()?;
+                        if !self.consume(TokenKind::Comma)? {
+                            self.expect(TokenKind::RightParen, "Expected ')'")?;
+                            break;
+                        }
+                    }
+                }
+                self.type_annotation()?;
+                name
+            } else {
+                return Err(expected("Expected class member", self.peek_token()));
+            };
+            self.expect(TokenKind::Semicolon, "Expected ';' after member")?;
+            members.push(IntrinsicMember {
+                name,
+                is_static,
+                is_private,
+            });
+        }
+
+        Ok(Statement::Intrinsic(Intrinsic {
+            path,
+            superclass,
+            members,
+        }))
+    }
+
+    fn class_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        self.read_token()?;
+        let path = self.path("Expected class name")?;
+        let superclass = self.superclass()?;
+        self.expect(TokenKind::LeftBrace, "Expected '{' after class name")?;
+
+        let mut members = Vec::new();
+        while !self.consume(TokenKind::RightBrace)? {
+            let start = self.position();
+            match self.class_member() {
+                Ok(member) => members.push(member),
+                Err(error) => self.recover(error, start)?,
+            }
+        }
+
+        Ok(Statement::Class(Class {
+            path,
+            superclass,
+            members,
+        }))
+    }
+
+    fn class_member(&mut self) -> Result<ClassMember<'a>, CompileError> {
+        let (is_static, is_private) = self.member_modifiers()?;
+        let kind = if self.consume(TokenKind::Var)? {
+            let name = self.declaration_name("Expected member name")?;
+            let value = if self.consume(TokenKind::Equal)? {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.expect(TokenKind::Semicolon, "Expected ';' after member")?;
+            ClassMemberKind::Var { name, value }
+        } else if self.peek_token().kind == TokenKind::Function {
+            let keyword = self.read_token()?;
+            let accessor = self.accessor()?;
+            let name = self.declaration_name("Expected method name")?;
+            let function = self.function_body(keyword, Some(name))?;
+            ClassMemberKind::Method { accessor, function }
+        } else {
+            return Err(expected("Expected class member", self.peek_token()));
+        };
+        Ok(ClassMember {
+            is_static,
+            is_private,
+            kind,
+        })
+    }
+
+    // The name after `namespace::`, such as `DEBUG` in `CONFIG::DEBUG`.
+    fn constant(&mut self) -> Result<Token<'a>, CompileError> {
+        self.expect(TokenKind::DoubleColon, "Expected '::' after namespace")?;
+        self.expect(TokenKind::Identifier, "Expected constant name after '::'")
+    }
+
+    fn peek_constant(&self) -> Result<bool, CompileError> {
+        Ok(self.peek_token().kind == TokenKind::Identifier
+            && self.peek_next_token()?.kind == TokenKind::DoubleColon)
+    }
+
+    // A declaration preceded by a constant such as `CONFIG::DEBUG`.
+    fn conditional_declaration(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<Statement<'a>, CompileError>,
+    ) -> Result<Statement<'a>, CompileError> {
+        let namespace = self.read_token()?;
+        let name = self.constant()?;
+        let body = f(self)?;
+        Ok(Statement::Conditional {
+            namespace,
+            name,
+            body: Box::new(body),
+        })
+    }
+
+    fn declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        self.nested(STATEMENT_TOO_DEEP, |p| p.parse_declaration())
+    }
+
+    fn parse_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        if self.peek_constant()? {
+            self.conditional_declaration(|p| p.declaration())
+        } else if self.consume(TokenKind::Var)? {
+            self.variable_declaration()
+        } else if self.peek_declaration("const")? {
+            self.read_token()?;
+            self.const_declaration()
+        } else if self.peek_token().kind == TokenKind::Function {
+            let keyword = self.read_token()?;
+            self.function_declaration(keyword)
+        } else {
+            self.statement()
+        }
+    }
+
+    fn top_level_declaration(&mut self) -> Result<Statement<'a>, CompileError> {
+        if self.peek_constant()? {
+            self.conditional_declaration(|p| p.top_level_declaration())
+        } else if self.peek_declaration("dynamic")? {
+            self.read_token()?;
+            if self.peek_declaration("intrinsic")? {
+                self.intrinsic_declaration()
+            } else if self.peek_declaration("class")? {
+                self.class_declaration()
+            } else {
+                Err(expected(
+                    "Expected class after 'dynamic'",
+                    self.peek_token(),
+                ))
+            }
+        } else if self.peek_declaration("intrinsic")? {
+            self.intrinsic_declaration()
+        } else if self.peek_declaration("class")? {
+            self.class_declaration()
+        } else if self.peek_declaration("import")? {
+            self.import_declaration()
+        } else {
+            self.declaration()
+        }
+    }
+
+    fn peek_directive(&self, name: &str) -> bool {
+        let token = self.peek_token();
+        token.kind == TokenKind::Directive && token.source == name
+    }
+
+    fn init_clip(&mut self) -> Result<InitClip<'a>, CompileError> {
+        let directive = self.read_token()?;
+        let token = self.peek_token();
+        let order = if token.kind == TokenKind::Number && token.line == directive.line {
+            let token = self.read_token()?;
+            token.source.parse().map_err(|_| CompileError {
+                kind: ErrorKind::InvalidValue,
+                message: "Invalid initclip order".to_string(),
+                line: token.line,
+                column: token.column,
+            })?
+        } else {
+            0
+        };
+
+        let mut body = Vec::new();
+        while !self.peek_directive("#endinitclip") {
+            if self.peek_token().kind == TokenKind::Eof {
+                return Err(CompileError {
+                    kind: ErrorKind::Syntax,
+                    message: "Expected '#endinitclip' after '#initclip', found end of file"
+                        .to_string(),
+                    line: directive.line,
+                    column: directive.column,
+                });
+            }
+            let start = self.position();
+            match self.top_level_declaration() {
+                Ok(statement) => body.push(statement),
+                Err(error) => self.recover(error, start)?,
+            }
+        }
+        self.read_token()?;
+        Ok(InitClip { order, body })
+    }
+
+    fn frame(&mut self) -> Result<Frame<'a>, CompileError> {
+        let directive = self.read_token()?;
+        let mut argument = |kind| -> Result<Option<Token<'a>>, CompileError> {
+            let token = self.peek_token();
+            if token.kind == kind && token.line == directive.line {
+                Ok(Some(self.read_token()?))
+            } else {
+                Ok(None)
+            }
+        };
+        let number = argument(TokenKind::Number)?;
+        let label = argument(TokenKind::String)?;
+        Ok(Frame {
+            directive,
+            number,
+            label,
+        })
+    }
+
+    fn sprite(&mut self) -> Result<Sprite<'a>, CompileError> {
+        let directive = self.read_token()?;
+        let token = self.peek_token();
+        let name = if token.kind == TokenKind::String && token.line == directive.line {
+            Some(self.read_token()?)
+        } else {
+            None
+        };
+
+        let mut body = Vec::new();
+        while !self.peek_directive("#endsprite") {
+            if self.peek_token().kind == TokenKind::Eof {
+                return Err(CompileError {
+                    kind: ErrorKind::Syntax,
+                    message: "Expected '#endsprite' after '#sprite', found end of file".to_string(),
+                    line: directive.line,
+                    column: directive.column,
+                });
+            }
+            let start = self.position();
+            match self.top_level_declaration() {
+                Ok(statement) => body.push(statement),
+                Err(error) => self.recover(error, start)?,
+            }
+        }
+        self.read_token()?;
+        Ok(Sprite {
+            directive,
+            name,
+            body,
+        })
+    }
+
+    fn export(&mut self) -> Result<Export<'a>, CompileError> {
+        let directive = self.read_token()?;
+        let token = self.peek_token();
+        if !matches!(token.kind, TokenKind::Identifier | TokenKind::String)
+            || token.line != directive.line
+        {
+            return Err(expected("Expected a linkage name after '#export'", token));
+        }
+        let name = self.read_token()?;
+        let token = self.peek_token();
+        if token.kind != TokenKind::Number || token.line != directive.line {
+            return Err(expected(
+                "Expected a character id after the linkage name",
+                token,
+            ));
+        }
+        let token = self.read_token()?;
+        let id = token.source.parse().map_err(|_| CompileError {
+            kind: ErrorKind::InvalidValue,
+            message: "Invalid character id".to_string(),
+            line: token.line,
+            column: token.column,
+        })?;
+        Ok(Export { name, id })
+    }
+
+    // Whether a declaration starting with a contextual keyword, such as `class Foo`, follows.
+    fn peek_declaration(&self, keyword: &str) -> Result<bool, CompileError> {
+        let token = self.peek_token();
+        Ok(token.kind == TokenKind::Identifier
+            && token.source == keyword
+            && self.peek_next_token()?.kind == TokenKind::Identifier)
+    }
+
+    // Whether an event handler block such as `on(...)` follows.
+    fn peek_handler(&self, keyword: &str) -> Result<bool, CompileError> {
+        let token = self.peek_token();
+        Ok(token.kind == TokenKind::Identifier
+            && token.source == keyword
+            && self.peek_next_token()?.kind == TokenKind::LeftParen)
+    }
+
+    fn handler(&mut self) -> Result<Handler<'a>, CompileError> {
+        let keyword = self.read_token()?;
+        self.expect(TokenKind::LeftParen, "Expected '('")?;
+
+        let mut events = Vec::new();
+        loop {
+            let name = self.expect(TokenKind::Identifier, "Expected event name")?;
+            if name.source == "keyPress" {
+                let key = self.expect(TokenKind::String, "Expected key after keyPress")?;
+                events.push(Event::KeyPress(key));
+            } else {
+                events.push(Event::Name(name));
+            }
+
+            if !self.consume(TokenKind::Comma)? {
+                self.expect(TokenKind::RightParen, "Expected ')' after events")?;
+                break;
+            }
+        }
+
+        self.expect(TokenKind::LeftBrace, "Expected '{'")?;
+        let body = self.block_statement()?;
+        Ok(Handler {
+            keyword,
+            events,
+            body,
+        })
+    }
+
+    fn item(&mut self) -> Result<Item<'a>, CompileError> {
+        if self.peek_handler("on")? || self.peek_handler("onClipEvent")? {
+            Ok(Item::Handler(self.handler()?))
+        } else if self.peek_directive("#initclip") {
+            Ok(Item::InitClip(self.init_clip()?))
+        } else if self.peek_directive("#frame") {
+            Ok(Item::Frame(self.frame()?))
+        } else if self.peek_directive("#sprite") {
+            Ok(Item::Sprite(self.sprite()?))
+        } else if self.peek_directive("#export") {
+            Ok(Item::Export(self.export()?))
+        } else {
+            Ok(Item::Statement(self.top_level_declaration()?))
+        }
+    }
+
+    fn items(&mut self) -> Result<Vec<Item<'a>>, CompileError> {
+        // Initialize `self.current`.
+        self.read_token()?;
+
+        let mut items = Vec::new();
+        while self.peek_token().kind != TokenKind::Eof {
+            let start = self.position();
+            match self.item() {
+                Ok(item) => items.push(item),
+                Err(error) => self.recover(error, start)?,
+            }
+        }
+        Ok(items)
+    }
+
+    // Parse the whole source, going on after errors to report every one of them.
+    pub fn parse(&mut self) -> Result<Program<'a>, Vec<CompileError>> {
+        let result = self.items();
+        if let Err(error) = &result {
+            if !self.gave_up {
+                self.errors.push(error.clone());
+            }
+        }
+        match result {
+            Ok(items) if self.errors.is_empty() => {
+                log::debug!("Parsed {} top-level items", items.len());
+                Ok(Program { items })
+            }
+            _ => Err(std::mem::take(&mut self.errors)),
+        }
+    }
+}
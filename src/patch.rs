@@ -0,0 +1,340 @@
+// Patching of existing SWF files with compiled scripts, for adding scripts to movies made with
+// other tools, or replacing some of their scripts.
+
+use crate::compiler::{compile_to_actions, CompileOptions};
+use crate::diagnostic::Diagnostic;
+use crate::scanner::{CompileError, ErrorKind};
+
+// Where the compiled actions go in a SWF file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Patch {
+    // Add the actions to the frame, counting from 1, after those that it has.
+    Inject(u16),
+    // Replace the actions of the frame, counting from 1.
+    ReplaceFrame(u16),
+    // Replace the init clip actions of the sprite with the character id.
+    ReplaceInitClip(swf::CharacterId),
+    // Replace the body of each function with the name, in the actions of frames and init clips of
+    // the movie and its sprites. The parameters of the function are kept, by name.
+    ReplaceFunction(String),
+}
+
+// Compile `source` and add its actions to the frame `frame`, counting from 1, of the SWF file that
+// `host` reads, writing the patched file to `output`, like `patch_swf`.
+pub fn inject_into_swf<R: std::io::Read, W: std::io::Write>(
+    source: &str,
+    options: &CompileOptions,
+    host: R,
+    frame: u16,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    patch_swf(source, options, host, &Patch::Inject(frame), output)
+}
+
+// Compile `source` and put its actions where `patch` says in the SWF file that `host` reads,
+// writing the patched file to `output`. The other tags and the header are kept as they are, and
+// the source is compiled for the SWF version of the header. Returns warnings about the source, or
+// the errors along with them.
+pub fn patch_swf<R: std::io::Read, W: std::io::Write>(
+    source: &str,
+    options: &CompileOptions,
+    host: R,
+    patch: &Patch,
+    output: W,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let swf_buf = swf::decompress_swf(host).map_err(|error| vec![read_error(error).into()])?;
+    let swf = swf::parse_swf(&swf_buf).map_err(|error| vec![read_error(error).into()])?;
+    let options = CompileOptions {
+        swf_version: swf.header.version(),
+        ..options.clone()
+    };
+    let (warnings, action_data) = compile_to_actions(source, &options)?;
+    let mut tags = swf.tags;
+    let not_found = |message: String| {
+        vec![Diagnostic::from(CompileError {
+            kind: ErrorKind::InvalidValue,
+            message,
+            line: 0,
+            column: 0,
+        })]
+    };
+    let frame_missing = |frame: u16| {
+        not_found(format!(
+            "Frame {} is not among the {} frames of the SWF file",
+            frame,
+            swf.header.num_frames()
+        ))
+    };
+
+    // The action data of the `DoAction` and `DoInitAction` tags with functions replaced, in the
+    // order of `action_data_mut`, which the tags are then changed to.
+    let mut replaced = Vec::new();
+    match patch {
+        Patch::Inject(frame) => {
+            let Some(index) = show_frame_index(&tags, *frame) else {
+                return Err(frame_missing(*frame));
+            };
+            // Actions of a frame run once its other tags are processed, so they come last.
+            tags.insert(index, swf::Tag::DoAction(&action_data));
+        }
+        Patch::ReplaceFrame(frame) => {
+            let Some(end) = show_frame_index(&tags, *frame) else {
+                return Err(frame_missing(*frame));
+            };
+            let start = tags[..end]
+                .iter()
+                .rposition(|tag| matches!(tag, swf::Tag::ShowFrame))
+                .map_or(0, |index| index + 1);
+            let frame_tags = tags.splice(start..end, []).collect::<Vec<_>>();
+            let kept = frame_tags
+                .into_iter()
+                .filter(|tag| !matches!(tag, swf::Tag::DoAction(_)));
+            tags.splice(start..start, kept);
+            let index = show_frame_index(&tags, *frame).unwrap();
+            tags.insert(index, swf::Tag::DoAction(&action_data));
+        }
+        Patch::ReplaceInitClip(id) => {
+            let init_clip = tags.iter_mut().find_map(|tag| match tag {
+                swf::Tag::DoInitAction(init_clip) if init_clip.id == *id => Some(init_clip),
+                _ => None,
+            });
+            let Some(init_clip) = init_clip else {
+                return Err(not_found(format!(
+                    "Sprite {} has no init clip actions in the SWF file",
+                    id
+                )));
+            };
+            init_clip.action_data = &action_data;
+        }
+        Patch::ReplaceFunction(name) => {
+            if action_data.len() > u16::MAX.into() {
+                return Err(vec![CompileError {
+                    kind: ErrorKind::TooLarge,
+                    message: "Function body too large".to_string(),
+                    line: 0,
+                    column: 0,
+                }
+                .into()]);
+            }
+            let mut error = None;
+            action_data_mut(&mut tags, &mut |data| {
+                if error.is_none() {
+                    let result = replace_function(data, name, &action_data);
+                    replaced.push(result.unwrap_or_else(|replace_error| {
+                        error = Some(replace_error);
+                        None
+                    }));
+                }
+            });
+            if let Some(error) = error {
+                return Err(vec![error.into()]);
+            }
+            if replaced.iter().all(Option::is_none) {
+                return Err(not_found(format!(
+                    "No function is named '{}' in the SWF file",
+                    name
+                )));
+            }
+            let mut replaced = replaced.iter();
+            action_data_mut(&mut tags, &mut |data| {
+                if let Some(Some(action_data)) = replaced.next() {
+                    *data = action_data;
+                }
+            });
+        }
+    }
+    swf::write_swf(swf.header.swf_header(), &tags, output)
+        .map_err(|error| vec![CompileError::from(error).into()])?;
+    Ok(warnings)
+}
+
+// Index of the `ShowFrame` tag that ends the frame `frame`, counting from 1, among `tags`.
+fn show_frame_index(tags: &[swf::Tag], frame: u16) -> Option<usize> {
+    let frame = usize::from(frame.checked_sub(1)?);
+    tags.iter()
+        .enumerate()
+        .filter(|(_, tag)| matches!(tag, swf::Tag::ShowFrame))
+        .nth(frame)
+        .map(|(index, _)| index)
+}
+
+// Call `f` with the action data of each `DoAction` and `DoInitAction` tag among `tags`, including
+// those of sprites, in order.
+fn action_data_mut<'a>(tags: &mut [swf::Tag<'a>], f: &mut impl FnMut(&mut &'a [u8])) {
+    for tag in tags {
+        match tag {
+            swf::Tag::DoAction(action_data) => f(action_data),
+            swf::Tag::DoInitAction(init_clip) => f(&mut init_clip.action_data),
+            swf::Tag::DefineSprite(sprite) => action_data_mut(&mut sprite.tags, f),
+            _ => {}
+        }
+    }
+}
+
+// `action_data` with the body of each function named `name` replaced by `body`, and the branches
+// over them moved, or `None` if no function has that name. Functions are searched for in the
+// bodies of others and of blocks too.
+fn replace_function(
+    action_data: &[u8],
+    name: &str,
+    body: &[u8],
+) -> Result<Option<Vec<u8>>, CompileError> {
+    let too_large = |message: &str| CompileError {
+        kind: ErrorKind::TooLarge,
+        message: format!("{} too large after replacing function '{}'", message, name),
+        line: 0,
+        column: 0,
+    };
+    let mut reader = swf::avm1::read::Reader::new(action_data, 0);
+    let mut output = Vec::new();
+    // The offsets of each action in `action_data` and in `output`.
+    let mut offsets = Vec::new();
+    // The offsets of the branches in `output`, along with those of their targets in `action_data`.
+    let mut branches = Vec::new();
+    let mut is_replaced = false;
+    while !reader.get_ref().is_empty() {
+        let start = action_data.len() - reader.get_ref().len();
+        offsets.push((start, output.len()));
+        let action = reader.read_action().map_err(read_error)?;
+        let end = action_data.len() - reader.get_ref().len();
+
+        // The bodies of the action, with functions replaced.
+        let mut bodies = Vec::new();
+        let mut nested = |actions: &[u8]| -> Result<(), CompileError> {
+            let nested = replace_function(actions, name, body)?;
+            if nested
+                .as_ref()
+                .is_some_and(|nested| nested.len() > u16::MAX.into())
+            {
+                return Err(too_large("Body"));
+            }
+            is_replaced |= nested.is_some();
+            bodies.push(nested.unwrap_or_else(|| actions.to_vec()));
+            Ok(())
+        };
+        match &action {
+            swf::avm1::types::Action::DefineFunction(function)
+                if function.name.as_bytes() != name.as_bytes() =>
+            {
+                nested(function.actions)?
+            }
+            swf::avm1::types::Action::DefineFunction2(function)
+                if function.name.as_bytes() != name.as_bytes() =>
+            {
+                nested(function.actions)?
+            }
+            swf::avm1::types::Action::Try(try_action) => {
+                nested(try_action.try_body)?;
+                if let Some((_, catch_body)) = try_action.catch_body {
+                    nested(catch_body)?;
+                }
+                if let Some(finally_body) = try_action.finally_body {
+                    nested(finally_body)?;
+                }
+            }
+            swf::avm1::types::Action::With(with) => nested(with.actions)?,
+            swf::avm1::types::Action::Jump(jump) => {
+                let target = end as isize + isize::from(jump.offset);
+                branches.push((output.len(), target));
+            }
+            swf::avm1::types::Action::If(branch) => {
+                let target = end as isize + isize::from(branch.offset);
+                branches.push((output.len(), target));
+            }
+            _ => {}
+        }
+
+        let mut bodies = bodies.iter().map(Vec::as_slice);
+        let action = match action {
+            swf::avm1::types::Action::DefineFunction(function) => {
+                let actions = bodies.next().unwrap_or_else(|| {
+                    is_replaced = true;
+                    body
+                });
+                swf::avm1::types::Action::DefineFunction(swf::avm1::types::DefineFunction {
+                    actions,
+                    ..function
+                })
+            }
+            swf::avm1::types::Action::DefineFunction2(function) => match bodies.next() {
+                Some(actions) => {
+                    swf::avm1::types::Action::DefineFunction2(swf::avm1::types::DefineFunction2 {
+                        actions,
+                        ..function
+                    })
+                }
+                // The new body looks parameters up by name, and only uses the registers of top
+                // level code.
+                None => {
+                    is_replaced = true;
+                    let params = function
+                        .params
+                        .into_iter()
+                        .map(|param| swf::avm1::types::FunctionParam {
+                            register_index: None,
+                            ..param
+                        })
+                        .collect();
+                    swf::avm1::types::Action::DefineFunction2(swf::avm1::types::DefineFunction2 {
+                        name: function.name,
+                        register_count: 4,
+                        params,
+                        flags: swf::avm1::types::FunctionFlags::empty(),
+                        actions: body,
+                    })
+                }
+            },
+            swf::avm1::types::Action::Try(try_action) => {
+                let try_body = bodies.next().unwrap();
+                let catch_body = try_action
+                    .catch_body
+                    .map(|(catch_var, _)| (catch_var, bodies.next().unwrap()));
+                let finally_body = try_action.finally_body.map(|_| bodies.next().unwrap());
+                swf::avm1::types::Action::Try(swf::avm1::types::Try {
+                    try_body,
+                    catch_body,
+                    finally_body,
+                })
+            }
+            swf::avm1::types::Action::With(_) => {
+                swf::avm1::types::Action::With(swf::avm1::types::With {
+                    actions: bodies.next().unwrap(),
+                })
+            }
+            action => action,
+        };
+        let mut writer = swf::avm1::write::Writer::new(&mut output, 0);
+        writer.write_action(&action).unwrap();
+    }
+    if !is_replaced {
+        return Ok(None);
+    }
+
+    // Branches keep their size, so only their offsets change.
+    offsets.push((action_data.len(), output.len()));
+    for (branch, target) in branches {
+        // Branches into the middle of actions, as in obfuscated code, are left as they are.
+        let Some(index) = usize::try_from(target)
+            .ok()
+            .and_then(|target| offsets.binary_search_by_key(&target, |(old, _)| *old).ok())
+        else {
+            continue;
+        };
+        let end = branch + 5;
+        let offset = offsets[index].1 as isize - end as isize;
+        let Ok(offset) = i16::try_from(offset) else {
+            return Err(too_large("Branch"));
+        };
+        output[end - 2..end].copy_from_slice(&offset.to_le_bytes());
+    }
+    Ok(Some(output))
+}
+
+fn read_error(error: swf::error::Error) -> CompileError {
+    CompileError {
+        kind: ErrorKind::Io,
+        message: format!("Cannot read SWF file: {}", error),
+        line: 0,
+        column: 0,
+    }
+}
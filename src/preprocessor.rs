@@ -0,0 +1,535 @@
+use crate::scanner::{CompileError, ErrorKind, Scanner, Token, TokenKind};
+use std::rc::Rc;
+
+#[derive(Clone)]
+struct Define<'a> {
+    name: &'a str,
+    tokens: Vec<Token<'a>>,
+}
+
+// The definitions in effect at some point of a source file.
+#[derive(Clone)]
+pub struct Defines<'a>(Rc<Vec<Define<'a>>>);
+
+// An `#if` block whose taken branch is being read.
+#[derive(Clone)]
+struct Conditional {
+    line: usize,
+    column: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Number(f64),
+    String(&'a str),
+}
+
+impl Value<'_> {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Number(number) => *number != 0.0,
+            Self::String(string) => !string.is_empty(),
+        }
+    }
+}
+
+// Handles `#define`, `#undef`, `#if`, `#ifdef`, `#ifndef`, `#elif`, `#else`, `#endif` and `#pragma`
+// between the scanner and the compiler. Other directives are passed through.
+#[derive(Clone)]
+pub struct Preprocessor<'a> {
+    scanner: Scanner<'a>,
+    // Definitions read before the source, such as command line defines.
+    prelude: Option<Scanner<'a>>,
+    defines: Defines<'a>,
+    // Tokens of macros being expanded, in reverse order, each with the names of the macros it was
+    // expanded from.
+    pending: Vec<(Token<'a>, Rc<Vec<&'a str>>)>,
+    conditionals: Vec<Conditional>,
+    // Whether `#pragma strict` was read.
+    strict: bool,
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            scanner: Scanner::new(source),
+            prelude: None,
+            defines: Defines(Rc::new(Vec::new())),
+            pending: Vec::new(),
+            conditionals: Vec::new(),
+            strict: false,
+        }
+    }
+
+    // Read the directives of `prelude` before the source.
+    pub fn set_prelude(&mut self, prelude: &'a str) {
+        self.prelude = Some(Scanner::new(prelude));
+    }
+
+    pub fn defines(&self) -> Defines<'a> {
+        self.defines.clone()
+    }
+
+    // Start with the definitions of another file, such as the one importing this one.
+    pub fn inherit_defines(&mut self, defines: Defines<'a>) {
+        self.defines = defines;
+    }
+
+    // Whether the source asks for strict mode by `#pragma strict`, once read.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    fn read_raw_token(&mut self) -> Result<Token<'a>, CompileError> {
+        if let Some((token, _)) = self.pending.pop() {
+            return Ok(token);
+        }
+        if let Some(prelude) = &mut self.prelude {
+            let token = prelude.read_token()?;
+            if token.kind != TokenKind::Eof {
+                return Ok(token);
+            }
+            self.prelude = None;
+        }
+        self.scanner.read_token()
+    }
+
+    fn peek_raw_token(&self) -> Result<Token<'a>, CompileError> {
+        if let Some((token, _)) = self.pending.last() {
+            return Ok(token.clone());
+        }
+        if let Some(prelude) = &self.prelude {
+            let token = prelude.clone().read_token()?;
+            if token.kind != TokenKind::Eof {
+                return Ok(token);
+            }
+        }
+        self.scanner.clone().read_token()
+    }
+
+    // The rest of the tokens on the line of `directive`.
+    fn read_line(&mut self, directive: &Token) -> Result<Vec<Token<'a>>, CompileError> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.peek_raw_token()?;
+            if token.kind == TokenKind::Eof || token.line != directive.line {
+                return Ok(tokens);
+            }
+            tokens.push(self.read_raw_token()?);
+        }
+    }
+
+    fn read_name(&mut self, directive: &Token) -> Result<Token<'a>, CompileError> {
+        let token = self.read_raw_token()?;
+        if token.kind != TokenKind::Identifier || token.line != directive.line {
+            return Err(CompileError {
+                kind: ErrorKind::Preprocessor,
+                message: format!("Expected name after '{}'", directive.source),
+                line: directive.line,
+                column: directive.column,
+            });
+        }
+        Ok(token)
+    }
+
+    fn define(&self, name: &str) -> Option<&Define<'a>> {
+        self.defines.0.iter().find(|define| define.name == name)
+    }
+
+    fn undefine(&mut self, name: &str) {
+        Rc::make_mut(&mut self.defines.0).retain(|define| define.name != name);
+    }
+
+    pub fn read_token(&mut self) -> Result<Token<'a>, CompileError> {
+        loop {
+            let (token, expanded_from) = match self.pending.pop() {
+                Some(pending) => pending,
+                None => (self.read_raw_token()?, Rc::default()),
+            };
+            match token.kind {
+                TokenKind::Identifier => {
+                    // As in C, a name is left as is within its own expansion, so recursive
+                    // definitions end.
+                    let Some(define) = self
+                        .define(token.source)
+                        .filter(|_| !expanded_from.contains(&token.source))
+                    else {
+                        return Ok(token);
+                    };
+                    let mut names = (*expanded_from).clone();
+                    names.push(token.source);
+                    let names = Rc::new(names);
+                    let tokens: Vec<_> = define
+                        .tokens
+                        .iter()
+                        .rev()
+                        .map(|token| (token.clone(), names.clone()))
+                        .collect();
+                    self.pending.extend(tokens);
+                }
+                TokenKind::Directive => match token.source {
+                    "#define" => {
+                        let name = self.read_name(&token)?;
+                        let tokens = self.read_line(&token)?;
+                        self.undefine(name.source);
+                        Rc::make_mut(&mut self.defines.0).push(Define {
+                            name: name.source,
+                            tokens,
+                        });
+                    }
+                    "#undef" => {
+                        let name = self.read_name(&token)?;
+                        self.undefine(name.source);
+                    }
+                    "#pragma" => {
+                        let name = self.read_name(&token)?;
+                        match name.source {
+                            "strict" => self.strict = true,
+                            _ => {
+                                return Err(CompileError {
+                                    kind: ErrorKind::Preprocessor,
+                                    message: format!("Unknown pragma '{}'", name.source),
+                                    line: name.line,
+                                    column: name.column,
+                                })
+                            }
+                        }
+                    }
+                    "#ifdef" | "#ifndef" => {
+                        let name = self.read_name(&token)?;
+                        let condition =
+                            self.define(name.source).is_some() == (token.source == "#ifdef");
+                        self.conditional(&token, condition)?;
+                    }
+                    "#if" => {
+                        let condition = self.condition(&token)?;
+                        self.conditional(&token, condition)?;
+                    }
+                    "#elif" | "#else" => {
+                        // The taken branch ended.
+                        if self.conditionals.pop().is_none() {
+                            return Err(CompileError {
+                                kind: ErrorKind::Preprocessor,
+                                message: format!("Unexpected '{}'", token.source),
+                                line: token.line,
+                                column: token.column,
+                            });
+                        }
+                        self.skip_branches(&token, true)?;
+                    }
+                    "#endif" => {
+                        if self.conditionals.pop().is_none() {
+                            return Err(CompileError {
+                                kind: ErrorKind::Preprocessor,
+                                message: "Unexpected '#endif'".to_string(),
+                                line: token.line,
+                                column: token.column,
+                            });
+                        }
+                    }
+                    _ => return Ok(token),
+                },
+                TokenKind::Eof => {
+                    if let Some(conditional) = self.conditionals.last() {
+                        return Err(CompileError {
+                            kind: ErrorKind::Preprocessor,
+                            message: "Expected '#endif'".to_string(),
+                            line: conditional.line,
+                            column: conditional.column,
+                        });
+                    }
+                    return Ok(token);
+                }
+                _ => return Ok(token),
+            }
+        }
+    }
+
+    // Enter the branch of an `#if`, or look for the next one to take.
+    fn conditional(&mut self, directive: &Token, condition: bool) -> Result<(), CompileError> {
+        if condition {
+            self.conditionals.push(Conditional {
+                line: directive.line,
+                column: directive.column,
+            });
+            Ok(())
+        } else {
+            self.skip_branches(directive, false)
+        }
+    }
+
+    // Skip tokens up to the next branch to take, or to the matching `#endif`.
+    fn skip_branches(&mut self, directive: &Token, taken: bool) -> Result<(), CompileError> {
+        let mut depth = 0;
+        loop {
+            let token = self.read_raw_token()?;
+            match (token.kind, token.source) {
+                (TokenKind::Eof, _) => {
+                    return Err(CompileError {
+                        kind: ErrorKind::Preprocessor,
+                        message: "Expected '#endif'".to_string(),
+                        line: directive.line,
+                        column: directive.column,
+                    })
+                }
+                (TokenKind::Directive, "#if" | "#ifdef" | "#ifndef") => depth += 1,
+                (TokenKind::Directive, "#endif") if depth > 0 => depth -= 1,
+                (TokenKind::Directive, "#endif") => return Ok(()),
+                (TokenKind::Directive, "#elif") if depth == 0 && !taken => {
+                    let condition = self.condition(&token)?;
+                    if condition {
+                        return self.conditional(&token, true);
+                    }
+                }
+                (TokenKind::Directive, "#else") if depth == 0 && !taken => {
+                    return self.conditional(&token, true);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Evaluate the expression on the line of an `#if` or `#elif`.
+    fn condition(&mut self, directive: &Token) -> Result<bool, CompileError> {
+        let tokens = self.read_line(directive)?;
+        let mut evaluator = Evaluator {
+            preprocessor: self,
+            tokens: &tokens,
+            position: 0,
+            directive,
+            expanding: Vec::new(),
+        };
+        let value = evaluator.or()?;
+        evaluator.end()?;
+        Ok(value.is_truthy())
+    }
+}
+
+// Evaluates `#if` conditions, made of numbers, strings, names, `defined(NAME)`, `!`, comparisons,
+// `&&`, `||` and parentheses. Undefined names evaluate to 0.
+struct Evaluator<'a, 'b> {
+    preprocessor: &'b Preprocessor<'a>,
+    tokens: &'b [Token<'a>],
+    position: usize,
+    directive: &'b Token<'b>,
+    // The names of the definitions being evaluated, to catch recursive ones.
+    expanding: Vec<&'a str>,
+}
+
+impl<'a, 'b> Evaluator<'a, 'b> {
+    fn peek(&self) -> Option<TokenKind> {
+        self.tokens.get(self.position).map(|token| token.kind)
+    }
+
+    fn next(&mut self) -> Result<&'b Token<'a>, CompileError> {
+        let tokens = self.tokens;
+        let token = tokens.get(self.position).ok_or_else(|| CompileError {
+            kind: ErrorKind::Preprocessor,
+            message: "Expected expression in condition".to_string(),
+            line: self.directive.line,
+            column: self.directive.column,
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    // Fail if tokens are left after the expression.
+    fn end(&self) -> Result<(), CompileError> {
+        match self.tokens.get(self.position) {
+            Some(token) => Err(CompileError {
+                kind: ErrorKind::Preprocessor,
+                message: format!("Unexpected '{}' in condition", token.source),
+                line: token.line,
+                column: token.column,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn or(&mut self) -> Result<Value<'a>, CompileError> {
+        let mut value = self.and()?;
+        while self.peek() == Some(TokenKind::DoubleBar) {
+            self.position += 1;
+            let right = self.and()?;
+            value = Value::Number((value.is_truthy() || right.is_truthy()).into());
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<Value<'a>, CompileError> {
+        let mut value = self.equality()?;
+        while self.peek() == Some(TokenKind::DoubleAmpersand) {
+            self.position += 1;
+            let right = self.equality()?;
+            value = Value::Number((value.is_truthy() && right.is_truthy()).into());
+        }
+        Ok(value)
+    }
+
+    fn equality(&mut self) -> Result<Value<'a>, CompileError> {
+        let mut value = self.comparison()?;
+        while let Some(kind @ (TokenKind::DoubleEqual | TokenKind::BangEqual)) = self.peek() {
+            self.position += 1;
+            let right = self.comparison()?;
+            let equal = match (value, right) {
+                (Value::Number(left), Value::Number(right)) => left == right,
+                (Value::String(left), Value::String(right)) => left == right,
+                _ => false,
+            };
+            value = Value::Number((equal == (kind == TokenKind::DoubleEqual)).into());
+        }
+        Ok(value)
+    }
+
+    fn comparison(&mut self) -> Result<Value<'a>, CompileError> {
+        let mut value = self.unary()?;
+        while let Some(
+            kind @ (TokenKind::Less
+            | TokenKind::LessEqual
+            | TokenKind::Greater
+            | TokenKind::GreaterEqual),
+        ) = self.peek()
+        {
+            self.position += 1;
+            let right = self.unary()?;
+            let result = match (value, right) {
+                (Value::Number(left), Value::Number(right)) => match kind {
+                    TokenKind::Less => left < right,
+                    TokenKind::LessEqual => left <= right,
+                    TokenKind::Greater => left > right,
+                    _ => left >= right,
+                },
+                _ => false,
+            };
+            value = Value::Number(result.into());
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<Value<'a>, CompileError> {
+        if self.peek() == Some(TokenKind::Bang) {
+            self.position += 1;
+            let value = self.unary()?;
+            return Ok(Value::Number((!value.is_truthy()).into()));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Value<'a>, CompileError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::Number => {
+                let number = token.source.parse().map_err(|_| CompileError {
+                    kind: ErrorKind::Preprocessor,
+                    message: "Invalid number".to_string(),
+                    line: token.line,
+                    column: token.column,
+                })?;
+                Ok(Value::Number(number))
+            }
+            TokenKind::String => Ok(Value::String(&token.source[1..token.source.len() - 1])),
+            TokenKind::True => Ok(Value::Number(1.0)),
+            TokenKind::False => Ok(Value::Number(0.0)),
+            TokenKind::LeftParen => {
+                let value = self.or()?;
+                self.expect(TokenKind::RightParen)?;
+                Ok(value)
+            }
+            TokenKind::Identifier if token.source == "defined" => {
+                self.expect(TokenKind::LeftParen)?;
+                let name = self.next()?.source;
+                self.expect(TokenKind::RightParen)?;
+                Ok(Value::Number(
+                    self.preprocessor.define(name).is_some().into(),
+                ))
+            }
+            TokenKind::Identifier => {
+                let value = match self.preprocessor.define(token.source) {
+                    Some(_) if self.expanding.contains(&token.source) => {
+                        return Err(CompileError {
+                            kind: ErrorKind::Preprocessor,
+                            message: format!("Recursive definition of '{}'", token.source),
+                            line: token.line,
+                            column: token.column,
+                        });
+                    }
+                    Some(define) => {
+                        // Evaluate the definition in place.
+                        let mut expanding = self.expanding.clone();
+                        expanding.push(token.source);
+                        let mut evaluator = Evaluator {
+                            preprocessor: self.preprocessor,
+                            tokens: &define.tokens,
+                            position: 0,
+                            directive: self.directive,
+                            expanding,
+                        };
+                        if define.tokens.is_empty() {
+                            Value::Number(1.0)
+                        } else {
+                            let value = evaluator.or()?;
+                            evaluator.end()?;
+                            value
+                        }
+                    }
+                    None => Value::Number(0.0),
+                };
+                Ok(value)
+            }
+            _ => Err(CompileError {
+                kind: ErrorKind::Preprocessor,
+                message: format!("Unexpected '{}' in condition", token.source),
+                line: token.line,
+                column: token.column,
+            }),
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<(), CompileError> {
+        let token = self.next()?;
+        if token.kind != kind {
+            return Err(CompileError {
+                kind: ErrorKind::Preprocessor,
+                message: format!("Unexpected '{}' in condition", token.source),
+                line: token.line,
+                column: token.column,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(source: &str) -> Vec<&str> {
+        let mut preprocessor = Preprocessor::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            let token = preprocessor.read_token().unwrap();
+            if token.kind == TokenKind::Eof {
+                return tokens;
+            }
+            tokens.push(token.source);
+        }
+    }
+
+    #[test]
+    fn recursive_definition() {
+        assert_eq!(
+            expand("#define A 1 + A\nx = A;"),
+            ["x", "=", "1", "+", "A", ";"]
+        );
+    }
+
+    #[test]
+    fn mutually_recursive_definitions() {
+        assert_eq!(expand("#define A B\n#define B A\nA B"), ["A", "B"]);
+    }
+
+    #[test]
+    fn nested_definitions() {
+        assert_eq!(expand("#define A B B\n#define B 1\nA"), ["1", "1"]);
+    }
+}
@@ -0,0 +1,226 @@
+// Rendering of diagnostics as text for people, showing the code that each one is about in a frame
+// of source lines, in the style of rustc.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use std::fmt::Write;
+
+// The source lines that a multi-line span shows at most, eliding those in the middle.
+const MAX_SPAN_LINES: usize = 6;
+
+// The ranges of characters that terminals show two columns wide, such as CJK ideographs.
+const WIDE: &[(char, char)] = &[
+    ('\u{1100}', '\u{115F}'),
+    ('\u{2E80}', '\u{303E}'),
+    ('\u{3041}', '\u{33FF}'),
+    ('\u{3400}', '\u{4DBF}'),
+    ('\u{4E00}', '\u{9FFF}'),
+    ('\u{A000}', '\u{A4CF}'),
+    ('\u{AC00}', '\u{D7A3}'),
+    ('\u{F900}', '\u{FAFF}'),
+    ('\u{FE30}', '\u{FE4F}'),
+    ('\u{FF00}', '\u{FF60}'),
+    ('\u{FFE0}', '\u{FFE6}'),
+    ('\u{1F300}', '\u{1F64F}'),
+    ('\u{1F900}', '\u{1F9FF}'),
+    ('\u{20000}', '\u{3FFFD}'),
+];
+
+// The ranges of characters that terminals show on top of the previous one, taking no columns.
+const ZERO_WIDTH: &[(char, char)] = &[
+    ('\u{0300}', '\u{036F}'),
+    ('\u{200B}', '\u{200F}'),
+    ('\u{20D0}', '\u{20FF}'),
+    ('\u{FE00}', '\u{FE0F}'),
+    ('\u{FE20}', '\u{FE2F}'),
+];
+
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    // Color the output with ANSI escape codes, as for a terminal.
+    pub color: bool,
+    // The columns between tab stops, to which source lines expand their tabs.
+    pub tab_width: usize,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            color: false,
+            tab_width: 4,
+        }
+    }
+}
+
+impl Renderer {
+    // Render `diagnostic` about `source`, which `filename` names, along with its notes.
+    pub fn render(&self, filename: &str, source: &str, diagnostic: &Diagnostic) -> String {
+        let mut output = String::new();
+        let mut lines = vec![diagnostic.line, diagnostic.end.map_or(0, |(line, _)| line)];
+        lines.extend(diagnostic.notes.iter().map(|note| note.line));
+        let width = lines.into_iter().max().unwrap_or(0).to_string().len();
+
+        self.header(&mut output, diagnostic);
+        self.frame(&mut output, filename, source, diagnostic, width);
+        for note in &diagnostic.notes {
+            if note.line == 0 {
+                let label = self.paint("1", "note");
+                writeln!(
+                    output,
+                    "{} = {}: {}",
+                    " ".repeat(width),
+                    label,
+                    note.message
+                )
+                .unwrap();
+            } else {
+                self.header(&mut output, note);
+                self.frame(&mut output, filename, source, note, width);
+            }
+        }
+        output
+    }
+
+    fn header(&self, output: &mut String, diagnostic: &Diagnostic) {
+        let label = diagnostic.severity.name();
+        let code = diagnostic
+            .code()
+            .map(|code| format!("[{}]", code))
+            .unwrap_or_default();
+        // Name the option that controls a warning.
+        let option = match (diagnostic.severity, diagnostic.lint) {
+            (Severity::Error, Some(lint)) => format!(" [-Werror={}]", lint.name()),
+            (_, Some(lint)) => format!(" [-W{}]", lint.name()),
+            (_, None) => String::new(),
+        };
+        let label = self.paint(style(diagnostic.severity), &format!("{}{}", label, code));
+        let message = self.paint("1", &format!("{}{}", diagnostic.message, option));
+        writeln!(output, "{}: {}", label, message).unwrap();
+    }
+
+    // The location of the diagnostic and the source lines that it is about.
+    fn frame(
+        &self,
+        output: &mut String,
+        filename: &str,
+        source: &str,
+        diagnostic: &Diagnostic,
+        width: usize,
+    ) {
+        let pad = " ".repeat(width);
+        let arrow = self.paint("1;34", "-->");
+        if diagnostic.line == 0 {
+            writeln!(output, "{}{} {}", pad, arrow, filename).unwrap();
+            return;
+        }
+        let (line, column) = (diagnostic.line, diagnostic.column);
+        writeln!(output, "{}{} {}:{}:{}", pad, arrow, filename, line, column).unwrap();
+        let gutter = self.paint("1;34", "|");
+        writeln!(output, "{} {}", pad, gutter).unwrap();
+
+        let style = style(diagnostic.severity);
+        let source_line = |line: usize| source.lines().nth(line - 1).unwrap_or_default();
+        // Source lines expand their tabs, and marks count the columns that characters take.
+        let text = |line: usize| self.expand_tabs(source_line(line));
+        let columns = |line: usize, column: usize| self.width(source_line(line), column);
+        let number = |line: usize| self.paint("1;34", &format!("{:>width$}", line));
+        match diagnostic.end {
+            Some((end_line, end_column)) if end_line > line => {
+                // Connect the start and the end of the span by a line on the left.
+                let bar = self.paint(style, "|");
+                writeln!(output, "{} {}   {}", number(line), gutter, text(line)).unwrap();
+                let start = "_".repeat(columns(line, column) + 1);
+                let start = self.paint(style, &format!("{}^", start));
+                writeln!(output, "{} {}  {}", pad, gutter, start).unwrap();
+                let is_long = end_line - line + 1 > MAX_SPAN_LINES;
+                for middle in line + 1..end_line {
+                    // Long spans only show the lines next to their start and end.
+                    if is_long && middle > line + 1 && middle + 1 < end_line {
+                        if middle == line + 2 {
+                            writeln!(output, "{}", self.paint("1;34", "...")).unwrap();
+                        }
+                        continue;
+                    }
+                    let text = text(middle);
+                    writeln!(output, "{} {} {} {}", number(middle), gutter, bar, text).unwrap();
+                }
+                let text = text(end_line);
+                writeln!(output, "{} {} {} {}", number(end_line), gutter, bar, text).unwrap();
+                let end = format!("{}^", "_".repeat(columns(end_line, end_column)));
+                let end = self.paint(style, &format!("|{}", end));
+                writeln!(output, "{} {} {}", pad, gutter, end).unwrap();
+            }
+            end => {
+                let start = columns(line, column);
+                let length = match end {
+                    Some((_, end_column)) => columns(line, end_column).saturating_sub(start),
+                    None => columns(line, column + 1) - start,
+                };
+                writeln!(output, "{} {} {}", number(line), gutter, text(line)).unwrap();
+                let carets = self.paint(style, &"^".repeat(length.max(1)));
+                let indent = " ".repeat(start);
+                writeln!(output, "{} {} {}{}", pad, gutter, indent, carets).unwrap();
+            }
+        }
+    }
+
+    // `line` with its tabs replaced by spaces up to the next tab stop.
+    fn expand_tabs(&self, line: &str) -> String {
+        let mut expanded = String::with_capacity(line.len());
+        let mut width = 0;
+        for c in line.chars() {
+            if c == '\t' {
+                let spaces = self.tab_width - width % self.tab_width.max(1);
+                expanded.push_str(&" ".repeat(spaces));
+                width += spaces;
+            } else {
+                expanded.push(c);
+                width += char_width(c);
+            }
+        }
+        expanded
+    }
+
+    // The columns that the characters of `line` before the 1-based `column` take on a terminal.
+    fn width(&self, line: &str, column: usize) -> usize {
+        line.chars()
+            .take(column.saturating_sub(1))
+            .fold(0, |width, c| match c {
+                '\t' => width + self.tab_width - width % self.tab_width.max(1),
+                c => width + char_width(c),
+            })
+    }
+
+    // `text` in the style of the ANSI escape code `style`, if coloring.
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", style, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// The columns that a character other than a tab takes on a terminal.
+fn char_width(c: char) -> usize {
+    let is_in = |ranges: &[(char, char)]| {
+        ranges
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&c))
+    };
+    if c.is_control() || is_in(ZERO_WIDTH) {
+        0
+    } else if is_in(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+// The ANSI escape code of the labels and marks of diagnostics of `severity`.
+fn style(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "1;31",
+        Severity::Warning => "1;33",
+        Severity::Note => "1;32",
+    }
+}
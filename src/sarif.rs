@@ -0,0 +1,95 @@
+// Diagnostics as a log in the Static Analysis Results Interchange Format (SARIF) 2.1.0, which
+// code scanning dashboards import.
+
+use crate::diagnostic::{Diagnostic, Lint};
+use crate::json;
+use crate::scanner::ErrorKind;
+
+// The diagnostics about each file, named by its path, as a SARIF log of a single run.
+pub fn sarif_log(files: &[(&str, &[Diagnostic])]) -> String {
+    let rules: Vec<_> = ErrorKind::ALL
+        .iter()
+        .map(|kind| rule(kind.code(), &format!("{:?}", kind)))
+        .chain(Lint::ALL.iter().map(|lint| rule(lint.code(), lint.name())))
+        .collect();
+    let results: Vec<_> = files
+        .iter()
+        .flat_map(|(filename, diagnostics)| {
+            diagnostics
+                .iter()
+                .map(move |diagnostic| result(filename, diagnostic))
+        })
+        .collect();
+    format!(
+        r#"{{"version":"2.1.0","$schema":"https://json.schemastore.org/sarif-2.1.0.json","runs":[{{"tool":{{"driver":{{"name":"asc","version":{},"rules":[{}]}}}},"results":[{}]}}]}}"#,
+        json::string(env!("CARGO_PKG_VERSION")),
+        rules.join(","),
+        results.join(",")
+    ) + "\n"
+}
+
+fn rule(id: &str, name: &str) -> String {
+    format!(
+        r#"{{"id":{},"name":{}}}"#,
+        json::string(id),
+        json::string(name)
+    )
+}
+
+fn result(filename: &str, diagnostic: &Diagnostic) -> String {
+    let mut fields = Vec::new();
+    if let Some(code) = diagnostic.code() {
+        fields.push(format!(r#""ruleId":{}"#, json::string(code)));
+    }
+    fields.push(format!(
+        r#""level":{}"#,
+        json::string(diagnostic.severity.name())
+    ));
+    // Notes without a position have no location to relate to, so they go in the message.
+    let mut message = diagnostic.message.clone();
+    for note in diagnostic.notes.iter().filter(|note| note.line == 0) {
+        message.push_str("\nnote: ");
+        message.push_str(&note.message);
+    }
+    fields.push(format!(
+        r#""message":{{"text":{}}}"#,
+        json::string(&message)
+    ));
+    if let Some(location) = location(filename, diagnostic, None) {
+        fields.push(format!(r#""locations":[{}]"#, location));
+    }
+    let related: Vec<_> = diagnostic
+        .notes
+        .iter()
+        .filter_map(|note| location(filename, note, Some(&note.message)))
+        .collect();
+    if !related.is_empty() {
+        fields.push(format!(r#""relatedLocations":[{}]"#, related.join(",")));
+    }
+    format!("{{{}}}", fields.join(","))
+}
+
+// Where in `filename` the diagnostic is, unless it is about no particular file or position, along
+// with a message about the location, such as that of a note.
+fn location(filename: &str, diagnostic: &Diagnostic, message: Option<&str>) -> Option<String> {
+    if filename.is_empty() || diagnostic.line == 0 {
+        return None;
+    }
+    let (end_line, end_column) = diagnostic
+        .end
+        .unwrap_or((diagnostic.line, diagnostic.column + 1));
+    // Artifact locations are URIs, which only separate directories by slashes.
+    let uri = filename.replace('\\', "/");
+    let message = message
+        .map(|message| format!(r#","message":{{"text":{}}}"#, json::string(message)))
+        .unwrap_or_default();
+    Some(format!(
+        r#"{{"physicalLocation":{{"artifactLocation":{{"uri":{}}},"region":{{"startLine":{},"startColumn":{},"endLine":{},"endColumn":{}}}}}{}}}"#,
+        json::string(&uri),
+        diagnostic.line,
+        diagnostic.column,
+        end_line,
+        end_column,
+        message
+    ))
+}
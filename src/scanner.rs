@@ -12,10 +12,13 @@ pub enum TokenKind {
     RightSquareBrace,   // ]
     Ampersand,          // &
     AmpersandEqual,     // &=
+    DoubleAmpersand,    // &&
     Bang,               // !
     Bar,                // |
     BarEqual,           // |=
+    DoubleBar,          // ||
     BangEqual,          // !=
+    BangDoubleEqual,    // !==
     Caret,              // ^
     CaretEqual,         // ^=
     Comma,              // ,
@@ -42,6 +45,7 @@ pub enum TokenKind {
     PlusEqual,          // +=
     DoublePlus,         // ++
     Colon,              // :
+    DoubleColon,        // ::
     Semicolon,          // ;
     Slash,              // /
     SlashEqual,         // /=
@@ -49,6 +53,9 @@ pub enum TokenKind {
     StarEqual,          // *=
     Tilda,              // ~
 
+    // Directives.
+    Directive, // #name
+
     // Literals.
     False,
     Identifier,
@@ -59,18 +66,30 @@ pub enum TokenKind {
     Undefined,
 
     // Keywords.
+    Add,
+    And,
     Break,
     Catch,
     Continue,
     Delete,
+    Do,
     Else,
+    Eq,
     Finally,
     For,
     Function,
+    Ge,
+    Gt,
     If,
     In,
     InstanceOf,
+    Le,
+    Lt,
+    Ne,
     New,
+    Not,
+    Or,
+    TellTarget,
     Throw,
     Trace,
     Try,
@@ -82,7 +101,7 @@ pub enum TokenKind {
     Eof,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Token<'a> {
     pub kind: TokenKind,
     pub source: &'a str,
@@ -99,12 +118,15 @@ impl Token<'_> {
     };
 }
 
+#[derive(Clone)]
 pub struct Scanner<'a> {
     source: &'a str,
     chars: Peekable<CharIndices<'a>>,
     offset: usize,
     line: usize,
     column: usize,
+    // The line and text of each line comment since last taken, if keeping them.
+    comments: Option<Vec<(usize, usize, &'a str)>>,
 }
 
 impl<'a> Scanner<'a> {
@@ -115,9 +137,27 @@ impl<'a> Scanner<'a> {
             offset: 0,
             line: 1,
             column: 1,
+            comments: None,
+        }
+    }
+
+    // A scanner that keeps the line comments before each token, for `take_comments`.
+    pub fn with_comments(source: &'a str) -> Self {
+        Self {
+            comments: Some(Vec::new()),
+            ..Self::new(source)
         }
     }
 
+    // The line comments read since last taken, without their `//`, along with the positions of
+    // their `//`.
+    pub fn take_comments(&mut self) -> Vec<(usize, usize, &'a str)> {
+        self.comments
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
     fn read_char(&mut self) -> Option<char> {
         // This will be kept on EOF.
         self.offset = self.source.len();
@@ -142,32 +182,39 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    fn read_radix_digits(
+        &mut self,
+        is_digit: impl Fn(char) -> bool,
+        radix_name: &str,
+    ) -> Result<TokenKind, CompileError> {
+        // Skip radix prefix.
+        self.read_char();
+
+        if !matches!(self.chars.peek(), Some((_, c)) if is_digit(*c)) {
+            return Err(CompileError {
+                kind: ErrorKind::InvalidToken,
+                message: format!("Expected {} digit", radix_name),
+                line: self.line,
+                column: self.column,
+            });
+        }
+        while matches!(self.chars.peek(), Some((_, c)) if is_digit(*c)) {
+            self.read_char();
+        }
+        Ok(TokenKind::Number)
+    }
+
     fn read_number(&mut self, first_digit: char) -> Result<TokenKind, CompileError> {
         if first_digit == '0' {
             match self.chars.peek() {
                 Some((_, 'b' | 'B')) => {
-                    // Binary number.
-                    self.read_char();
-                    while let Some((_, '0'..='1')) = self.chars.peek() {
-                        self.read_char();
-                    }
-                    return Ok(TokenKind::Number);
+                    return self.read_radix_digits(|c| matches!(c, '0'..='1'), "binary");
                 }
                 Some((_, 'o' | 'O')) => {
-                    // Octal number.
-                    self.read_char();
-                    while let Some((_, '0'..='7')) = self.chars.peek() {
-                        self.read_char();
-                    }
-                    return Ok(TokenKind::Number);
+                    return self.read_radix_digits(|c| matches!(c, '0'..='7'), "octal");
                 }
                 Some((_, 'x' | 'X')) => {
-                    // Hexadecimal number.
-                    self.read_char();
-                    while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_hexdigit()) {
-                        self.read_char();
-                    }
-                    return Ok(TokenKind::Number);
+                    return self.read_radix_digits(|c| c.is_ascii_hexdigit(), "hexadecimal");
                 }
                 _ => {}
             }
@@ -178,11 +225,13 @@ impl<'a> Scanner<'a> {
             self.read_char();
         }
 
-        // Decimal dot.
-        if let Some((_, '.')) = self.chars.peek() {
-            self.read_char();
-            while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_digit()) {
+        // Decimal dot, unless the number started with it.
+        if first_digit != '.' {
+            if let Some((_, '.')) = self.chars.peek() {
                 self.read_char();
+                while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_digit()) {
+                    self.read_char();
+                }
             }
         }
 
@@ -194,6 +243,7 @@ impl<'a> Scanner<'a> {
             }
             if !matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_digit()) {
                 return Err(CompileError {
+                    kind: ErrorKind::InvalidToken,
                     message: "Expected exponent".to_string(),
                     line: self.line,
                     column: self.column,
@@ -217,6 +267,7 @@ impl<'a> Scanner<'a> {
                 Some(_) => {}
                 None => {
                     return Err(CompileError {
+                        kind: ErrorKind::InvalidToken,
                         message: "Unclosed string".to_string(),
                         line,
                         column,
@@ -261,12 +312,22 @@ impl<'a> Scanner<'a> {
                     self.read_char();
                     TokenKind::AmpersandEqual
                 }
+                Some((_, '&')) => {
+                    self.read_char();
+                    TokenKind::DoubleAmpersand
+                }
                 _ => TokenKind::Ampersand,
             },
             Some('!') => match self.chars.peek() {
                 Some((_, '=')) => {
                     self.read_char();
-                    TokenKind::BangEqual
+                    match self.chars.peek() {
+                        Some((_, '=')) => {
+                            self.read_char();
+                            TokenKind::BangDoubleEqual
+                        }
+                        _ => TokenKind::BangEqual,
+                    }
                 }
                 _ => TokenKind::Bang,
             },
@@ -275,6 +336,10 @@ impl<'a> Scanner<'a> {
                     self.read_char();
                     TokenKind::BarEqual
                 }
+                Some((_, '|')) => {
+                    self.read_char();
+                    TokenKind::DoubleBar
+                }
                 _ => TokenKind::Bar,
             },
             Some('^') => match self.chars.peek() {
@@ -285,7 +350,10 @@ impl<'a> Scanner<'a> {
                 _ => TokenKind::Caret,
             },
             Some(',') => TokenKind::Comma,
-            Some('.') => TokenKind::Dot,
+            Some('.') => match self.chars.peek() {
+                Some((_, c)) if c.is_ascii_digit() => self.read_number('.')?,
+                _ => TokenKind::Dot,
+            },
             Some('=') => match self.chars.peek() {
                 Some((_, '=')) => {
                     self.read_char();
@@ -372,7 +440,13 @@ impl<'a> Scanner<'a> {
                 }
                 _ => TokenKind::Plus,
             },
-            Some(':') => TokenKind::Colon,
+            Some(':') => match self.chars.peek() {
+                Some((_, ':')) => {
+                    self.read_char();
+                    TokenKind::DoubleColon
+                }
+                _ => TokenKind::Colon,
+            },
             Some(';') => TokenKind::Semicolon,
             Some('/') => match self.chars.peek() {
                 Some((_, '/')) => {
@@ -382,6 +456,10 @@ impl<'a> Scanner<'a> {
                             _ => {}
                         }
                     }
+                    if let Some(comments) = &mut self.comments {
+                        let text = &self.source[start + 2..self.offset];
+                        comments.push((line, column, text.trim_end_matches('\r')));
+                    }
                     return self.read_token();
                 }
                 Some((_, '*')) => {
@@ -411,23 +489,55 @@ impl<'a> Scanner<'a> {
                 _ => TokenKind::Star,
             },
             Some('~') => TokenKind::Tilda,
+            Some('#') => {
+                self.read_identifier();
+                TokenKind::Directive
+            }
+            // Registers, such as `@r1`.
+            Some('@') => {
+                let name = self.read_identifier();
+                let is_register = name
+                    .strip_prefix("@r")
+                    .is_some_and(|index| index.parse::<u8>().is_ok());
+                if !is_register {
+                    return Err(CompileError {
+                        kind: ErrorKind::InvalidToken,
+                        message: format!("Invalid register '{}'", name),
+                        line,
+                        column,
+                    });
+                }
+                TokenKind::Identifier
+            }
             Some(digit) if digit.is_ascii_digit() => self.read_number(digit)?,
             Some(quote @ ('"' | '\'')) => self.read_string(quote)?,
             Some('A'..='Z' | 'a'..='z' | '_' | '$') => match self.read_identifier() {
+                "add" => TokenKind::Add,
+                "and" => TokenKind::And,
                 "break" => TokenKind::Break,
                 "catch" => TokenKind::Catch,
                 "continue" => TokenKind::Continue,
                 "delete" => TokenKind::Delete,
+                "do" => TokenKind::Do,
                 "else" => TokenKind::Else,
+                "eq" => TokenKind::Eq,
                 "false" => TokenKind::False,
                 "finally" => TokenKind::Finally,
                 "for" => TokenKind::For,
                 "function" => TokenKind::Function,
+                "ge" => TokenKind::Ge,
+                "gt" => TokenKind::Gt,
                 "if" => TokenKind::If,
                 "in" => TokenKind::In,
                 "instanceof" => TokenKind::InstanceOf,
+                "le" => TokenKind::Le,
+                "lt" => TokenKind::Lt,
+                "ne" => TokenKind::Ne,
                 "new" => TokenKind::New,
+                "not" => TokenKind::Not,
                 "null" => TokenKind::Null,
+                "or" => TokenKind::Or,
+                "tellTarget" => TokenKind::TellTarget,
                 "throw" => TokenKind::Throw,
                 "trace" => TokenKind::Trace,
                 "true" => TokenKind::True,
@@ -440,6 +550,7 @@ impl<'a> Scanner<'a> {
             },
             Some(c) => {
                 return Err(CompileError {
+                    kind: ErrorKind::InvalidToken,
                     message: format!("Unknown character '{}'", c),
                     line,
                     column,
@@ -448,6 +559,7 @@ impl<'a> Scanner<'a> {
         };
         let end = (self.offset + 1).min(self.source.len());
         let source = &self.source[start..end];
+        log::trace!("{}:{}: Token {:?} {:?}", line, column, kind, source);
         Ok(Token {
             kind,
             source,
@@ -457,9 +569,140 @@ impl<'a> Scanner<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CompileError {
+    pub kind: ErrorKind,
     pub message: String,
     pub line: usize,
     pub column: usize,
 }
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Errors about the whole source, such as failing to read it, have no position.
+        if self.line == 0 {
+            write!(f, "error[{}]: {}", self.kind.code(), self.message)
+        } else {
+            write!(
+                f,
+                "{}:{}: error[{}]: {}",
+                self.line,
+                self.column,
+                self.kind.code(),
+                self.message
+            )
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<std::io::Error> for CompileError {
+    fn from(error: std::io::Error) -> Self {
+        Self {
+            kind: ErrorKind::Io,
+            message: error.to_string(),
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+impl From<swf::error::Error> for CompileError {
+    fn from(error: swf::error::Error) -> Self {
+        Self {
+            kind: ErrorKind::Io,
+            message: format!("Cannot write SWF file: {}", error),
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+// The category of an error, which has a stable code for tools to match on.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ErrorKind {
+    // A character or literal that is not a valid token.
+    InvalidToken,
+    // A token where the grammar does not allow it.
+    Syntax,
+    // A malformed or unbalanced preprocessor directive.
+    Preprocessor,
+    // Statements or expressions nested deeper than the maximum depth.
+    TooDeep,
+    // An expression that cannot be assigned, deleted, called or constructed.
+    InvalidTarget,
+    // A literal or argument that its use does not allow, such as a frame number out of range.
+    InvalidValue,
+    // A reference to a label, property, key or constant that does not exist.
+    Unknown,
+    // A second definition of a label, constructor or accessor.
+    Duplicate,
+    // A call with the wrong number of arguments.
+    Arity,
+    // An assignment to a constant.
+    ConstAssignment,
+    // An access to a private member from outside of its class.
+    PrivateAccess,
+    // A statement or expression where it cannot be, such as `break` outside of loops.
+    Misplaced,
+    // Code or data larger than SWF files can hold.
+    TooLarge,
+    // An imported class that cannot be found.
+    Import,
+    // A source that cannot be read.
+    Io,
+    // A bug in the compiler.
+    Internal,
+    // A feature that the targeted SWF version lacks.
+    Unsupported,
+}
+
+impl ErrorKind {
+    pub const ALL: &'static [Self] = &[
+        Self::InvalidToken,
+        Self::Syntax,
+        Self::Preprocessor,
+        Self::TooDeep,
+        Self::InvalidTarget,
+        Self::InvalidValue,
+        Self::Unknown,
+        Self::Duplicate,
+        Self::Arity,
+        Self::ConstAssignment,
+        Self::PrivateAccess,
+        Self::Misplaced,
+        Self::TooLarge,
+        Self::Import,
+        Self::Io,
+        Self::Internal,
+        Self::Unsupported,
+    ];
+
+    // The code of the kind, which never changes once released.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::InvalidToken => "E0001",
+            Self::Syntax => "E0002",
+            Self::Preprocessor => "E0003",
+            Self::TooDeep => "E0004",
+            Self::InvalidTarget => "E0005",
+            Self::InvalidValue => "E0006",
+            Self::Unknown => "E0007",
+            Self::Duplicate => "E0008",
+            Self::Arity => "E0009",
+            Self::ConstAssignment => "E0010",
+            Self::PrivateAccess => "E0011",
+            Self::Misplaced => "E0012",
+            Self::TooLarge => "E0013",
+            Self::Import => "E0014",
+            Self::Io => "E0015",
+            Self::Internal => "E0016",
+            Self::Unsupported => "E0017",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.code() == code)
+    }
+}
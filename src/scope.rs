@@ -0,0 +1,360 @@
+// Analysis of the variables that a function body uses, to decide which of them can be kept in
+// registers rather than looked up by name.
+
+use crate::ast::{ClassMemberKind, Expression, Function, Statement};
+use crate::scanner::{Token, TokenKind};
+use std::collections::HashSet;
+
+#[derive(Default)]
+pub struct Scope<'a> {
+    // Names used directly by the body, outside of nested functions.
+    names: HashSet<&'a str>,
+    // Local variables that the body declares with `var`, in order.
+    vars: Vec<&'a str>,
+    // The first declaration of each local variable.
+    declarations: Vec<Token<'a>>,
+    // The names of every parameter, variable and function that the body declares, in order and
+    // including those declared again.
+    bindings: Vec<Token<'a>>,
+    // Names whose values the body reads directly, rather than only assigns.
+    reads: HashSet<&'a str>,
+    // Names that must stay variables: those that nested functions may capture, that `delete`
+    // removes, or that function declarations define by name.
+    captured: HashSet<&'a str>,
+    // Whether variables may be accessed by computed names, such as through `eval`.
+    dynamic: bool,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(function: &Function<'a>) -> Self {
+        let mut scope = Self::default();
+        scope.bindings.extend(function.parameters.iter().cloned());
+        scope.walk(&function.body);
+        scope
+    }
+
+    // The scope of statements outside of functions, such as those of the timeline.
+    pub fn of_statements<'s>(statements: impl IntoIterator<Item = &'s Statement<'a>>) -> Self
+    where
+        'a: 's,
+    {
+        let mut scope = Self::default();
+        scope.walk(statements);
+        scope
+    }
+
+    fn walk<'s>(&mut self, statements: impl IntoIterator<Item = &'s Statement<'a>>)
+    where
+        'a: 's,
+    {
+        let mut walker = Walker {
+            scope: self,
+            nested: false,
+        };
+        for statement in statements {
+            walker.statement(statement);
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.names.iter().copied()
+    }
+
+    pub fn vars(&self) -> &[&'a str] {
+        &self.vars
+    }
+
+    // Whether the body uses `name` directly, outside of nested functions.
+    pub fn uses(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    // Whether the body may access variables by computed names.
+    pub fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    // Whether the value of `name` may be read, by the body or by nested functions.
+    pub fn is_read(&self, name: &str) -> bool {
+        self.dynamic || self.reads.contains(name) || self.captured.contains(name)
+    }
+
+    // The declarations of the local variables whose values are never read.
+    pub fn unread_vars(&self) -> impl Iterator<Item = &Token<'a>> {
+        self.declarations
+            .iter()
+            .filter(|declaration| !self.is_read(declaration.source))
+    }
+
+    // The first declaration of each name that the body declares, parameters first.
+    pub fn bindings(&self) -> impl Iterator<Item = &Token<'a>> {
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter_map(|(index, binding)| {
+                let is_first = self.bindings[..index]
+                    .iter()
+                    .all(|other| other.source != binding.source);
+                is_first.then_some(binding)
+            })
+    }
+
+    // Each declaration of a name that the body already declares, along with the first one.
+    pub fn redeclarations(&self) -> impl Iterator<Item = (&Token<'a>, &Token<'a>)> {
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter_map(|(index, binding)| {
+                self.bindings[..index]
+                    .iter()
+                    .find(|other| other.source == binding.source)
+                    .map(|first| (first, binding))
+            })
+    }
+
+    // Whether `name` can be kept in a register throughout the body.
+    pub fn is_register_candidate(&self, name: &str) -> bool {
+        !self.dynamic && !self.captured.contains(name)
+    }
+}
+
+struct Walker<'s, 'a> {
+    scope: &'s mut Scope<'a>,
+    // Whether the walk is inside a nested function.
+    nested: bool,
+}
+
+impl<'a> Walker<'_, 'a> {
+    fn name(&mut self, name: &'a str) {
+        // Every function has its own special variables, so nested functions do not capture them.
+        let is_special = matches!(
+            name,
+            "this" | "arguments" | "super" | "_root" | "_parent" | "_global"
+        );
+        if self.nested {
+            if !is_special {
+                self.scope.captured.insert(name);
+            }
+        } else {
+            self.scope.names.insert(name);
+        }
+    }
+
+    fn read(&mut self, name: &'a str) {
+        self.name(name);
+        if !self.nested {
+            self.scope.reads.insert(name);
+        }
+    }
+
+    fn var(&mut self, token: &Token<'a>) {
+        let name = token.source;
+        self.name(name);
+        if !self.nested {
+            self.scope.bindings.push(token.clone());
+        }
+        if !self.nested && !self.scope.vars.contains(&name) {
+            self.scope.vars.push(name);
+            self.scope.declarations.push(token.clone());
+        }
+    }
+
+    fn nested_function(&mut self, function: &Function<'a>) {
+        let nested = std::mem::replace(&mut self.nested, true);
+        for parameter in &function.parameters {
+            self.name(parameter.source);
+        }
+        self.statements(&function.body);
+        self.nested = nested;
+    }
+
+    fn statements(&mut self, statements: &[Statement<'a>]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement<'a>) {
+        match statement {
+            Statement::Empty
+            | Statement::Break { .. }
+            | Statement::Continue { .. }
+            | Statement::Intrinsic(_)
+            | Statement::Import(_) => {}
+            Statement::Expression(expression) => self.expression(expression),
+            Statement::Block(statements) => self.statements(statements),
+            Statement::Var { name, value } => {
+                self.var(name);
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::Const { value, .. } => self.expression(value),
+            Statement::Function(function) => {
+                if let Some(name) = &function.name {
+                    self.scope.captured.insert(name.source);
+                    if !self.nested {
+                        self.scope.bindings.push(name.clone());
+                    }
+                }
+                self.nested_function(function);
+            }
+            Statement::If {
+                condition,
+                body,
+                else_body,
+                ..
+            } => {
+                self.expression(condition);
+                self.statement(body);
+                if let Some(else_body) = else_body {
+                    self.statement(else_body);
+                }
+            }
+            Statement::ForIn {
+                is_declaration,
+                variable,
+                object,
+                body,
+            } => {
+                if *is_declaration {
+                    self.var(variable);
+                } else {
+                    self.name(variable.source);
+                }
+                self.expression(object);
+                self.statement(body);
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            Statement::DoWhile {
+                body, condition, ..
+            } => {
+                self.statement(body);
+                self.expression(condition);
+            }
+            Statement::Labeled { body, .. } => self.statement(body),
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                self.statements(body);
+                if let Some(catch) = catch {
+                    self.name(catch.variable.source);
+                    self.statements(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.statements(finally);
+                }
+            }
+            Statement::Throw { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::TellTarget { target, body, .. } => {
+                self.expression(target);
+                self.statement(body);
+            }
+            Statement::Trace { arguments, .. } => {
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Statement::Class(class) => {
+                let nested = std::mem::replace(&mut self.nested, true);
+                for member in &class.members {
+                    match &member.kind {
+                        ClassMemberKind::Var { value, .. } => {
+                            if let Some(value) = value {
+                                self.expression(value);
+                            }
+                        }
+                        ClassMemberKind::Method { function, .. } => self.nested_function(function),
+                    }
+                }
+                self.nested = nested;
+            }
+            Statement::Conditional { body, .. } => self.statement(body),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression<'a>) {
+        match expression {
+            Expression::Literal(_) | Expression::Constant { .. } => {}
+            Expression::Identifier(token) => self.read(token.source),
+            Expression::Grouping { expression, .. } => self.expression(expression),
+            Expression::Array { elements, .. } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for (_, value) in properties {
+                    self.expression(value);
+                }
+            }
+            Expression::Function(function) => self.nested_function(function),
+            Expression::Member { object, .. } => self.expression(object),
+            Expression::Index { object, index } => {
+                self.expression(object);
+                self.expression(index);
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                // `eval(name)` and `set(name, value)` access variables by computed names.
+                if let Expression::Identifier(token) = &**callee {
+                    if matches!(token.source, "eval" | "set") {
+                        self.scope.dynamic = true;
+                    }
+                }
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::New {
+                callee, arguments, ..
+            } => {
+                self.expression(callee);
+                for argument in arguments.iter().flatten() {
+                    self.expression(argument);
+                }
+            }
+            Expression::Delete { target, .. } => {
+                if let Expression::Identifier(token) = &**target {
+                    self.scope.captured.insert(token.source);
+                }
+                self.expression(target);
+            }
+            Expression::Unary { operand, .. } => self.expression(operand),
+            Expression::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Assign {
+                operator,
+                target,
+                value,
+            } => {
+                // Plain assignments to a variable do not read it, unlike compound ones.
+                match &**target {
+                    Expression::Identifier(token) if operator.kind == TokenKind::Equal => {
+                        self.name(token.source)
+                    }
+                    target => self.expression(target),
+                }
+                self.expression(value);
+            }
+            Expression::Prefix { name, .. } => self.read(name.source),
+            Expression::Postfix { target, .. } => self.expression(target),
+        }
+    }
+}
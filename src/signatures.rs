@@ -0,0 +1,85 @@
+// The number of arguments that global functions and static methods of the Flash Player take, to
+// catch calls that pass too few or too many of them. Builtins compiled to actions check their own.
+
+// Functions and methods by path, along with their least and most numbers of arguments.
+const SIGNATURES: &[(&str, usize, usize)] = &[
+    ("ASSetPropFlags", 3, 4),
+    ("Boolean", 0, 1),
+    ("Date.UTC", 2, 7),
+    ("Key.addListener", 1, 1),
+    ("Key.getAscii", 0, 0),
+    ("Key.getCode", 0, 0),
+    ("Key.isDown", 1, 1),
+    ("Key.isToggled", 1, 1),
+    ("Key.removeListener", 1, 1),
+    ("Math.abs", 1, 1),
+    ("Math.acos", 1, 1),
+    ("Math.asin", 1, 1),
+    ("Math.atan", 1, 1),
+    ("Math.atan2", 2, 2),
+    ("Math.ceil", 1, 1),
+    ("Math.cos", 1, 1),
+    ("Math.exp", 1, 1),
+    ("Math.floor", 1, 1),
+    ("Math.log", 1, 1),
+    ("Math.max", 2, 2),
+    ("Math.min", 2, 2),
+    ("Math.pow", 2, 2),
+    ("Math.random", 0, 0),
+    ("Math.round", 1, 1),
+    ("Math.sin", 1, 1),
+    ("Math.sqrt", 1, 1),
+    ("Math.tan", 1, 1),
+    ("Mouse.addListener", 1, 1),
+    ("Mouse.hide", 0, 0),
+    ("Mouse.removeListener", 1, 1),
+    ("Mouse.show", 0, 0),
+    ("Number", 0, 1),
+    ("Selection.getFocus", 0, 0),
+    ("Selection.setFocus", 1, 1),
+    ("Selection.setSelection", 2, 2),
+    ("SharedObject.getLocal", 1, 3),
+    ("Stage.addListener", 1, 1),
+    ("Stage.removeListener", 1, 1),
+    ("String", 0, 1),
+    ("System.setClipboard", 1, 1),
+    ("System.showSettings", 0, 1),
+    ("clearInterval", 1, 1),
+    ("clearTimeout", 1, 1),
+    ("escape", 1, 1),
+    ("fscommand", 1, 2),
+    ("getURL", 1, 3),
+    ("getVersion", 0, 0),
+    ("isFinite", 1, 1),
+    ("isNaN", 1, 1),
+    ("parseFloat", 1, 1),
+    ("parseInt", 1, 2),
+    // `setInterval(function, interval, ...)` or `setInterval(object, name, interval, ...)`.
+    ("setInterval", 2, usize::MAX),
+    ("setTimeout", 2, usize::MAX),
+    ("unescape", 1, 1),
+    ("updateAfterEvent", 0, 0),
+];
+
+// The least and most numbers of arguments of the function or method `path`, if known.
+pub(crate) fn arity(path: &str) -> Option<(usize, usize)> {
+    SIGNATURES
+        .iter()
+        .find(|(name, _, _)| *name == path)
+        .map(|(_, min, max)| (*min, *max))
+}
+
+// The message about a call of `path` passing `count` arguments, rather than `min` to `max`.
+pub(crate) fn arity_message(path: &str, min: usize, max: usize, count: usize) -> String {
+    let expected = if min == max {
+        min.to_string()
+    } else if max == usize::MAX {
+        format!("at least {}", min)
+    } else {
+        format!("{} to {}", min, max)
+    };
+    format!(
+        "Expected {} argument(s) for '{}', got {}",
+        expected, path, count
+    )
+}
@@ -0,0 +1,30 @@
+// The text of a source file, held once for the whole compilation since tokens borrow from it.
+// Files are read straight into it, without copies along the way.
+
+use std::io::Read;
+use std::path::Path;
+
+pub struct SourceText {
+    text: String,
+}
+
+impl SourceText {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // Reserve the size of the file up front, rather than growing the text while reading.
+        let size = file.metadata()?.len();
+        let mut text = String::with_capacity(size.try_into().unwrap_or(0));
+        std::io::BufReader::new(file).read_to_string(&mut text)?;
+        Ok(Self { text })
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Self { text })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
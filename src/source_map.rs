@@ -0,0 +1,245 @@
+// A map from the offsets of actions in a compiled SWF file back to the source positions of the
+// statements that they come from, and the symbols of the functions that they define, for debuggers
+// and crash reports.
+
+use crate::json;
+use std::ops::Range;
+
+// The source position of the statement that the actions from an offset on come from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    // Offset in the action data of the block, which includes the bodies of its functions.
+    pub offset: usize,
+    // Index of the imported file among the files of the map, or `None` for the compiled source.
+    pub file: Option<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+// What a debugger needs to know about a defined function to show it and its variables by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionSymbols {
+    // The name of the function, qualified by that of its class if a method, or `None` if anonymous.
+    pub name: Option<String>,
+    // Index of the imported file that defines the function, or `None` for the compiled source.
+    pub file: Option<usize>,
+    // The source position of the `function` keyword.
+    pub line: usize,
+    pub column: usize,
+    // Offset of the action defining the function in the action data of the block.
+    pub offset: usize,
+    // The range of the body in the action data of the block, which its variables are scoped to.
+    pub body: Range<usize>,
+    pub parameters: Vec<String>,
+    // Parameters and variables kept in registers, including preloaded ones such as `this`, along
+    // with their registers.
+    pub registers: Vec<(String, u8)>,
+    // Local variables looked up by name rather than kept in registers.
+    pub variables: Vec<String>,
+}
+
+// The mappings and function symbols of assembled actions, by offsets in their action data.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DebugInfo {
+    // In order of their offsets.
+    pub mappings: Vec<Mapping>,
+    // In order of their offsets, except for those whose actions are not laid out yet.
+    pub functions: Vec<FunctionSymbols>,
+}
+
+impl DebugInfo {
+    // Add `mapping`, replacing the last one if at the same offset, such as that of a statement
+    // without actions.
+    pub fn map(&mut self, mapping: Mapping) {
+        match self.mappings.last_mut() {
+            Some(last) if last.offset == mapping.offset => *last = mapping,
+            _ => self.mappings.push(mapping),
+        }
+    }
+
+    // Add the information about the actions in `range` of the action data of `other`, which are
+    // moved to `start`.
+    pub fn extend_moved(&mut self, other: &DebugInfo, range: &Range<usize>, start: usize) {
+        let move_offset = |offset: usize| offset - range.start + start;
+        let first = other
+            .mappings
+            .partition_point(|mapping| mapping.offset < range.start);
+        let last = other
+            .mappings
+            .partition_point(|mapping| mapping.offset < range.end);
+        for mapping in &other.mappings[first..last] {
+            self.map(Mapping {
+                offset: move_offset(mapping.offset),
+                ..mapping.clone()
+            });
+        }
+        let functions = other
+            .functions
+            .iter()
+            .filter(|function| range.contains(&function.offset));
+        self.functions
+            .extend(functions.map(|function| FunctionSymbols {
+                offset: move_offset(function.offset),
+                body: move_offset(function.body.start)..move_offset(function.body.end),
+                ..function.clone()
+            }));
+    }
+
+    // The information about actions from an imported file, whose index is `file`, so that it
+    // refers to that file rather than to the compiled source.
+    pub fn in_file(mut self, file: usize) -> Self {
+        for mapping in &mut self.mappings {
+            mapping.file = mapping.file.or(Some(file));
+        }
+        for function in &mut self.functions {
+            function.file = function.file.or(Some(file));
+        }
+        self
+    }
+}
+
+// The actions of a tag, or of a handler within a tag.
+#[derive(Clone, Debug)]
+pub struct ActionBlock {
+    // The tag holding the actions, such as "DoAction".
+    pub tag: &'static str,
+    // The character that the actions belong to, such as the sprite of a `DoInitAction`.
+    pub id: Option<u16>,
+    // Index of the handler among those of the character, for button and clip events, or of the
+    // frame, counting from 0, for the actions of a frame.
+    pub index: Option<usize>,
+    // Mappings in order of their offsets.
+    pub mappings: Vec<Mapping>,
+    // The functions that the actions define, in order of their offsets.
+    pub functions: Vec<FunctionSymbols>,
+}
+
+impl ActionBlock {
+    pub(crate) fn new(
+        tag: &'static str,
+        id: Option<u16>,
+        index: Option<usize>,
+        debug: DebugInfo,
+    ) -> Self {
+        Self {
+            tag,
+            id,
+            index,
+            mappings: debug.mappings,
+            functions: debug.functions,
+        }
+    }
+
+    // The tag, the character and the handler of the block as JSON fields.
+    fn json_fields(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or("null".to_string());
+        format!(
+            r#""tag":{},"id":{},"index":{}"#,
+            json::string(self.tag),
+            optional(self.id.map(|id| id.to_string())),
+            optional(self.index.map(|index| index.to_string())),
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    // The imported files that mappings refer to.
+    pub files: Vec<std::path::PathBuf>,
+    // The blocks of actions, in the order of their tags.
+    pub blocks: Vec<ActionBlock>,
+}
+
+impl SourceMap {
+    // The map as JSON, with the compiled source named `source` as the first file. Each mapping is
+    // an array of the offset, the index of the file, the line and the column.
+    pub fn to_json(&self, source: &str) -> String {
+        let blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let mappings: Vec<_> = block
+                    .mappings
+                    .iter()
+                    .map(|mapping| {
+                        let file = mapping.file.map_or(0, |file| file + 1);
+                        format!(
+                            "[{},{},{},{}]",
+                            mapping.offset, file, mapping.line, mapping.column
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"{{{},"mappings":[{}]}}"#,
+                    block.json_fields(),
+                    mappings.join(",")
+                )
+            })
+            .collect();
+        self.json(source, &blocks)
+    }
+
+    // The symbols of the functions as JSON, with the compiled source named `source` as the first
+    // file. Each block of actions lists the functions that it defines, each with:
+    //
+    // - "name": the name of the function, qualified by that of its class if a method, or null;
+    // - "file", "line" and "column": the source position of its `function` keyword;
+    // - "offset": the offset of the action defining it in the action data of the block;
+    // - "body": the start and the end of its body in the action data of the block, the scope of
+    //   its variables, which includes the bodies of nested functions;
+    // - "parameters": the names of its parameters, in order;
+    // - "registers": the names of the variables kept in registers, each with its register;
+    // - "variables": the names of the local variables looked up by name.
+    pub fn symbols_to_json(&self, source: &str) -> String {
+        let blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let functions: Vec<_> = block.functions.iter().map(function_json).collect();
+                format!(
+                    r#"{{{},"functions":[{}]}}"#,
+                    block.json_fields(),
+                    functions.join(",")
+                )
+            })
+            .collect();
+        self.json(source, &blocks)
+    }
+
+    fn json(&self, source: &str, blocks: &[String]) -> String {
+        let files: Vec<_> = std::iter::once(source.to_string())
+            .chain(self.files.iter().map(|file| file.display().to_string()))
+            .map(|file| json::string(&file))
+            .collect();
+        format!(
+            "{{\"version\":1,\"files\":[{}],\"blocks\":[{}]}}\n",
+            files.join(","),
+            blocks.join(",")
+        )
+    }
+}
+
+fn function_json(function: &FunctionSymbols) -> String {
+    let names = |names: &[String]| {
+        let names: Vec<_> = names.iter().map(|name| json::string(name)).collect();
+        names.join(",")
+    };
+    let registers: Vec<_> = function
+        .registers
+        .iter()
+        .map(|(name, register)| format!("[{},{}]", json::string(name), register))
+        .collect();
+    format!(
+        r#"{{"name":{},"file":{},"line":{},"column":{},"offset":{},"body":[{},{}],"parameters":[{}],"registers":[{}],"variables":[{}]}}"#,
+        json::optional_string(function.name.as_deref()),
+        function.file.map_or(0, |file| file + 1),
+        function.line,
+        function.column,
+        function.offset,
+        function.body.start,
+        function.body.end,
+        names(&function.parameters),
+        registers.join(","),
+        names(&function.variables)
+    )
+}
@@ -0,0 +1,351 @@
+// A check that code leaves the stack as each statement found it, by following the values that its
+// actions push and pop along every branch. The compiler never generates such code, so failures
+// are internal errors.
+
+use crate::assembler::{Code, Instruction};
+use crate::cfg::Cfg;
+use crate::scanner::{CompileError, ErrorKind};
+use std::collections::VecDeque;
+
+// A value on the stack, known as far as actions that take counts need it.
+#[derive(Clone, Copy, PartialEq)]
+enum Slot {
+    Value,
+    Int(i32),
+    Bool(bool),
+    // The names of an enumeration in progress, which its loop leaves on the stack between the
+    // statements of its body.
+    Enumeration,
+}
+
+#[derive(Clone)]
+struct State {
+    stack: Vec<Slot>,
+    // The source position of the statement that execution is in.
+    statement: (usize, usize),
+}
+
+impl State {
+    fn pop(&mut self) -> Result<Slot, &'static str> {
+        self.stack
+            .pop()
+            .ok_or("takes values from the stack that it did not push")
+    }
+
+    fn pop_count(&mut self) -> Result<usize, &'static str> {
+        match self.pop()? {
+            Slot::Int(count) => usize::try_from(count).map_err(|_| "pops a negative count"),
+            _ => Err("pops a count that is not known"),
+        }
+    }
+
+    fn pop_values(&mut self, count: usize) -> Result<(), &'static str> {
+        for _ in 0..count {
+            self.pop()?;
+        }
+        Ok(())
+    }
+
+    // Apply an action that pops `pops` values and then pushes `pushes` others.
+    fn apply(&mut self, pops: usize, pushes: usize) -> Result<(), &'static str> {
+        self.pop_values(pops)?;
+        self.stack.extend(std::iter::repeat_n(Slot::Value, pushes));
+        Ok(())
+    }
+
+    // Whether the statement that execution is in left values on the stack.
+    fn is_balanced(&self) -> bool {
+        self.stack.iter().all(|slot| *slot == Slot::Enumeration)
+    }
+
+    fn action(&mut self, action: &swf::avm1::types::Action) -> Result<(), &'static str> {
+        use swf::avm1::types::Action;
+        match action {
+            Action::Push(push) => {
+                let slots = push.values.iter().map(|value| match value {
+                    swf::avm1::types::Value::Int(value) => Slot::Int(*value),
+                    swf::avm1::types::Value::Bool(value) => Slot::Bool(*value),
+                    _ => Slot::Value,
+                });
+                self.stack.extend(slots);
+            }
+            Action::PushDuplicate => {
+                let slot = match *self.stack.last().ok_or("duplicates an empty stack")? {
+                    // The copy is the next name only.
+                    Slot::Enumeration => Slot::Value,
+                    slot => slot,
+                };
+                self.stack.push(slot);
+            }
+            Action::StackSwap => {
+                let len = self.stack.len();
+                if len < 2 {
+                    return Err("swaps fewer than two values");
+                }
+                self.stack.swap(len - 1, len - 2);
+            }
+            Action::StoreRegister(_) => {
+                self.stack.last().ok_or("stores from an empty stack")?;
+            }
+            Action::Enumerate | Action::Enumerate2 => {
+                self.pop()?;
+                self.stack.push(Slot::Enumeration);
+            }
+            Action::CallFunction | Action::NewObject => {
+                self.pop()?;
+                let count = self.pop_count()?;
+                self.apply(count, 1)?;
+            }
+            Action::CallMethod | Action::NewMethod => {
+                self.pop_values(2)?;
+                let count = self.pop_count()?;
+                self.apply(count, 1)?;
+            }
+            Action::InitArray => {
+                let count = self.pop_count()?;
+                self.apply(count, 1)?;
+            }
+            Action::InitObject => {
+                let count = self.pop_count()?;
+                self.apply(2 * count, 1)?;
+            }
+            Action::StartDrag => {
+                self.pop_values(2)?;
+                match self.pop()? {
+                    Slot::Bool(true) => self.pop_values(4)?,
+                    Slot::Bool(false) => {}
+                    _ => return Err("pops a constraint flag that is not known"),
+                }
+            }
+            Action::DefineFunction(function) => {
+                self.apply(0, usize::from(function.name.is_empty()))?;
+            }
+            Action::DefineFunction2(function) => {
+                self.apply(0, usize::from(function.name.is_empty()))?;
+            }
+            Action::Add
+            | Action::Add2
+            | Action::And
+            | Action::BitAnd
+            | Action::BitLShift
+            | Action::BitOr
+            | Action::BitRShift
+            | Action::BitURShift
+            | Action::BitXor
+            | Action::Delete
+            | Action::Divide
+            | Action::Equals
+            | Action::Equals2
+            | Action::GetMember
+            | Action::GetProperty
+            | Action::Greater
+            | Action::InstanceOf
+            | Action::Less
+            | Action::Less2
+            | Action::Modulo
+            | Action::Multiply
+            | Action::Or
+            | Action::StrictEquals
+            | Action::StringAdd
+            | Action::StringEquals
+            | Action::StringGreater
+            | Action::StringLess
+            | Action::Subtract => self.apply(2, 1)?,
+            Action::AsciiToChar
+            | Action::CharToAscii
+            | Action::Decrement
+            | Action::Delete2
+            | Action::GetVariable
+            | Action::Increment
+            | Action::MBAsciiToChar
+            | Action::MBCharToAscii
+            | Action::MBStringLength
+            | Action::Not
+            | Action::RandomNumber
+            | Action::StringLength
+            | Action::TargetPath
+            | Action::ToInteger
+            | Action::ToNumber
+            | Action::ToString
+            | Action::TypeOf => self.apply(1, 1)?,
+            Action::MBStringExtract | Action::StringExtract => self.apply(3, 1)?,
+            Action::GetTime => self.apply(0, 1)?,
+            Action::CloneSprite | Action::SetMember | Action::SetProperty => self.apply(3, 0)?,
+            Action::DefineLocal
+            | Action::Extends
+            | Action::GetUrl2(_)
+            | Action::SetVariable => self.apply(2, 0)?,
+            Action::Call
+            | Action::DefineLocal2
+            | Action::GotoFrame2(_)
+            | Action::Pop
+            | Action::RemoveSprite
+            | Action::Return
+            | Action::SetTarget2
+            | Action::Throw
+            | Action::Trace => self.apply(1, 0)?,
+            Action::ConstantPool(_)
+            | Action::EndDrag
+            | Action::GetUrl(_)
+            | Action::GotoFrame(_)
+            | Action::GotoLabel(_)
+            | Action::NextFrame
+            | Action::Play
+            | Action::PreviousFrame
+            | Action::SetTarget(_)
+            | Action::Stop
+            | Action::StopSounds
+            | Action::ToggleQuality
+            // The bodies of a `Try` are checked as code of their own.
+            | Action::Try(_) => {}
+            _ => return Err("uses an action that the check does not know"),
+        }
+        Ok(())
+    }
+
+    // Follow the encoded actions of `action_data`.
+    fn actions(&mut self, action_data: &[u8]) -> Result<(), &'static str> {
+        let start = self.stack.clone();
+        let mut reader = swf::avm1::read::Reader::new(action_data, 0);
+        while !reader.get_ref().is_empty() {
+            let action = reader
+                .read_action()
+                .map_err(|_| "has actions that cannot be read")?;
+            if let swf::avm1::types::Action::Jump(_) | swf::avm1::types::Action::If(_) = action {
+                // Only imported code encodes its own branches. It was checked when compiled, and
+                // leaves the stack as it found it.
+                self.stack = start;
+                return Ok(());
+            }
+            self.action(&action)?;
+        }
+        Ok(())
+    }
+}
+
+fn error(statement: (usize, usize), message: &str) -> CompileError {
+    let (line, column) = statement;
+    CompileError {
+        kind: ErrorKind::Internal,
+        message: format!("Internal error: Statement {}", message),
+        line,
+        column,
+    }
+}
+
+// Check that every statement of `code` leaves the stack as it found it, apart from the names
+// that enclosing `for..in` loops enumerate, and that branches agree on the values on the stack.
+pub fn check(code: &Code, cfg: &Cfg) -> Result<(), CompileError> {
+    let instructions = code.instructions();
+    let action_data = code.action_data();
+
+    let mut states: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+    let mut pending = VecDeque::new();
+    if !cfg.blocks.is_empty() {
+        states[0] = Some(State {
+            stack: Vec::new(),
+            statement: (0, 0),
+        });
+        pending.push_back(0);
+    }
+
+    while let Some(i) = pending.pop_front() {
+        let block = &cfg.blocks[i];
+        let mut state = states[i].clone().unwrap();
+        for instruction in &instructions[block.start..block.end] {
+            let result = match instruction {
+                Instruction::Statement { line, column } => {
+                    if !state.is_balanced() {
+                        return Err(error(state.statement, "leaves values on the stack"));
+                    }
+                    state.statement = (*line, *column);
+                    Ok(())
+                }
+                Instruction::Actions(range) => state.actions(&action_data[range.clone()]),
+                Instruction::Function(range) => state.actions(&action_data[range.clone()]),
+                Instruction::If(_) => state.pop().map(drop),
+                Instruction::Enumerated(count) if *count > 0 => {
+                    let count = count.unsigned_abs();
+                    state
+                        .stack
+                        .extend(std::iter::repeat_n(Slot::Enumeration, count));
+                    Ok(())
+                }
+                Instruction::Enumerated(count) => {
+                    (0..count.unsigned_abs()).try_for_each(|_| match state.pop()? {
+                        Slot::Enumeration => Ok(()),
+                        _ => Err("ends an enumeration that is not on the stack"),
+                    })
+                }
+                Instruction::Jump(_)
+                | Instruction::Label(_)
+                | Instruction::Exit
+                | Instruction::EndFunction => Ok(()),
+            };
+            result.map_err(|message| error(state.statement, message))?;
+        }
+
+        // The end of the code or of a function body, which execution returns from.
+        let last = &instructions[block.end - 1];
+        let is_end = matches!(
+            instructions.get(block.end),
+            None | Some(Instruction::EndFunction)
+        ) && !matches!(
+            last,
+            Instruction::Jump(_) | Instruction::Exit | Instruction::Function(_)
+        );
+        if is_end && !state.stack.is_empty() {
+            return Err(error(state.statement, "leaves values on the stack"));
+        }
+
+        for (j, &successor) in block.successors.iter().enumerate() {
+            let successor_state = match last {
+                // Function bodies start with a stack of their own, unless empty.
+                Instruction::Function(_) if j == 0 => {
+                    if let Some(Instruction::EndFunction) = instructions.get(block.end) {
+                        continue;
+                    }
+                    State {
+                        stack: Vec::new(),
+                        statement: state.statement,
+                    }
+                }
+                // Execution does not continue past the end of a function body.
+                _ if is_end && successor == i + 1 => continue,
+                _ => state.clone(),
+            };
+            match &mut states[successor] {
+                Some(existing) => {
+                    let is_same_depth = existing.stack.len() == successor_state.stack.len()
+                        && existing
+                            .stack
+                            .iter()
+                            .zip(&successor_state.stack)
+                            .all(|(a, b)| (*a == Slot::Enumeration) == (*b == Slot::Enumeration));
+                    if !is_same_depth {
+                        return Err(error(
+                            successor_state.statement,
+                            "branches with different values on the stack",
+                        ));
+                    }
+                    // Values that branches disagree on are no longer known.
+                    let mut changed = false;
+                    for (a, b) in existing.stack.iter_mut().zip(&successor_state.stack) {
+                        if *a != *b && *a != Slot::Value {
+                            *a = Slot::Value;
+                            changed = true;
+                        }
+                    }
+                    if changed {
+                        pending.push_back(successor);
+                    }
+                }
+                None => {
+                    states[successor] = Some(successor_state);
+                    pending.push_back(successor);
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+// Statistics about compiled code, telling where the bytes of a SWF file go.
+
+use crate::source_map::SourceMap;
+use crate::timing::PhaseTime;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default)]
+pub struct CompileStats {
+    // Size of the written SWF file.
+    pub swf_size: usize,
+    // Size of all action data, including that of handlers and `#initclip` blocks.
+    pub action_data_size: usize,
+    // Number of strings in constant pools.
+    pub constant_pool_size: usize,
+    // The defined functions, in order of their definitions.
+    pub functions: Vec<FunctionStats>,
+    // Number of actions of each kind, by name.
+    pub actions: BTreeMap<String, usize>,
+    // Time spent in each phase of compilation, in order of their starts.
+    pub phases: Vec<PhaseTime>,
+    // The source positions that the actions of each tag come from.
+    pub source_map: SourceMap,
+}
+
+#[derive(Debug)]
+pub struct FunctionStats {
+    // The name of the function, or that of the variable or member it is assigned to if anonymous.
+    pub name: Option<String>,
+    // Size of the definition of the function, including its body.
+    pub size: usize,
+}
+
+impl CompileStats {
+    pub(crate) fn add_action_data(&mut self, action_data: &[u8]) {
+        self.action_data_size += action_data.len();
+        self.count(action_data);
+    }
+
+    fn count(&mut self, action_data: &[u8]) {
+        let mut reader = swf::avm1::read::Reader::new(action_data, 0);
+        // The last string pushed, which names the anonymous function that follows it.
+        let mut last_string = None;
+        loop {
+            let remaining = reader.get_ref().len();
+            if remaining == 0 {
+                break;
+            }
+            let Ok(action) = reader.read_action() else {
+                break;
+            };
+            let size = remaining - reader.get_ref().len();
+
+            // The name of the kind of action is that of its variant.
+            let debug = format!("{:?}", action);
+            let kind = debug.split(['(', ' ']).next().unwrap_or_default();
+            *self.actions.entry(kind.to_string()).or_default() += 1;
+
+            let function = match &action {
+                swf::avm1::types::Action::Push(push) => {
+                    last_string = match push.values.last() {
+                        Some(swf::avm1::types::Value::Str(value)) => {
+                            Some(String::from_utf8_lossy(value.as_bytes()).into_owned())
+                        }
+                        _ => None,
+                    };
+                    continue;
+                }
+                swf::avm1::types::Action::ConstantPool(pool) => {
+                    self.constant_pool_size += pool.strings.len();
+                    None
+                }
+                swf::avm1::types::Action::DefineFunction(function) => {
+                    Some((function.name, function.actions))
+                }
+                swf::avm1::types::Action::DefineFunction2(function) => {
+                    Some((function.name, function.actions))
+                }
+                swf::avm1::types::Action::Try(try_action) => {
+                    self.count(try_action.try_body);
+                    if let Some((_, catch_body)) = &try_action.catch_body {
+                        self.count(catch_body);
+                    }
+                    if let Some(finally_body) = try_action.finally_body {
+                        self.count(finally_body);
+                    }
+                    None
+                }
+                _ => None,
+            };
+            if let Some((name, body)) = function {
+                let name = if name.is_empty() {
+                    last_string.take()
+                } else {
+                    Some(String::from_utf8_lossy(name.as_bytes()).into_owned())
+                };
+                self.functions.push(FunctionStats { name, size });
+                self.count(body);
+            }
+            last_string = None;
+        }
+    }
+}
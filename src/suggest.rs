@@ -0,0 +1,58 @@
+// Suggestions of what was likely meant by a misspelled name, such as a keyword or a property.
+
+// The candidate closest to `name`, if any is close enough to likely be what was meant: one edit
+// away for each three characters. Names differing only in case are the closest.
+pub(crate) fn closest<'c>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Option<&'c str> {
+    let lowercase = name.to_lowercase();
+    let max_distance = name.chars().count() / 3;
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(&lowercase, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// `message` followed by the suggestion of the candidate closest to `name`, if any.
+pub(crate) fn did_you_mean<'c>(
+    message: String,
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> String {
+    match closest(name, candidates) {
+        Some(candidate) => format!("{}, did you mean '{}'?", message, candidate),
+        None => message,
+    }
+}
+
+// The edit distance between `a` and `b`: the number of characters to insert, delete or replace,
+// and of adjacent characters to swap, to turn one into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // The distances between the prefixes of `a` and those of `b`.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = replace
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
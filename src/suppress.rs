@@ -0,0 +1,159 @@
+// Comments such as `// asc-ignore(unreachable)` that suppress warnings, so that a source can adopt
+// lints bit by bit. A comment at the start of a block suppresses warnings in the whole block, any
+// other one those on the line of the code after it. Without a list of lints, it suppresses all.
+
+use crate::diagnostic::{Diagnostic, Lint, Severity};
+use crate::scanner::{Scanner, TokenKind};
+use crate::suggest;
+
+struct Suppression {
+    // The suppressed lints, or `None` for all of them.
+    lints: Option<Vec<Lint>>,
+    start: usize,
+    end: usize,
+}
+
+impl Suppression {
+    fn suppresses(&self, diagnostic: &Diagnostic) -> bool {
+        let Some(lint) = diagnostic.lint else {
+            return false;
+        };
+        (self.start..=self.end).contains(&diagnostic.line)
+            && self
+                .lints
+                .as_ref()
+                .is_none_or(|lints| lints.contains(&lint))
+    }
+}
+
+// An unknown lint named by a suppression.
+struct UnknownLint<'a> {
+    name: &'a str,
+    line: usize,
+    column: usize,
+}
+
+// The lints that a comment suppresses, if it is a suppression, along with the names in it that are
+// not those of lints. The `//` of the comment is at `line` and `column`.
+fn parse(
+    comment: &str,
+    line: usize,
+    column: usize,
+) -> Option<(Option<Vec<Lint>>, Vec<UnknownLint<'_>>)> {
+    const PREFIX: &str = "asc-ignore";
+    let rest = comment.trim().strip_prefix(PREFIX)?;
+    if rest.is_empty() {
+        return Some((None, Vec::new()));
+    }
+    let names = rest.strip_prefix('(')?.strip_suffix(')')?;
+    let mut lints = Vec::new();
+    let mut unknown = Vec::new();
+    let mut offset = comment.len() - comment.trim_start().len() + PREFIX.len() + 1;
+    for name in names.split(',') {
+        let trimmed = name.trim();
+        match Lint::from_name(trimmed) {
+            Some(lint) => lints.push(lint),
+            None if trimmed.is_empty() => {}
+            None => {
+                let offset = offset + name.len() - name.trim_start().len();
+                unknown.push(UnknownLint {
+                    name: trimmed,
+                    line,
+                    // Past the `//`.
+                    column: column + 2 + comment[..offset].chars().count(),
+                });
+            }
+        }
+        offset += name.len() + 1;
+    }
+    Some((Some(lints), unknown))
+}
+
+fn suppressions(source: &str) -> (Vec<Suppression>, Vec<UnknownLint<'_>>) {
+    let mut suppressions = Vec::new();
+    let mut unknown_lints = Vec::new();
+    let mut scanner = Scanner::with_comments(source);
+    // For each open block, the suppression at its start.
+    let mut blocks: Vec<Option<usize>> = Vec::new();
+    let mut previous = TokenKind::Eof;
+    // Invalid tokens were already reported when parsing, so stop at them.
+    while let Ok(token) = scanner.read_token() {
+        for (line, column, comment) in scanner.take_comments() {
+            let Some((lints, unknown)) = parse(comment, line, column) else {
+                continue;
+            };
+            unknown_lints.extend(unknown);
+            let start = if previous == TokenKind::LeftBrace {
+                if let Some(block) = blocks.last_mut() {
+                    *block = Some(suppressions.len());
+                }
+                line
+            } else {
+                token.line
+            };
+            suppressions.push(Suppression {
+                lints,
+                start,
+                end: token.line,
+            });
+        }
+        match token.kind {
+            TokenKind::LeftBrace => blocks.push(None),
+            TokenKind::RightBrace => {
+                if let Some(Some(index)) = blocks.pop() {
+                    suppressions[index].end = token.line;
+                }
+            }
+            TokenKind::Eof => break,
+            _ => {}
+        }
+        previous = token.kind;
+    }
+    // Blocks left open by syntax errors last until the end.
+    for index in blocks.into_iter().flatten() {
+        suppressions[index].end = usize::MAX;
+    }
+    (suppressions, unknown_lints)
+}
+
+// Remove the warnings about `source` that its comments suppress, and warn about the names in them
+// that are not those of lints with `unknown_lint`, the severity of such warnings if enabled.
+pub(crate) fn suppress(
+    diagnostics: &mut Vec<Diagnostic>,
+    source: &str,
+    unknown_lint: Option<Severity>,
+) {
+    let has_warnings = diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.lint.is_some());
+    if !has_warnings && (unknown_lint.is_none() || !source.contains("asc-ignore")) {
+        return;
+    }
+    let (suppressions, unknown_lints) = suppressions(source);
+    diagnostics.retain(|diagnostic| {
+        !suppressions
+            .iter()
+            .any(|suppression| suppression.suppresses(diagnostic))
+    });
+
+    let Some(severity) = unknown_lint else {
+        return;
+    };
+    for unknown in unknown_lints {
+        let names = Lint::ALL.iter().map(|lint| lint.name());
+        let message = match suggest::closest(unknown.name, names) {
+            Some(lint) => format!("Unknown lint '{}', did you mean '{}'?", unknown.name, lint),
+            None => format!("Unknown lint '{}'", unknown.name),
+        };
+        diagnostics.push(Diagnostic {
+            severity,
+            kind: None,
+            lint: Some(Lint::UnknownLint),
+            message,
+            line: unknown.line,
+            column: unknown.column,
+            end: Some((unknown.line, unknown.column + unknown.name.chars().count())),
+            notes: Vec::new(),
+        });
+    }
+}
@@ -0,0 +1,85 @@
+// Time spent in each phase of compilation, to find out what makes compiling slow.
+
+use crate::json;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct PhaseTime {
+    // Name of the phase, such as "Parsing".
+    pub name: &'static str,
+    // The imported file that the phase processed, or `None` for the compiled source.
+    pub file: Option<std::path::PathBuf>,
+    // Time from the start of compilation to the start of the phase.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+pub(crate) struct Timings {
+    epoch: Instant,
+    pub phases: Vec<PhaseTime>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    // Record a phase of `file` that started at `start` and lasted until now.
+    pub fn record(&mut self, name: &'static str, file: Option<&std::path::Path>, start: Instant) {
+        self.record_span(name, file, start, start.elapsed());
+    }
+
+    pub fn record_span(
+        &mut self,
+        name: &'static str,
+        file: Option<&std::path::Path>,
+        start: Instant,
+        duration: Duration,
+    ) {
+        self.phases.push(PhaseTime {
+            name,
+            file: file.map(std::path::Path::to_path_buf),
+            start: start - self.epoch,
+            duration,
+        });
+    }
+
+    // Record parsing that started at `start`, of which scanning took `scan_time`. Scanning
+    // interleaves with parsing, so its time is summed up and put before that of parsing.
+    pub fn record_parsing(
+        &mut self,
+        file: Option<&std::path::Path>,
+        start: Instant,
+        scan_time: Duration,
+    ) {
+        let duration = start.elapsed();
+        let scan_time = scan_time.min(duration);
+        self.record_span("Scanning", file, start, scan_time);
+        self.record_span("Parsing", file, start + scan_time, duration - scan_time);
+    }
+}
+
+// The phases as JSON in the trace event format of Chrome, for `chrome://tracing` and similar
+// viewers.
+pub fn chrome_trace(phases: &[PhaseTime]) -> String {
+    let events: Vec<_> = phases
+        .iter()
+        .map(|phase| {
+            let file = match &phase.file {
+                Some(file) => json::string(&file.display().to_string()),
+                None => "null".to_string(),
+            };
+            format!(
+                r#"{{"name":{},"cat":"asc","ph":"X","ts":{},"dur":{},"pid":1,"tid":1,"args":{{"file":{}}}}}"#,
+                json::string(phase.name),
+                phase.start.as_micros(),
+                phase.duration.as_micros(),
+                file
+            )
+        })
+        .collect();
+    format!("{{\"traceEvents\":[{}]}}\n", events.join(","))
+}
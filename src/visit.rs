@@ -0,0 +1,229 @@
+// A mutable traversal of the syntax tree, to transform programs between parsing and code generation.
+//
+// Each `visit_*` method walks the children of its node by default. Override a method to transform
+// a node, and call the matching `walk_*` function from it to keep visiting the children.
+
+use crate::ast::{
+    Class, ClassMemberKind, Expression, Function, Handler, InitClip, Item, Program, Sprite,
+    Statement,
+};
+
+pub trait VisitMut {
+    fn visit_program(&mut self, program: &mut Program<'_>) {
+        walk_program(self, program);
+    }
+
+    fn visit_item(&mut self, item: &mut Item<'_>) {
+        walk_item(self, item);
+    }
+
+    fn visit_handler(&mut self, handler: &mut Handler<'_>) {
+        walk_handler(self, handler);
+    }
+
+    fn visit_init_clip(&mut self, init_clip: &mut InitClip<'_>) {
+        walk_init_clip(self, init_clip);
+    }
+
+    fn visit_sprite(&mut self, sprite: &mut Sprite<'_>) {
+        walk_sprite(self, sprite);
+    }
+
+    fn visit_statement(&mut self, statement: &mut Statement<'_>) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_function(&mut self, function: &mut Function<'_>) {
+        walk_function(self, function);
+    }
+
+    fn visit_class(&mut self, class: &mut Class<'_>) {
+        walk_class(self, class);
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression<'_>) {
+        walk_expression(self, expression);
+    }
+}
+
+pub fn walk_program<V: VisitMut + ?Sized>(visitor: &mut V, program: &mut Program<'_>) {
+    for item in &mut program.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: VisitMut + ?Sized>(visitor: &mut V, item: &mut Item<'_>) {
+    match item {
+        Item::Statement(statement) => visitor.visit_statement(statement),
+        Item::Handler(handler) => visitor.visit_handler(handler),
+        Item::InitClip(init_clip) => visitor.visit_init_clip(init_clip),
+        Item::Frame(_) | Item::Export(_) => {}
+        Item::Sprite(sprite) => visitor.visit_sprite(sprite),
+    }
+}
+
+pub fn walk_handler<V: VisitMut + ?Sized>(visitor: &mut V, handler: &mut Handler<'_>) {
+    walk_statements(visitor, &mut handler.body);
+}
+
+pub fn walk_init_clip<V: VisitMut + ?Sized>(visitor: &mut V, init_clip: &mut InitClip<'_>) {
+    walk_statements(visitor, &mut init_clip.body);
+}
+
+pub fn walk_sprite<V: VisitMut + ?Sized>(visitor: &mut V, sprite: &mut Sprite<'_>) {
+    walk_statements(visitor, &mut sprite.body);
+}
+
+fn walk_statements<V: VisitMut + ?Sized>(visitor: &mut V, statements: &mut [Statement<'_>]) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: VisitMut + ?Sized>(visitor: &mut V, statement: &mut Statement<'_>) {
+    match statement {
+        Statement::Empty
+        | Statement::Break { .. }
+        | Statement::Continue { .. }
+        | Statement::Intrinsic(_)
+        | Statement::Import(_) => {}
+        Statement::Expression(expression) => visitor.visit_expression(expression),
+        Statement::Block(statements) => walk_statements(visitor, statements),
+        Statement::Var { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expression(value);
+            }
+        }
+        Statement::Const { value, .. } => visitor.visit_expression(value),
+        Statement::Function(function) => visitor.visit_function(function),
+        Statement::If {
+            condition,
+            body,
+            else_body,
+            ..
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_statement(body);
+            if let Some(else_body) = else_body {
+                visitor.visit_statement(else_body);
+            }
+        }
+        Statement::ForIn { object, body, .. } => {
+            visitor.visit_expression(object);
+            visitor.visit_statement(body);
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_statement(body);
+        }
+        Statement::DoWhile {
+            body, condition, ..
+        } => {
+            visitor.visit_statement(body);
+            visitor.visit_expression(condition);
+        }
+        Statement::Labeled { body, .. } => visitor.visit_statement(body),
+        Statement::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            walk_statements(visitor, body);
+            if let Some(catch) = catch {
+                walk_statements(visitor, &mut catch.body);
+            }
+            if let Some(finally) = finally {
+                walk_statements(visitor, finally);
+            }
+        }
+        Statement::Throw { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expression(value);
+            }
+        }
+        Statement::TellTarget { target, body, .. } => {
+            visitor.visit_expression(target);
+            visitor.visit_statement(body);
+        }
+        Statement::Trace { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        Statement::Class(class) => visitor.visit_class(class),
+        Statement::Conditional { body, .. } => visitor.visit_statement(body),
+    }
+}
+
+pub fn walk_function<V: VisitMut + ?Sized>(visitor: &mut V, function: &mut Function<'_>) {
+    walk_statements(visitor, &mut function.body);
+}
+
+pub fn walk_class<V: VisitMut + ?Sized>(visitor: &mut V, class: &mut Class<'_>) {
+    for member in &mut class.members {
+        match &mut member.kind {
+            ClassMemberKind::Var { value, .. } => {
+                if let Some(value) = value {
+                    visitor.visit_expression(value);
+                }
+            }
+            ClassMemberKind::Method { function, .. } => visitor.visit_function(function),
+        }
+    }
+}
+
+pub fn walk_expression<V: VisitMut + ?Sized>(visitor: &mut V, expression: &mut Expression<'_>) {
+    match expression {
+        Expression::Literal(_)
+        | Expression::Identifier(_)
+        | Expression::Constant { .. }
+        | Expression::Prefix { .. } => {}
+        Expression::Grouping { expression, .. } => visitor.visit_expression(expression),
+        Expression::Array { elements, .. } => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::Object { properties, .. } => {
+            for (_, value) in properties {
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::Function(function) => visitor.visit_function(function),
+        Expression::Member { object, .. } => visitor.visit_expression(object),
+        Expression::Index { object, index } => {
+            visitor.visit_expression(object);
+            visitor.visit_expression(index);
+        }
+        Expression::Call {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expression(callee);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        Expression::New {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expression(callee);
+            for argument in arguments.iter_mut().flatten() {
+                visitor.visit_expression(argument);
+            }
+        }
+        Expression::Delete { target, .. } => visitor.visit_expression(target),
+        Expression::Unary { operand, .. } => visitor.visit_expression(operand),
+        Expression::Binary { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::Assign { target, value, .. } => {
+            visitor.visit_expression(target);
+            visitor.visit_expression(value);
+        }
+        Expression::Postfix { target, .. } => visitor.visit_expression(target),
+    }
+}